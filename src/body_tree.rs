@@ -0,0 +1,520 @@
+use crate::decoders::DecoderRegistry;
+use crate::gvariant;
+use ratatui::widgets::ListState;
+use zbus::zvariant::{Signature, Structure, Value};
+
+/// Arrays with more elements than this start collapsed.
+const LARGE_ARRAY_LEN: usize = 16;
+/// Bytes per hexdump line of a byte array.
+pub(crate) const HEXDUMP_WIDTH: usize = 16;
+
+/// A value in the body of a message, as shown in the details tree.
+pub struct BodyNode {
+    pub label: String,
+    /// Body path of the value, e.g. `arg1['Metadata'][0]`, usable as a filter field.
+    pub path: String,
+    pub value: Value<'static>,
+    /// Symbolic name of the value from a decoder.
+    pub decoded: Option<String>,
+    /// A line of the hexdump of a byte array, shown instead of the value.
+    pub hexdump: Option<String>,
+    pub children: Vec<BodyNode>,
+    pub expanded: bool,
+}
+
+impl BodyNode {
+    fn new(label: String, path: String, value: &Value<'static>, decoded: Option<String>) -> Self {
+        let value = unwrap_variants(value);
+        let children = match value {
+            Value::Array(array) if array.element_signature() == &Signature::U8 => {
+                let bytes: Vec<u8> = array
+                    .iter()
+                    .filter_map(|v| match v {
+                        Value::U8(b) => Some(*b),
+                        _ => None,
+                    })
+                    .collect();
+                bytes
+                    .chunks(HEXDUMP_WIDTH)
+                    .enumerate()
+                    .map(|(i, chunk)| BodyNode {
+                        label: format!("{:08x}", i * HEXDUMP_WIDTH),
+                        path: path.clone(),
+                        value: Value::from(chunk.to_vec()),
+                        decoded: None,
+                        hexdump: Some(hexdump(chunk)),
+                        children: Vec::new(),
+                        expanded: false,
+                    })
+                    .collect()
+            }
+            Value::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(i, v)| BodyNode::new(i.to_string(), format!("{}[{}]", path, i), v, None))
+                .collect(),
+            Value::Structure(structure) => structure
+                .fields()
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    BodyNode::new(format!("i_{}", i + 1), format!("{}[{}]", path, i), v, None)
+                })
+                .collect(),
+            Value::Dict(dict) => dict
+                .iter()
+                .map(|(k, v)| {
+                    let key = gvariant::format(k);
+                    BodyNode::new(key_text(k), format!("{}[{}]", path, key), v, None)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let expanded = !matches!(value, Value::Array(array) if array.len() > LARGE_ARRAY_LEN);
+
+        BodyNode {
+            label,
+            path,
+            value: value.clone(),
+            decoded,
+            hexdump: None,
+            children,
+            expanded,
+        }
+    }
+
+    pub fn is_container(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// The signature of the value, unless the label already ends with it.
+    pub fn type_text(&self) -> Option<String> {
+        if self.hexdump.is_some() {
+            return None;
+        }
+        let signature = self.value.value_signature().to_string();
+        (!self.label.ends_with(&format!("({})", signature))).then_some(signature)
+    }
+
+    /// The value on one line: the value itself for basic types, a count for containers.
+    pub fn value_text(&self) -> String {
+        if let Some(hexdump) = &self.hexdump {
+            return hexdump.clone();
+        }
+        match &self.value {
+            Value::Array(array) if array.element_signature() == &Signature::U8 => {
+                format!("{} bytes", array.len())
+            }
+            Value::Array(array) => format!("{} items", array.len()),
+            Value::Dict(dict) => format!("{} entries", dict.iter().count()),
+            Value::Structure(structure) => format!("{} fields", structure.fields().len()),
+            value => gvariant::format(value),
+        }
+    }
+
+    /// The whole subtree as indented text, expanded regardless of what is collapsed on screen.
+    pub fn to_text(&self) -> String {
+        fn write(node: &BodyNode, depth: usize, out: &mut String) {
+            out.push_str(&"  ".repeat(depth));
+            out.push_str(&node.label);
+            if let Some(signature) = node.type_text() {
+                out.push_str(&format!(" [{}]", signature));
+            }
+            if node.hexdump.is_some() || !node.is_container() {
+                out.push_str(": ");
+                out.push_str(&node.value_text());
+            }
+            if let Some(decoded) = &node.decoded {
+                out.push_str(&format!(" ({})", decoded));
+            }
+            out.push('\n');
+            for child in &node.children {
+                write(child, depth + 1, out);
+            }
+        }
+
+        let mut out = String::new();
+        write(self, 0, &mut out);
+        out
+    }
+}
+
+/// Navigable tree of the arguments of a message for the details view.
+#[derive(Default)]
+pub struct BodyTree {
+    pub roots: Vec<BodyNode>,
+    /// Selection among the visible rows.
+    pub state: ListState,
+}
+
+impl BodyTree {
+    /// Builds the tree of the arguments of `message`, labelled with `labels` where given.
+    pub fn from_message(
+        message: &zbus::Message,
+        labels: &[String],
+        decoders: &DecoderRegistry,
+    ) -> Self {
+        let header = message.header();
+        let interface = header.interface().map(|i| i.as_str()).unwrap_or_default();
+        let member = header.member().map(|m| m.as_str()).unwrap_or_default();
+
+        let roots: Vec<BodyNode> = body_args(message)
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                let label = labels
+                    .get(i)
+                    .cloned()
+                    .unwrap_or_else(|| format!("i_{}", i + 1));
+                let decoded = decoders.decode_arg(interface, member, i, arg);
+                BodyNode::new(label, format!("arg{}", i), arg, decoded)
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        state.select((!roots.is_empty()).then_some(0));
+        BodyTree { roots, state }
+    }
+
+    /// Every argument as indented text, as copied from the details.
+    pub fn to_text(&self) -> String {
+        self.roots.iter().map(BodyNode::to_text).collect()
+    }
+
+    /// The rows currently shown, with their depth.
+    pub fn rows(&self) -> Vec<(usize, &BodyNode)> {
+        fn walk<'a>(nodes: &'a [BodyNode], depth: usize, rows: &mut Vec<(usize, &'a BodyNode)>) {
+            for node in nodes {
+                rows.push((depth, node));
+                if node.expanded {
+                    walk(&node.children, depth + 1, rows);
+                }
+            }
+        }
+
+        let mut rows = Vec::new();
+        walk(&self.roots, 0, &mut rows);
+        rows
+    }
+
+    pub fn selected(&self) -> Option<&BodyNode> {
+        let index = self.state.selected()?;
+        self.rows().get(index).map(|(_, node)| *node)
+    }
+
+    pub fn move_cursor(&mut self, delta: i32) {
+        let count = self.rows().len();
+        if count == 0 {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as i32;
+        let next = (current + delta).clamp(0, count as i32 - 1);
+        self.state.select(Some(next as usize));
+    }
+
+    /// Expands the selected node, or moves to its first child if it is already expanded.
+    pub fn expand(&mut self) {
+        let Some(index) = self.state.selected() else {
+            return;
+        };
+        if let Some(node) = self.selected_mut() {
+            if !node.is_container() {
+                return;
+            }
+            if node.expanded {
+                self.state.select(Some(index + 1));
+            } else {
+                node.expanded = true;
+            }
+        }
+    }
+
+    /// Collapses the selected node, or moves to its parent if there is nothing to collapse.
+    pub fn collapse(&mut self) {
+        let Some(path) = self.selected_index_path() else {
+            return;
+        };
+        if let Some(node) = self.selected_mut() {
+            if node.is_container() && node.expanded {
+                node.expanded = false;
+                return;
+            }
+        }
+        if path.len() > 1 {
+            let parent = &path[..path.len() - 1];
+            let index = self.visible_index_paths().iter().position(|p| p == parent);
+            self.state.select(index);
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if let Some(node) = self.selected_mut() {
+            if node.is_container() {
+                node.expanded = !node.expanded;
+            }
+        }
+    }
+
+    /// Child indexes from the roots down to each visible row.
+    fn visible_index_paths(&self) -> Vec<Vec<usize>> {
+        fn walk(nodes: &[BodyNode], prefix: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+            for (i, node) in nodes.iter().enumerate() {
+                prefix.push(i);
+                paths.push(prefix.clone());
+                if node.expanded {
+                    walk(&node.children, prefix, paths);
+                }
+                prefix.pop();
+            }
+        }
+
+        let mut paths = Vec::new();
+        walk(&self.roots, &mut Vec::new(), &mut paths);
+        paths
+    }
+
+    fn selected_index_path(&self) -> Option<Vec<usize>> {
+        let index = self.state.selected()?;
+        self.visible_index_paths().into_iter().nth(index)
+    }
+
+    fn selected_mut(&mut self) -> Option<&mut BodyNode> {
+        let path = self.selected_index_path()?;
+        let (first, rest) = path.split_first()?;
+        let mut node = self.roots.get_mut(*first)?;
+        for i in rest {
+            node = node.children.get_mut(*i)?;
+        }
+        Some(node)
+    }
+}
+
+/// The arguments of a message.
+pub fn body_args(message: &zbus::Message) -> Vec<Value<'static>> {
+    let body = message.body();
+    if body.signature() == &Signature::Unit {
+        return Vec::new();
+    }
+    // A body of a single value deserializes as a structure with one field as well
+    body.deserialize::<Structure>()
+        .map(Structure::into_fields)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|arg| arg.try_to_owned().ok().map(Value::from))
+        .collect()
+}
+
+/// Text a body path filter matches against: strings as they are, other values in GVariant text format.
+pub fn filter_text(value: &Value) -> String {
+    match unwrap_variants(value) {
+        Value::Str(s) => s.to_string(),
+        Value::ObjectPath(p) => p.to_string(),
+        Value::Signature(s) => s.to_string(),
+        value => gvariant::format(value),
+    }
+}
+
+/// Looks up a body path such as `arg1['Metadata'][0]` in the arguments of a message.
+/// Indexes select array elements and struct fields, and dict entries are selected by key.
+pub fn lookup(args: &[Value<'static>], path: &str) -> Option<String> {
+    let rest = path.strip_prefix("arg")?;
+    let digits = rest.find('[').unwrap_or(rest.len());
+    let arg: usize = rest[..digits].parse().ok()?;
+    let segments = parse_segments(&rest[digits..])?;
+
+    let mut value = unwrap_variants(args.get(arg)?);
+    for segment in &segments {
+        value = unwrap_variants(match value {
+            Value::Array(array) => array.inner().get(segment.parse::<usize>().ok()?)?,
+            Value::Structure(structure) => {
+                structure.fields().get(segment.parse::<usize>().ok()?)?
+            }
+            Value::Dict(dict) => dict
+                .iter()
+                .find(|(k, _)| gvariant::format(k) == *segment)
+                .map(|(_, v)| v)?,
+            _ => return None,
+        });
+    }
+    Some(filter_text(value))
+}
+
+/// Splits `['a'][0]` into `'a'` and `0`, keeping quoted keys intact.
+fn parse_segments(text: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '[' {
+            return None;
+        }
+        let mut segment = String::new();
+        let mut quoted = false;
+        loop {
+            let c = chars.next()?;
+            match c {
+                '\\' if quoted => {
+                    segment.push(c);
+                    segment.push(chars.next()?);
+                }
+                '\'' => {
+                    quoted = !quoted;
+                    segment.push(c);
+                }
+                ']' if !quoted => break,
+                c => segment.push(c),
+            }
+        }
+        segments.push(segment);
+    }
+    Some(segments)
+}
+
+/// The value as JSON. Structs become arrays and dict keys are converted to strings.
+pub fn to_json(value: &Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+    match value {
+        Value::U8(v) => Json::from(*v),
+        Value::Bool(v) => Json::from(*v),
+        Value::I16(v) => Json::from(*v),
+        Value::U16(v) => Json::from(*v),
+        Value::I32(v) => Json::from(*v),
+        Value::U32(v) => Json::from(*v),
+        Value::I64(v) => Json::from(*v),
+        Value::U64(v) => Json::from(*v),
+        Value::F64(v) => Json::from(*v),
+        Value::Str(v) => Json::from(v.as_str()),
+        Value::Signature(v) => Json::from(v.to_string()),
+        Value::ObjectPath(v) => Json::from(v.as_str()),
+        Value::Value(inner) => to_json(inner),
+        Value::Array(array) => Json::Array(array.iter().map(to_json).collect()),
+        Value::Structure(structure) => {
+            Json::Array(structure.fields().iter().map(to_json).collect())
+        }
+        Value::Dict(dict) => Json::Object(
+            dict.iter()
+                .map(|(k, v)| (key_text(k), to_json(v)))
+                .collect(),
+        ),
+        #[cfg(unix)]
+        Value::Fd(_) => Json::Null,
+    }
+}
+
+fn unwrap_variants<'a, 'v>(mut value: &'a Value<'v>) -> &'a Value<'v> {
+    while let Value::Value(inner) = value {
+        value = inner;
+    }
+    value
+}
+
+fn key_text(key: &Value) -> String {
+    match key {
+        Value::Str(s) => s.to_string(),
+        key => gvariant::format(key),
+    }
+}
+
+/// One line of a hex dump: the bytes in hex, padded to a full line, then as ASCII.
+pub(crate) fn hexdump(chunk: &[u8]) -> String {
+    let hex: String = chunk.iter().map(|b| format!("{:02x} ", b)).collect();
+    let ascii: String = chunk
+        .iter()
+        .map(|&b| {
+            if (0x20..=0x7e).contains(&b) {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!("{:width$}|{}|", hex, ascii, width = HEXDUMP_WIDTH * 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// The arguments of a signal carrying `(7, {'Metadata': <{'title': <'Song'>}>, 'Rate': <1.5>},
+    /// ['a', 'b'], (3, 'x'), {2: 'two'})`.
+    fn args() -> Vec<Value<'static>> {
+        let metadata = HashMap::from([("title", Value::from("Song"))]);
+        let properties = HashMap::from([
+            ("Metadata", Value::from(metadata)),
+            ("Rate", Value::from(1.5)),
+        ]);
+        let names = HashMap::from([(2u32, "two")]);
+        let message = zbus::Message::signal("/org/example", "org.example.Player", "Changed")
+            .unwrap()
+            .build(&(7u32, properties, vec!["a", "b"], (3i32, "x"), names))
+            .unwrap();
+        body_args(&message)
+    }
+
+    #[test]
+    fn segments_are_split_with_quoted_keys_intact() {
+        assert_eq!(
+            parse_segments("['a'][0]"),
+            Some(vec!["'a'".to_string(), "0".to_string()])
+        );
+        assert_eq!(
+            parse_segments(r"['it\'s [x]']"),
+            Some(vec![r"'it\'s [x]'".to_string()])
+        );
+        assert_eq!(parse_segments(""), Some(Vec::new()));
+        assert_eq!(parse_segments("[0"), None);
+        assert_eq!(parse_segments("x[0]"), None);
+    }
+
+    #[test]
+    fn whole_arguments() {
+        let args = args();
+        assert_eq!(lookup(&args, "arg0"), Some("7".to_string()));
+        assert_eq!(lookup(&args, "arg9"), None);
+        assert_eq!(lookup(&args, "argx"), None);
+        assert_eq!(lookup(&args, "member"), None);
+    }
+
+    #[test]
+    fn indexes_select_array_elements_and_struct_fields() {
+        let args = args();
+        assert_eq!(lookup(&args, "arg2[1]"), Some("b".to_string()));
+        assert_eq!(lookup(&args, "arg2[2]"), None);
+        assert_eq!(lookup(&args, "arg3[0]"), Some("3".to_string()));
+        assert_eq!(lookup(&args, "arg3[1]"), Some("x".to_string()));
+        assert_eq!(lookup(&args, "arg0[0]"), None);
+    }
+
+    #[test]
+    fn keys_select_dict_entries() {
+        let args = args();
+        assert_eq!(lookup(&args, "arg1['Rate']"), Some("1.5".to_string()));
+        assert_eq!(lookup(&args, "arg4[2]"), Some("two".to_string()));
+        assert_eq!(lookup(&args, "arg1['Missing']"), None);
+        // Keys are written the way they are formatted, so strings need their quotes
+        assert_eq!(lookup(&args, "arg1[Rate]"), None);
+    }
+
+    #[test]
+    fn nested_paths_look_through_variants() {
+        let args = args();
+        assert_eq!(
+            lookup(&args, "arg1['Metadata']['title']"),
+            Some("Song".to_string())
+        );
+        assert_eq!(lookup(&args, "arg1['Metadata']['title'][0]"), None);
+    }
+
+    #[test]
+    fn tree_text_has_every_argument_expanded() {
+        let message = zbus::Message::signal("/org/example", "org.example.Player", "Changed")
+            .unwrap()
+            .build(&(7u32, vec!["a", "b"]))
+            .unwrap();
+        let tree = BodyTree::from_message(&message, &[], &DecoderRegistry::default());
+        assert_eq!(
+            tree.to_text(),
+            "i_1 [u]: 7\ni_2 [as]\n  0 [s]: 'a'\n  1 [s]: 'b'\n"
+        );
+    }
+}
@@ -1,15 +1,63 @@
-use anyhow::Result;
+use crate::decoders::DecoderRegistry;
+use crate::sandbox::{self, Sandbox};
+use anyhow::{bail, Result};
 use futures::StreamExt;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
 use tokio::fs::read;
 use tokio::sync::Mutex;
 use tracing::instrument;
+use zbus::message::{Flags, Type};
+use zbus::zvariant::{OwnedValue, Structure, StructureBuilder, Value};
 use zbus::{fdo::DBusProxy, Connection, MessageStream};
 
-type ProcessInfo = (u32, String, String, Vec<String>);
+/// What is known about the process behind a connection, read from `/proc`.
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    /// The command as started, `argv[0]`.
+    pub path: String,
+    pub args: Vec<String>,
+    /// The executable file, empty if it cannot be read.
+    pub exe: String,
+    /// The systemd unit and slice the process runs in, ie `NetworkManager.service` and
+    /// `system.slice`. Empty outside of systemd.
+    pub unit: String,
+    pub slice: String,
+    /// The Flatpak, snap or container the process runs in, and the ID of its app.
+    pub sandbox: Sandbox,
+    pub app_id: String,
+    /// Parent, grandparent and so on, up to but without init.
+    pub ancestors: Vec<Ancestor>,
+}
+
+impl ProcessInfo {
+    /// Stands in for a process that could not be looked up.
+    fn unknown() -> Self {
+        ProcessInfo {
+            pid: 0,
+            name: "Unknown".to_string(),
+            path: String::new(),
+            args: Vec::new(),
+            exe: String::new(),
+            unit: String::new(),
+            slice: String::new(),
+            sandbox: Sandbox::None,
+            app_id: String::new(),
+            ancestors: Vec::new(),
+        }
+    }
+}
+
+/// A process further up the tree of a sender or receiver.
+#[derive(Debug, Clone)]
+pub struct Ancestor {
+    pub pid: u32,
+    pub name: String,
+}
 
 #[derive(Debug, Clone)]
 pub struct Item {
@@ -18,7 +66,11 @@ pub struct Item {
     pub receiver: String,
     pub member: String,
     pub path: String,
+    pub interface: String,
     pub message: Option<zbus::Message>,
+    pub msg_type: Type,
+    pub no_reply_expected: bool,
+    pub error_name: String,
     pub serial: String,
     pub reply_serial: String,
     pub is_reply: bool,
@@ -27,12 +79,40 @@ pub struct Item {
     pub app_name: String,
     pub app_path: String,
     pub app_args: Vec<String>,
+    pub exe: String,
+    pub unit: String,
+    pub slice: String,
+    pub sandbox: Sandbox,
+    pub app_id: String,
+    /// Parent, grandparent and so on of the sender process, read while it was running.
+    pub ancestors: Vec<Ancestor>,
     pub receiver_pid: Option<u32>,
     pub receiver_app_name: String,
     pub receiver_app_path: String,
     pub receiver_app_args: Vec<String>,
+    pub receiver_ancestors: Vec<Ancestor>,
+    /// Changes carried by a `PropertiesChanged` signal, relative to the last known values.
+    pub property_changes: Vec<PropertyChange>,
+    /// The fault a proxy rule injected into this message, ie `dropped`.
+    pub fault: Option<String>,
+    /// Arguments of the body, parsed the first time a filter looks into them.
+    pub args: Arc<OnceLock<Vec<Value<'static>>>>,
 }
 
+/// A single property in a `PropertiesChanged` signal.
+#[derive(Debug, Clone)]
+pub struct PropertyChange {
+    pub interface: String,
+    pub name: String,
+    /// The last known value, `None` if no earlier signal carried it.
+    pub old: Option<Value<'static>>,
+    /// The new value, `None` if the property was invalidated.
+    pub new: Option<Value<'static>>,
+}
+
+/// Last known property values per sender, object path, interface and property name.
+pub type PropertyStore = HashMap<(String, String, String, String), Value<'static>>;
+
 impl Default for Item {
     fn default() -> Self {
         Item {
@@ -41,7 +121,11 @@ impl Default for Item {
             receiver: String::new(),
             member: String::new(),
             path: String::new(),
+            interface: String::new(),
             message: None,
+            msg_type: Type::Signal,
+            no_reply_expected: false,
+            error_name: String::new(),
             serial: String::new(),
             reply_serial: String::new(),
             is_reply: false,
@@ -50,15 +134,164 @@ impl Default for Item {
             app_name: String::new(),
             app_path: String::new(),
             app_args: Vec::new(),
+            exe: String::new(),
+            unit: String::new(),
+            slice: String::new(),
+            sandbox: Sandbox::None,
+            app_id: String::new(),
+            ancestors: Vec::new(),
             receiver_pid: None,
             receiver_app_name: String::new(),
             receiver_app_path: String::new(),
             receiver_app_args: Vec::new(),
+            receiver_ancestors: Vec::new(),
+            property_changes: Vec::new(),
+            fault: None,
+            args: Arc::default(),
         }
     }
 }
 
+/// Fields that can be used in `field=value` filters and are offered by the autofilter popup.
+pub const FILTER_FIELDS: [&str; 15] = [
+    "type",
+    "sender",
+    "member",
+    "path",
+    "interface",
+    "serial",
+    "reply_serial",
+    "exe",
+    "unit",
+    "slice",
+    "ppid",
+    "ancestor",
+    "sandbox",
+    "app_id",
+    "peer",
+];
+
 impl Item {
+    /// Fills in the process that sent the message, `None` if it could not be looked up.
+    pub fn set_sender_process(&mut self, info: Option<ProcessInfo>) {
+        self.pid = info.as_ref().map(|info| info.pid);
+        let info = info.unwrap_or_else(ProcessInfo::unknown);
+        self.app_name = info.name;
+        self.app_path = info.path;
+        self.app_args = info.args;
+        self.exe = info.exe;
+        self.unit = info.unit;
+        self.slice = info.slice;
+        self.sandbox = info.sandbox;
+        self.app_id = info.app_id;
+        self.ancestors = info.ancestors;
+    }
+
+    /// Fills in the process the message was sent to, `None` if it could not be looked up.
+    pub fn set_receiver_process(&mut self, info: Option<ProcessInfo>) {
+        self.receiver_pid = info.as_ref().map(|info| info.pid);
+        let info = info.unwrap_or_else(ProcessInfo::unknown);
+        self.receiver_app_name = info.name;
+        self.receiver_app_path = info.path;
+        self.receiver_app_args = info.args;
+        self.receiver_ancestors = info.ancestors;
+    }
+
+    /// Arguments of the body, empty for an item without a message.
+    pub fn args(&self) -> &[Value<'static>] {
+        self.args.get_or_init(|| {
+            self.message
+                .as_ref()
+                .map(crate::body_tree::body_args)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Name and pid of the process that started the sender.
+    pub fn parent(&self) -> Option<(&str, u32)> {
+        self.ancestors
+            .first()
+            .map(|parent| (parent.name.as_str(), parent.pid))
+    }
+
+    /// The value of a filter field, or `None` if the field is unknown.
+    pub fn field_value(&self, field: &str) -> Option<std::borrow::Cow<'_, str>> {
+        match field {
+            "type" => Some(self.type_name().into()),
+            "sender" => Some(self.sender_display()),
+            "member" => Some(self.member.as_str().into()),
+            "path" => Some(self.path.as_str().into()),
+            "interface" => Some(self.interface.as_str().into()),
+            "serial" => Some(self.serial.as_str().into()),
+            "reply_serial" => Some(self.reply_serial.as_str().into()),
+            "exe" => Some(self.exe.as_str().into()),
+            "unit" => Some(self.unit.as_str().into()),
+            "slice" => Some(self.slice.as_str().into()),
+            "sandbox" => Some(self.sandbox.to_string().into()),
+            "app_id" => Some(self.app_id.as_str().into()),
+            // Both ends of the message, ie `:1.42,org.example.Service`
+            "peer" => Some(format!("{},{}", self.sender, self.receiver).into()),
+            "ppid" => self
+                .ancestors
+                .first()
+                .map(|parent| parent.pid.to_string().into()),
+            // Names of all processes above the sender, ie `gnome-shell>bash`
+            "ancestor" => Some(
+                self.ancestors
+                    .iter()
+                    .rev()
+                    .map(|ancestor| ancestor.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(">")
+                    .into(),
+            ),
+            // Body paths such as `arg1['Metadata']`
+            field if field.starts_with("arg") => {
+                crate::body_tree::lookup(self.args(), field).map(Into::into)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a `field=value` filter term matches, ie the field contains the value.
+    /// `peer` takes a comma separated list of names, one of which has to be the sender or
+    /// the receiver.
+    pub fn matches_field(&self, field: &str, value: &str) -> bool {
+        if field == "peer" {
+            return value
+                .split(',')
+                .any(|name| name == self.sender || name == self.receiver);
+        }
+        self.field_value(field)
+            .is_some_and(|field_value| field_value.contains(value))
+    }
+
+    /// Whether the general filter text matches the sender, member or path.
+    pub fn matches_text(&self, text: &str) -> bool {
+        self.sender.contains(text) || self.member.contains(text) || self.path.contains(text)
+    }
+
+    /// Whether every term of a filter matches, `field=value` terms and plain text alike.
+    /// An empty filter matches everything.
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        filter
+            .split_whitespace()
+            .all(|term| match term.split_once('=') {
+                Some((field, value)) => self.matches_field(field, value),
+                None => self.matches_text(term),
+            })
+    }
+
+    /// The message type as used by the `type` filter field.
+    pub fn type_name(&self) -> &'static str {
+        match self.msg_type {
+            Type::MethodCall => "method_call",
+            Type::MethodReturn => "method_return",
+            Type::Error => "error",
+            Type::Signal => "signal",
+        }
+    }
+
     pub fn sender_display(&self) -> std::borrow::Cow<'_, str> {
         if self.app_name != "Unknown" && self.pid.is_some() {
             format!("{}:{}", self.app_name, self.pid.unwrap_or(0)).into()
@@ -83,6 +316,62 @@ impl Item {
             "".into() // Return an empty Cow::Borrowed("")
         }
     }
+
+    /// A one line summary of the property changes, such as `ActiveState: activating→active`.
+    /// Values with a symbolic name are shown by that name.
+    pub fn property_changes_summary(&self, decoders: &DecoderRegistry) -> String {
+        let short = |change: &PropertyChange, value: &Option<Value>| match value {
+            Some(value) => decoders
+                .decode_property(&change.interface, &change.name, value)
+                .unwrap_or_else(|| match value {
+                    Value::Str(s) => s.to_string(),
+                    value => crate::gvariant::format(value),
+                }),
+            None => "?".to_string(),
+        };
+        self.property_changes
+            .iter()
+            .map(|change| match &change.new {
+                Some(_) if change.old.is_none() => {
+                    format!("{}: {}", change.name, short(change, &change.new))
+                }
+                Some(_) => format!(
+                    "{}: {}→{}",
+                    change.name,
+                    short(change, &change.old),
+                    short(change, &change.new)
+                ),
+                None => format!("{}: invalidated", change.name),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The human readable text of an error message, which by convention is its first string argument.
+    pub fn error_text(&self) -> String {
+        let Some(message) = &self.message else {
+            return String::new();
+        };
+        let body = message.body();
+        let first_arg = match body.deserialize::<Structure>() {
+            Ok(structure) => structure.into_fields().into_iter().next(),
+            Err(_) => body.deserialize::<Value>().ok(),
+        };
+        match first_arg {
+            Some(Value::Str(s)) => s.to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Aggregated view of every occurrence of a single D-Bus error name.
+#[derive(Debug, Clone)]
+pub struct ErrorSummary {
+    pub name: String,
+    pub count: usize,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    pub latest: Item,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -90,6 +379,7 @@ pub enum BusType {
     Session = 0,
     System = 1,
     Both = 2,
+    Isolated = 3, // Private bus started by `d-buddy run-isolated`
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -99,9 +389,31 @@ pub enum GroupingType {
     Member,
     Path,
     Serial,
+    Executable,
+    Unit,
+    Slice,
+    Parent,
+    Sandbox,
+    AppId,
     None,
 }
 
+impl GroupingType {
+    pub const ALL: [GroupingType; 11] = [
+        GroupingType::Sender,
+        GroupingType::Member,
+        GroupingType::Path,
+        GroupingType::Serial,
+        GroupingType::Executable,
+        GroupingType::Unit,
+        GroupingType::Slice,
+        GroupingType::Parent,
+        GroupingType::Sandbox,
+        GroupingType::AppId,
+        GroupingType::None,
+    ];
+}
+
 impl std::fmt::Display for GroupingType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -109,6 +421,12 @@ impl std::fmt::Display for GroupingType {
             GroupingType::Member => write!(f, "Member"),
             GroupingType::Path => write!(f, "Path"),
             GroupingType::Serial => write!(f, "Serial"),
+            GroupingType::Executable => write!(f, "Executable"),
+            GroupingType::Unit => write!(f, "Unit"),
+            GroupingType::Slice => write!(f, "Slice"),
+            GroupingType::Parent => write!(f, "Parent"),
+            GroupingType::Sandbox => write!(f, "Sandbox"),
+            GroupingType::AppId => write!(f, "App ID"),
             GroupingType::None => write!(f, "None"),
         }
     }
@@ -123,14 +441,82 @@ impl std::str::FromStr for GroupingType {
             "Member" => Ok(GroupingType::Member),
             "Path" => Ok(GroupingType::Path),
             "Serial" => Ok(GroupingType::Serial),
+            "Executable" => Ok(GroupingType::Executable),
+            "Unit" => Ok(GroupingType::Unit),
+            "Slice" => Ok(GroupingType::Slice),
+            "Parent" => Ok(GroupingType::Parent),
+            "Sandbox" => Ok(GroupingType::Sandbox),
+            "App ID" => Ok(GroupingType::AppId),
             "None" => Ok(GroupingType::None),
             _ => Err(anyhow::anyhow!("Unknown GroupingType: {}", s)),
         }
     }
 }
 
+/// Returns the method calls that have not been answered by a method return or error yet,
+/// oldest first. Calls flagged `NO_REPLY_EXPECTED` are skipped.
+pub fn pending_calls(items: &[Item]) -> Vec<Item> {
+    // Serials are only unique per connection, so a reply is matched on the bus,
+    // the caller it is addressed to and the serial it answers.
+    let answered: HashSet<(BusType, &str, &str)> = items
+        .iter()
+        .filter(|item| matches!(item.msg_type, Type::MethodReturn | Type::Error))
+        .map(|item| {
+            (
+                item.stream_type,
+                item.receiver.as_str(),
+                item.reply_serial.as_str(),
+            )
+        })
+        .collect();
+
+    let mut pending: Vec<Item> = items
+        .iter()
+        .filter(|item| {
+            item.msg_type == Type::MethodCall
+                && !item.no_reply_expected
+                && !answered.contains(&(
+                    item.stream_type,
+                    item.sender.as_str(),
+                    item.serial.as_str(),
+                ))
+        })
+        .cloned()
+        .collect();
+    pending.sort_by_key(|item| item.timestamp);
+    pending
+}
+
+/// Groups the error messages in `items` by error name, most frequent first.
+pub fn error_summaries(items: &[Item]) -> Vec<ErrorSummary> {
+    let mut summaries: HashMap<&str, ErrorSummary> = HashMap::new();
+    for item in items.iter().filter(|item| item.msg_type == Type::Error) {
+        summaries
+            .entry(item.error_name.as_str())
+            .and_modify(|summary| {
+                summary.count += 1;
+                summary.first_seen = summary.first_seen.min(item.timestamp);
+                if item.timestamp >= summary.last_seen {
+                    summary.last_seen = item.timestamp;
+                    summary.latest = item.clone();
+                }
+            })
+            .or_insert_with(|| ErrorSummary {
+                name: item.error_name.clone(),
+                count: 1,
+                first_seen: item.timestamp,
+                last_seen: item.timestamp,
+                latest: item.clone(),
+            });
+    }
+
+    let mut summaries: Vec<ErrorSummary> = summaries.into_values().collect();
+    summaries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    summaries
+}
+
 #[instrument(skip(conn, cache))]
-async fn get_process_info(
+pub async fn get_process_info(
     conn: &zbus::Connection,
     bus_name: &str,
     cache: &Arc<Mutex<HashMap<String, ProcessInfo>>>,
@@ -156,6 +542,18 @@ async fn get_process_info(
         .deserialize()
         .ok()?;
 
+    let info = process_info_for_pid(pid).await?;
+
+    {
+        let mut cache_locked = cache.lock().await;
+        cache_locked.insert(bus_name.to_string(), info.clone());
+    }
+
+    Some(info)
+}
+
+/// Reads the name, command line, executable, systemd unit and ancestors of a process from `/proc`.
+pub async fn process_info_for_pid(pid: u32) -> Option<ProcessInfo> {
     let cmdline_path = format!("/proc/{}/cmdline", pid);
     let cmdline_content = read(&cmdline_path).await.ok()?;
 
@@ -170,43 +568,240 @@ async fn get_process_info(
         })
         .collect();
 
-    if args.is_empty() {
-        return Some((pid, "Unknown".to_string(), "".to_string(), Vec::new()));
+    let (mut name, path) = match args.first() {
+        Some(app_path) => (
+            PathBuf::from(app_path)
+                .file_name()?
+                .to_string_lossy()
+                .to_string(),
+            app_path.clone(),
+        ),
+        None => ("Unknown".to_string(), String::new()),
+    };
+    let exe = tokio::fs::read_link(format!("/proc/{}/exe", pid))
+        .await
+        .map(|exe| exe.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let cgroup = tokio::fs::read_to_string(format!("/proc/{}/cgroup", pid))
+        .await
+        .unwrap_or_default();
+    let (unit, slice) = systemd_unit(&cgroup);
+    // A sandboxed app is known by its ID rather than by bwrap or xdg-dbus-proxy
+    let (sandbox, app_id) = {
+        // Detection reads files in the root of the process, which is kept off the runtime
+        let exe = exe.clone();
+        tokio::task::spawn_blocking(move || sandbox::detect(pid, &cgroup, &exe))
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    };
+    if !app_id.is_empty() {
+        name = app_id.clone();
     }
+    // Walking up the tree takes a read per ancestor, which is kept off the runtime
+    let ancestors = tokio::task::spawn_blocking(move || ancestors(pid))
+        .await
+        .unwrap_or_default();
 
-    let app_path = args[0].clone();
-    let app_name = PathBuf::from(&app_path)
-        .file_name()?
-        .to_string_lossy()
-        .to_string();
+    Some(ProcessInfo {
+        pid,
+        name,
+        path,
+        args,
+        exe,
+        unit,
+        slice,
+        sandbox,
+        app_id,
+        ancestors,
+    })
+}
 
-    let info = (pid, app_name, app_path, args);
+/// The unit and the innermost slice in the contents of `/proc/<pid>/cgroup`, ie
+/// `0::/system.slice/NetworkManager.service` gives `NetworkManager.service` and `system.slice`.
+fn systemd_unit(cgroup: &str) -> (String, String) {
+    // The unified hierarchy, or the systemd one on cgroup v1 and hybrid setups where the unified
+    // one is empty
+    let paths = cgroup
+        .lines()
+        .filter_map(|line| line.strip_prefix("0::"))
+        .chain(
+            cgroup
+                .lines()
+                .filter_map(|line| line.split_once(":name=systemd:").map(|(_, path)| path)),
+        );
+    for path in paths {
+        let unit = path
+            .split('/')
+            .rfind(|part| part.ends_with(".service") || part.ends_with(".scope"));
+        let slice = path.split('/').rfind(|part| part.ends_with(".slice"));
+        if unit.is_some() || slice.is_some() {
+            return (
+                unit.unwrap_or_default().to_string(),
+                slice.unwrap_or_default().to_string(),
+            );
+        }
+    }
+    (String::new(), String::new())
+}
 
-    {
-        let mut cache_locked = cache.lock().await;
-        cache_locked.insert(bus_name.to_string(), info.clone());
+/// The parent of a process, read from `/proc/<pid>/stat`.
+pub fn parent_pid(pid: u32) -> Option<u32> {
+    parse_parent_pid(&std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+}
+
+/// The parent pid in the contents of `/proc/<pid>/stat`, ie `42 (bash) S 7 ...` gives 7.
+fn parse_parent_pid(stat: &str) -> Option<u32> {
+    // The command name in parentheses may contain spaces and parentheses, the fields after it
+    // do not
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// The parent, grandparent and so on of a process, up to but without init.
+pub fn ancestor_pids(pid: u32) -> Vec<u32> {
+    let mut ancestors = Vec::new();
+    let mut pid = pid;
+    while let Some(ppid) = parent_pid(pid).filter(|&ppid| ppid > 1) {
+        ancestors.push(ppid);
+        pid = ppid;
     }
+    ancestors
+}
 
-    Some(info)
+/// The ancestors of a process with their names from `/proc/<pid>/comm`.
+fn ancestors(pid: u32) -> Vec<Ancestor> {
+    ancestor_pids(pid)
+        .into_iter()
+        .map(|pid| Ancestor {
+            pid,
+            name: std::fs::read_to_string(format!("/proc/{}/comm", pid))
+                .map(|comm| comm.trim_end().to_string())
+                .unwrap_or_else(|_| "Unknown".to_string()),
+        })
+        .collect()
 }
 
-pub async fn dbus_listener(t: BusType) -> Result<Arc<Mutex<Vec<Item>>>> {
-    let messages = Arc::new(Mutex::new(Vec::new()));
-    let messages_clone = Arc::clone(&messages);
-    let cache = Arc::new(Mutex::new(HashMap::<String, ProcessInfo>::new()));
+/// A process and its ancestors from the top down, ie `gnome-shell:1200 › bash:3000 › app:3100`.
+pub fn ancestry(ancestors: &[Ancestor], name: &str, pid: u32) -> String {
+    ancestors
+        .iter()
+        .rev()
+        .map(|ancestor| format!("{}:{}", ancestor.name, ancestor.pid))
+        .chain(std::iter::once(format!("{}:{}", name, pid)))
+        .collect::<Vec<_>>()
+        .join(" › ")
+}
+
+/// Reads the changes out of a `PropertiesChanged` signal and records the new values in `store`.
+fn track_property_changes(
+    store: &mut PropertyStore,
+    msg: &zbus::Message,
+    sender: &str,
+    path: &str,
+) -> Vec<PropertyChange> {
+    let Ok((interface, changed, invalidated)) =
+        msg.body()
+            .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+    else {
+        return Vec::new();
+    };
+    let key = |name: &str| {
+        (
+            sender.to_string(),
+            path.to_string(),
+            interface.clone(),
+            name.to_string(),
+        )
+    };
 
-    let conn: Connection = match t {
+    let mut changes: Vec<PropertyChange> = changed
+        .into_iter()
+        .map(|(name, value)| {
+            let new = Value::from(value);
+            let old = store.insert(key(&name), new.clone());
+            PropertyChange {
+                interface: interface.clone(),
+                name,
+                old,
+                new: Some(new),
+            }
+        })
+        .collect();
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes.extend(invalidated.into_iter().map(|name| PropertyChange {
+        interface: interface.clone(),
+        old: store.remove(&key(&name)),
+        name,
+        new: None,
+    }));
+    changes
+}
+
+/// Calls a method with a dynamically built argument list.
+/// Both method returns and error replies are returned as the reply message.
+pub async fn call_method(
+    conn: &Connection,
+    destination: &str,
+    path: &str,
+    interface: Option<&str>,
+    member: &str,
+    args: Vec<Value<'static>>,
+    timeout: Duration,
+) -> Result<zbus::Message> {
+    let call = async {
+        if args.is_empty() {
+            conn.call_method(Some(destination), path, interface, member, &())
+                .await
+        } else {
+            // A structure body is sent as its fields, one argument each
+            let body = args
+                .into_iter()
+                .fold(StructureBuilder::new(), |builder, arg| {
+                    builder.append_field(arg)
+                })
+                .build()?;
+            conn.call_method(Some(destination), path, interface, member, &body)
+                .await
+        }
+    };
+
+    match tokio::time::timeout(timeout, call).await {
+        Ok(Ok(reply)) => Ok(reply),
+        Ok(Err(zbus::Error::MethodError(_, _, reply))) => Ok(reply),
+        Ok(Err(e)) => Err(e.into()),
+        Err(_) => Err(anyhow::anyhow!("No reply within {}s", timeout.as_secs())),
+    }
+}
+
+/// Opens a new connection to the given bus.
+pub async fn connect(t: BusType) -> Result<Connection> {
+    Ok(match t {
         BusType::Session => zbus::Connection::session().await?,
         BusType::System => zbus::Connection::system().await?,
         BusType::Both => zbus::Connection::session().await?,
-    };
+        BusType::Isolated => bail!("The isolated bus is connected to by its address"),
+    })
+}
 
-    if let Some(our_name) = conn.unique_name() {
-        // Prime the cache with our own info
-        let _ = get_process_info(&conn, our_name.as_str(), &cache).await;
-    }
+/// A connection builder for `session`, `system` or a D-Bus address.
+pub fn connection_builder(bus: &str) -> Result<zbus::connection::Builder<'static>> {
+    Ok(match bus {
+        "session" => zbus::connection::Builder::session()?,
+        "system" => zbus::connection::Builder::system()?,
+        address => zbus::connection::Builder::address(address)?,
+    })
+}
 
-    let proxy = DBusProxy::new(&conn).await?;
+/// Opens a bus connection to a D-Bus address, ie `unix:path=/run/user/1000/bus`.
+pub async fn connect_address(address: &str) -> Result<Connection> {
+    Ok(zbus::connection::Builder::address(address)?.build().await?)
+}
+
+/// Adds match rules for every message type, so the connection receives the bus traffic.
+pub async fn subscribe(conn: &Connection) -> Result<()> {
+    let proxy = DBusProxy::new(conn).await?;
     proxy
         .add_match_rule(
             zbus::MatchRule::builder()
@@ -235,11 +830,31 @@ pub async fn dbus_listener(t: BusType) -> Result<Arc<Mutex<Vec<Item>>>> {
                 .build(),
         )
         .await?;
+    Ok(())
+}
+
+pub async fn dbus_listener(t: BusType) -> Result<Arc<Mutex<Vec<Item>>>> {
+    listen(connect(t).await?, t).await
+}
+
+/// Collects the traffic seen by `conn`, listed under the bus `t`.
+pub async fn listen(conn: Connection, t: BusType) -> Result<Arc<Mutex<Vec<Item>>>> {
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let messages_clone = Arc::clone(&messages);
+    let cache = Arc::new(Mutex::new(HashMap::<String, ProcessInfo>::new()));
+
+    if let Some(our_name) = conn.unique_name() {
+        // Prime the cache with our own info
+        let _ = get_process_info(&conn, our_name.as_str(), &cache).await;
+    }
+
+    subscribe(&conn).await?;
 
     let stream = MessageStream::from(&conn);
     let cache_clone = Arc::clone(&cache);
     tokio::spawn(async move {
         let mut stream = stream;
+        let mut property_store = PropertyStore::new();
         while let Some(Ok(msg)) = stream.next().await {
             let header = msg.header();
 
@@ -253,67 +868,17 @@ pub async fn dbus_listener(t: BusType) -> Result<Arc<Mutex<Vec<Item>>>> {
                 .map(|s| s.as_str().to_string())
                 .unwrap_or_default();
 
-            let mut pid_val = None;
-            let mut app_name_val = "Unknown".to_string();
-            let mut app_path_val = String::new();
-            let mut app_args_val = Vec::new();
-
-            if sender_name.starts_with(':') {
-                if let Some((p, an, ap, aa)) =
-                    get_process_info(&conn, &sender_name, &cache_clone).await
-                {
-                    pid_val = Some(p);
-                    app_name_val = an;
-                    app_path_val = ap;
-                    app_args_val = aa;
-                }
-            }
-
-            let mut receiver_pid_val = None;
-            let mut receiver_app_name_val = "Unknown".to_string();
-            let mut receiver_app_path_val = String::new();
-            let mut receiver_app_args_val = Vec::new();
-
-            if receiver_name.starts_with(':') {
-                if let Some((p, an, ap, aa)) =
-                    get_process_info(&conn, &receiver_name, &cache_clone).await
-                {
-                    receiver_pid_val = Some(p);
-                    receiver_app_name_val = an;
-                    receiver_app_path_val = ap;
-                    receiver_app_args_val = aa;
-                }
-            }
-
-            let item = Item {
-                timestamp: SystemTime::now(),
-                sender: sender_name.clone(),
-                receiver: receiver_name.clone(),
-                member: header
-                    .member()
-                    .map(|s| s.as_str().to_string())
-                    .unwrap_or_default(),
-                path: header
-                    .path()
-                    .map(|p| p.as_str().to_string())
-                    .unwrap_or_default(),
-                is_reply: header.reply_serial().is_some(),
-                reply_serial: header
-                    .reply_serial()
-                    .map(|s| s.to_string())
-                    .unwrap_or_default(),
-                serial: header.primary().serial_num().to_string(),
-                message: Some(msg),
-                stream_type: t,
-                pid: pid_val,
-                app_name: app_name_val,
-                app_path: app_path_val,
-                app_args: app_args_val,
-                receiver_pid: receiver_pid_val,
-                receiver_app_name: receiver_app_name_val,
-                receiver_app_path: receiver_app_path_val,
-                receiver_app_args: receiver_app_args_val,
+            let sender_info = if sender_name.starts_with(':') {
+                get_process_info(&conn, &sender_name, &cache_clone).await
+            } else {
+                None
+            };
+            let receiver_info = if receiver_name.starts_with(':') {
+                get_process_info(&conn, &receiver_name, &cache_clone).await
+            } else {
+                None
             };
+            let item = new_item(msg, t, sender_info, receiver_info, &mut property_store);
 
             messages_clone.lock().await.push(item);
         }
@@ -321,3 +886,139 @@ pub async fn dbus_listener(t: BusType) -> Result<Arc<Mutex<Vec<Item>>>> {
 
     Ok(messages)
 }
+
+/// Builds the list entry for a captured message. `property_store` holds the last known
+/// property values, which `PropertiesChanged` signals are compared against.
+pub fn new_item(
+    msg: zbus::Message,
+    t: BusType,
+    sender_info: Option<ProcessInfo>,
+    receiver_info: Option<ProcessInfo>,
+    property_store: &mut PropertyStore,
+) -> Item {
+    let header = msg.header();
+
+    let sender_name = header
+        .sender()
+        .map(|s| s.as_str().to_string())
+        .unwrap_or_default();
+
+    let receiver_name = header
+        .destination()
+        .map(|s| s.as_str().to_string())
+        .unwrap_or_default();
+
+    let property_changes = if msg.message_type() == Type::Signal
+        && header
+            .member()
+            .is_some_and(|m| m.as_str() == "PropertiesChanged")
+        && header
+            .interface()
+            .is_some_and(|i| i.as_str() == "org.freedesktop.DBus.Properties")
+    {
+        track_property_changes(
+            property_store,
+            &msg,
+            &sender_name,
+            header.path().map(|p| p.as_str()).unwrap_or_default(),
+        )
+    } else {
+        Vec::new()
+    };
+
+    let mut item = Item {
+        timestamp: SystemTime::now(),
+        sender: sender_name,
+        receiver: receiver_name,
+        member: header
+            .member()
+            .map(|s| s.as_str().to_string())
+            .unwrap_or_default(),
+        path: header
+            .path()
+            .map(|p| p.as_str().to_string())
+            .unwrap_or_default(),
+        interface: header
+            .interface()
+            .map(|i| i.as_str().to_string())
+            .unwrap_or_default(),
+        is_reply: header.reply_serial().is_some(),
+        reply_serial: header
+            .reply_serial()
+            .map(|s| s.to_string())
+            .unwrap_or_default(),
+        serial: header.primary().serial_num().to_string(),
+        msg_type: msg.message_type(),
+        no_reply_expected: header.primary().flags().contains(Flags::NoReplyExpected),
+        error_name: header
+            .error_name()
+            .map(|e| e.as_str().to_string())
+            .unwrap_or_default(),
+        message: Some(msg.clone()),
+        stream_type: t,
+        property_changes,
+        ..Default::default()
+    };
+    item.set_sender_process(sender_info);
+    item.set_receiver_process(receiver_info);
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_and_slices_from_cgroups() {
+        assert_eq!(
+            systemd_unit("0::/system.slice/NetworkManager.service\n"),
+            (
+                "NetworkManager.service".to_string(),
+                "system.slice".to_string()
+            )
+        );
+        assert_eq!(
+            systemd_unit("0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-firefox-1234.scope\n"),
+            (
+                "app-firefox-1234.scope".to_string(),
+                "app.slice".to_string()
+            )
+        );
+        // Only a slice, ie a process placed directly in one
+        assert_eq!(
+            systemd_unit("0::/user.slice/user-1000.slice\n"),
+            (String::new(), "user-1000.slice".to_string())
+        );
+        assert_eq!(systemd_unit("0::/\n"), (String::new(), String::new()));
+        assert_eq!(systemd_unit(""), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn units_from_cgroup_v1_and_hybrid_setups() {
+        let v1 = "12:cpu,cpuacct:/\n1:name=systemd:/system.slice/cron.service\n";
+        assert_eq!(
+            systemd_unit(v1),
+            ("cron.service".to_string(), "system.slice".to_string())
+        );
+        // The unified hierarchy is empty on hybrid setups, the systemd one is used then
+        let hybrid = "0::/\n1:name=systemd:/system.slice/cron.service\n";
+        assert_eq!(systemd_unit(hybrid), systemd_unit(v1));
+    }
+
+    #[test]
+    fn parent_pids_from_stat() {
+        assert_eq!(
+            parse_parent_pid("42 (bash) S 7 42 42 34816 42 4194560 0 0\n"),
+            Some(7)
+        );
+        assert_eq!(
+            parse_parent_pid("100 (Web Content) S 90 90 90 0 -1 4194560\n"),
+            Some(90)
+        );
+        // A name with parentheses and spaces, as programs can set it to anything
+        assert_eq!(parse_parent_pid("5 (a) b) (c ) S 3 5 5 0 -1\n"), Some(3));
+        assert_eq!(parse_parent_pid("5 (no end S 3 5"), None);
+        assert_eq!(parse_parent_pid("5 (x) S"), None);
+        assert_eq!(parse_parent_pid(""), None);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::body_tree::body_args;
+use crate::bus::{BusType, Item};
+use crate::gvariant;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zbus::message::{Builder, Type};
+use zbus::zvariant::{Signature, StructureBuilder, Value};
+
+/// One message of a saved capture, stored as a line of JSON.
+///
+/// Arguments are kept as GVariant text together with the body signature, so they read like
+/// `gdbus` output and parse back to the same types.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedMessage {
+    /// Seconds since the Unix epoch.
+    pub timestamp: f64,
+    /// `session`, `system` or `isolated`.
+    pub bus: String,
+    /// `method_call`, `method_return`, `error` or `signal`.
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub serial: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_serial: Option<u32>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sender: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub destination: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub interface: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub member: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub error_name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub args: String,
+}
+
+impl CapturedMessage {
+    pub fn from_item(item: &Item) -> Self {
+        let (signature, args) = match &item.message {
+            Some(message) => {
+                let args = body_args(message);
+                // The signature as in the message header, without the parentheses zbus adds
+                let signature = args
+                    .iter()
+                    .map(|arg| arg.value_signature().to_string())
+                    .collect();
+                (signature, gvariant::format_args(&args))
+            }
+            None => (String::new(), String::new()),
+        };
+        CapturedMessage {
+            timestamp: item
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            bus: match item.stream_type {
+                BusType::System => "system",
+                BusType::Isolated => "isolated",
+                _ => "session",
+            }
+            .to_string(),
+            msg_type: item.type_name().to_string(),
+            serial: item.serial.parse().unwrap_or_default(),
+            reply_serial: item.reply_serial.parse().ok(),
+            sender: item.sender.clone(),
+            destination: item.receiver.clone(),
+            path: item.path.clone(),
+            interface: item.interface.clone(),
+            member: item.member.clone(),
+            error_name: item.error_name.clone(),
+            signature,
+            args,
+        }
+    }
+
+    pub fn message_type(&self) -> Option<Type> {
+        match self.msg_type.as_str() {
+            "method_call" => Some(Type::MethodCall),
+            "method_return" => Some(Type::MethodReturn),
+            "error" => Some(Type::Error),
+            "signal" => Some(Type::Signal),
+            _ => None,
+        }
+    }
+
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs_f64(self.timestamp.max(0.0))
+    }
+
+    /// Rebuilds a signal or method call for sending, with a new serial and no sender.
+    pub fn to_message(&self) -> Result<zbus::Message> {
+        let builder = match self.message_type() {
+            Some(Type::Signal) => zbus::Message::signal(
+                self.path.as_str(),
+                self.interface.as_str(),
+                self.member.as_str(),
+            )?,
+            Some(Type::MethodCall) => {
+                let mut builder =
+                    zbus::Message::method_call(self.path.as_str(), self.member.as_str())?;
+                if !self.interface.is_empty() {
+                    builder = builder.interface(self.interface.as_str())?;
+                }
+                if !self.destination.is_empty() {
+                    builder = builder.destination(self.destination.as_str())?;
+                }
+                builder
+            }
+            _ => bail!("Only signals and method calls can be sent again"),
+        };
+        build_message(builder, self.values()?)
+    }
+
+    /// The message as a list entry, so it can be matched with the filter syntax.
+    pub fn to_item(&self) -> Item {
+        Item {
+            timestamp: self.time(),
+            sender: self.sender.clone(),
+            receiver: self.destination.clone(),
+            member: self.member.clone(),
+            path: self.path.clone(),
+            interface: self.interface.clone(),
+            message: self.to_message().ok(),
+            msg_type: self.message_type().unwrap_or(Type::Signal),
+            error_name: self.error_name.clone(),
+            serial: self.serial.to_string(),
+            reply_serial: self
+                .reply_serial
+                .map(|serial| serial.to_string())
+                .unwrap_or_default(),
+            is_reply: self.reply_serial.is_some(),
+            stream_type: match self.bus.as_str() {
+                "system" => BusType::System,
+                "isolated" => BusType::Isolated,
+                _ => BusType::Session,
+            },
+            app_name: "Unknown".to_string(),
+            receiver_app_name: "Unknown".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The arguments parsed back into values of the recorded types.
+    pub fn values(&self) -> Result<Vec<Value<'static>>> {
+        let signatures = match Signature::try_from(self.signature.as_str())
+            .map_err(|e| anyhow!("Invalid signature '{}': {}", self.signature, e))?
+        {
+            Signature::Unit => Vec::new(),
+            Signature::Structure(fields) => fields.iter().cloned().collect(),
+            signature => vec![signature],
+        };
+        gvariant::parse_args(&self.args, Some(&signatures))
+    }
+}
+
+/// Writes the items to a capture file, oldest first. Returns the number of messages written.
+pub fn save(path: &Path, items: &[Item]) -> Result<usize> {
+    let mut messages: Vec<CapturedMessage> = items.iter().map(CapturedMessage::from_item).collect();
+    messages.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    let mut text = String::new();
+    for message in &messages {
+        text.push_str(&serde_json::to_string(message)?);
+        text.push('\n');
+    }
+    std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(messages.len())
+}
+
+/// Reads a capture file. Empty lines are skipped.
+pub fn load(path: &Path) -> Result<Vec<CapturedMessage>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("{}:{}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Builds a message with the arguments as its body. A structure body is sent as its fields,
+/// one argument each.
+pub fn build_message(builder: Builder<'_>, args: Vec<Value<'static>>) -> Result<zbus::Message> {
+    if args.is_empty() {
+        return Ok(builder.build(&())?);
+    }
+    let body = args
+        .into_iter()
+        .fold(StructureBuilder::new(), |builder, arg| {
+            builder.append_field(arg)
+        })
+        .build()?;
+    Ok(builder.build(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{new_item, PropertyStore};
+    use std::collections::HashMap;
+
+    fn item(msg: zbus::Message) -> Item {
+        new_item(msg, BusType::System, None, None, &mut PropertyStore::new())
+    }
+
+    #[test]
+    fn messages_survive_a_round_trip_through_a_file() {
+        let signal = zbus::Message::signal("/org/example", "org.example.Device", "Changed")
+            .unwrap()
+            .sender(":1.5")
+            .unwrap()
+            .build(&(
+                "power",
+                7u32,
+                vec![1i16, -2],
+                HashMap::from([("on", Value::from(true))]),
+            ))
+            .unwrap();
+        let call = zbus::Message::method_call("/org/example", "Get")
+            .unwrap()
+            .interface("org.example.Device")
+            .unwrap()
+            .destination("org.example")
+            .unwrap()
+            .build(&())
+            .unwrap();
+        let items = vec![item(signal.clone()), item(call)];
+
+        let path = std::env::temp_dir().join(format!("d-buddy-capture-{}", std::process::id()));
+        assert_eq!(save(&path, &items).unwrap(), 2);
+        let loaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        let captured = &loaded[0];
+        assert_eq!(captured.msg_type, "signal");
+        assert_eq!(captured.bus, "system");
+        assert_eq!(captured.sender, ":1.5");
+        assert_eq!(captured.signature, "suana{sv}");
+        assert_eq!(captured.args, "'power', 7, [1, -2], {'on': <true>}");
+
+        let rebuilt = captured.to_message().unwrap();
+        let header = rebuilt.header();
+        assert_eq!(header.path().unwrap().as_str(), "/org/example");
+        assert_eq!(header.interface().unwrap().as_str(), "org.example.Device");
+        assert_eq!(header.member().unwrap().as_str(), "Changed");
+        assert_eq!(
+            rebuilt.body().signature().to_string(),
+            signal.body().signature().to_string()
+        );
+        assert_eq!(body_args(&rebuilt), body_args(&signal));
+
+        let call = &loaded[1];
+        assert_eq!(call.msg_type, "method_call");
+        assert_eq!(call.destination, "org.example");
+        assert!(call.args.is_empty() && call.signature.is_empty());
+        let rebuilt = call.to_message().unwrap();
+        assert_eq!(
+            rebuilt.header().destination().unwrap().as_str(),
+            "org.example"
+        );
+    }
+
+    #[test]
+    fn only_signals_and_calls_are_sent_again() {
+        let reply: CapturedMessage = serde_json::from_str(
+            r#"{"timestamp":1.0,"bus":"session","type":"method_return","serial":2,"reply_serial":1}"#,
+        )
+        .unwrap();
+        assert!(reply.to_message().is_err());
+    }
+}
@@ -0,0 +1,532 @@
+use crate::bus::{BusType, Item};
+use crate::introspect::IntrospectionCache;
+use anyhow::{bail, Result};
+use std::fmt::Write;
+use tui_input::Input;
+use zbus::message::Type;
+use zbus::zvariant::Signature;
+use zbus::{fdo::DBusProxy, Connection};
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// Interface of a service a proxy is generated for.
+pub struct ProxyTarget {
+    pub bus: BusType,
+    pub destination: String,
+    pub path: String,
+    pub interface: String,
+}
+
+/// State of the popup that writes a generated proxy to a file.
+#[derive(Default)]
+pub struct ProxyExport {
+    pub code: String,
+    /// Path of the file to write.
+    pub input: Input,
+    pub scroll: u16,
+    pub error: String,
+}
+
+impl ProxyExport {
+    /// Generates the proxy and proposes a file name derived from the interface.
+    pub async fn open(
+        &mut self,
+        conn: &Connection,
+        cache: &mut IntrospectionCache,
+        target: ProxyTarget,
+        items: &[Item],
+    ) -> Result<()> {
+        self.code = generate(conn, cache, &target, items).await?;
+        self.input = Input::from(format!(
+            "{}_proxy.rs",
+            snake_case(&trait_name(&target.interface))
+        ));
+        self.scroll = 0;
+        self.error.clear();
+        Ok(())
+    }
+
+    /// Writes the code to the entered path and returns a status message.
+    pub async fn write(&mut self) -> Result<String> {
+        let path = self.input.value().trim();
+        if path.is_empty() {
+            bail!("A file path is required");
+        }
+        tokio::fs::write(path, &self.code).await?;
+        Ok(format!(
+            "Wrote {} lines to {}",
+            self.code.lines().count(),
+            path
+        ))
+    }
+}
+
+struct ArgSpec {
+    name: String,
+    signature: Signature,
+}
+
+struct MethodSpec {
+    name: String,
+    inputs: Vec<ArgSpec>,
+    /// `None` when no reply was captured, so the return type is not known.
+    outputs: Option<Vec<ArgSpec>>,
+}
+
+struct SignalSpec {
+    name: String,
+    args: Vec<ArgSpec>,
+}
+
+struct PropertySpec {
+    name: String,
+    signature: Signature,
+    /// `None` when the access is not known.
+    writable: Option<bool>,
+}
+
+#[derive(Default)]
+struct InterfaceSpec {
+    methods: Vec<MethodSpec>,
+    signals: Vec<SignalSpec>,
+    properties: Vec<PropertySpec>,
+}
+
+/// Generates a `#[zbus::proxy]` trait for the target interface, from introspection when the
+/// service supports it, and otherwise from the calls, replies and signals that were captured.
+pub async fn generate(
+    conn: &Connection,
+    cache: &mut IntrospectionCache,
+    target: &ProxyTarget,
+    items: &[Item],
+) -> Result<String> {
+    if target.interface.is_empty() {
+        bail!("No interface to generate a proxy for");
+    }
+
+    let introspected = match cache
+        .get(conn, target.bus, &target.destination, &target.path)
+        .await
+    {
+        Some(node) => node
+            .interfaces()
+            .iter()
+            .find(|iface| iface.name().as_str() == target.interface)
+            .map(|iface| InterfaceSpec {
+                methods: iface
+                    .methods()
+                    .iter()
+                    .map(|method| {
+                        let (outputs, inputs): (Vec<_>, Vec<_>) = method
+                            .args()
+                            .iter()
+                            .partition(|arg| arg.direction() == Some(zbus_xml::ArgDirection::Out));
+                        let to_specs = |args: Vec<&zbus_xml::Arg>| {
+                            args.iter()
+                                .enumerate()
+                                .map(|(i, arg)| ArgSpec {
+                                    name: arg
+                                        .name()
+                                        .map_or_else(|| format!("arg{}", i), str::to_string),
+                                    signature: arg.ty().inner().clone(),
+                                })
+                                .collect::<Vec<_>>()
+                        };
+                        MethodSpec {
+                            name: method.name().to_string(),
+                            inputs: to_specs(inputs),
+                            outputs: Some(to_specs(outputs)),
+                        }
+                    })
+                    .collect(),
+                signals: iface
+                    .signals()
+                    .iter()
+                    .map(|signal| SignalSpec {
+                        name: signal.name().to_string(),
+                        args: signal
+                            .args()
+                            .iter()
+                            .enumerate()
+                            .map(|(i, arg)| ArgSpec {
+                                name: arg
+                                    .name()
+                                    .map_or_else(|| format!("arg{}", i), str::to_string),
+                                signature: arg.ty().inner().clone(),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                properties: iface
+                    .properties()
+                    .iter()
+                    .map(|property| PropertySpec {
+                        name: property.name().to_string(),
+                        signature: property.ty().inner().clone(),
+                        writable: Some(property.access().write()),
+                    })
+                    .collect(),
+            }),
+        None => None,
+    };
+
+    let (spec, source) = match introspected {
+        Some(spec) => (spec, "introspection"),
+        None => {
+            // Signals are sent from the unique name, calls may address the well-known one
+            let owner = if target.destination.starts_with(':') {
+                target.destination.to_string()
+            } else {
+                let proxy = DBusProxy::new(conn).await?;
+                match target.destination.as_str().try_into() {
+                    Ok(name) => proxy
+                        .get_name_owner(name)
+                        .await
+                        .map(|owner| owner.to_string())
+                        .unwrap_or_default(),
+                    Err(_) => String::new(),
+                }
+            };
+            let spec = infer(target, &owner, items);
+            if spec.methods.is_empty() && spec.signals.is_empty() && spec.properties.is_empty() {
+                bail!(
+                    "{} could not be introspected and no messages of {} were captured",
+                    target.destination,
+                    target.interface
+                );
+            }
+            (spec, "captured messages")
+        }
+    };
+    Ok(render(target, &spec, source))
+}
+
+/// Builds the interface from captured traffic. Argument names are not known, and properties
+/// are only known from `PropertiesChanged` signals.
+fn infer(target: &ProxyTarget, owner: &str, items: &[Item]) -> InterfaceSpec {
+    let is_peer = |name: &str| name == target.destination || (!owner.is_empty() && name == owner);
+    let mut spec = InterfaceSpec::default();
+
+    for item in items {
+        let Some(message) = &item.message else {
+            continue;
+        };
+        match item.msg_type {
+            Type::MethodCall if item.interface == target.interface && is_peer(&item.receiver) => {
+                let outputs = items
+                    .iter()
+                    .find(|reply| {
+                        reply.msg_type == Type::MethodReturn
+                            && reply.reply_serial == item.serial
+                            && reply.receiver == item.sender
+                    })
+                    .and_then(|reply| reply.message.as_ref())
+                    .map(|reply| unnamed_args(reply, "ret"));
+                match spec.methods.iter_mut().find(|m| m.name == item.member) {
+                    Some(method) => {
+                        if method.outputs.is_none() {
+                            method.outputs = outputs;
+                        }
+                    }
+                    None => spec.methods.push(MethodSpec {
+                        name: item.member.clone(),
+                        inputs: unnamed_args(message, "arg"),
+                        outputs,
+                    }),
+                }
+            }
+            Type::Signal
+                if item.interface == target.interface
+                    && is_peer(&item.sender)
+                    && !spec.signals.iter().any(|s| s.name == item.member) =>
+            {
+                spec.signals.push(SignalSpec {
+                    name: item.member.clone(),
+                    args: unnamed_args(message, "arg"),
+                });
+            }
+            Type::Signal
+                if item.interface == PROPERTIES_INTERFACE
+                    && item.member == "PropertiesChanged"
+                    && is_peer(&item.sender) =>
+            {
+                for change in item
+                    .property_changes
+                    .iter()
+                    .filter(|c| c.interface == target.interface)
+                {
+                    let Some(value) = &change.new else {
+                        continue;
+                    };
+                    if !spec.properties.iter().any(|p| p.name == change.name) {
+                        spec.properties.push(PropertySpec {
+                            name: change.name.clone(),
+                            signature: value.value_signature().clone(),
+                            writable: None,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    spec.methods.sort_by(|a, b| a.name.cmp(&b.name));
+    spec.signals.sort_by(|a, b| a.name.cmp(&b.name));
+    spec.properties.sort_by(|a, b| a.name.cmp(&b.name));
+    spec
+}
+
+fn unnamed_args(message: &zbus::Message, prefix: &str) -> Vec<ArgSpec> {
+    let signatures = match message.body().signature() {
+        Signature::Unit => Vec::new(),
+        Signature::Structure(fields) => fields.iter().cloned().collect(),
+        signature => vec![signature.clone()],
+    };
+    signatures
+        .into_iter()
+        .enumerate()
+        .map(|(i, signature)| ArgSpec {
+            name: format!("{}{}", prefix, i),
+            signature,
+        })
+        .collect()
+}
+
+fn render(target: &ProxyTarget, spec: &InterfaceSpec, source: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "//! Proxy for `{}`, generated by d-buddy from {}.",
+        target.interface, source
+    );
+    out.push('\n');
+    out.push_str("use zbus::proxy;\n\n");
+    let _ = writeln!(out, "#[proxy(");
+    let _ = writeln!(out, "    interface = \"{}\",", target.interface);
+    if !target.destination.starts_with(':') {
+        let _ = writeln!(out, "    default_service = \"{}\",", target.destination);
+    }
+    let _ = writeln!(out, "    default_path = \"{}\"", target.path);
+    let _ = writeln!(out, ")]");
+    let _ = writeln!(out, "pub trait {} {{", trait_name(&target.interface));
+
+    let mut first = true;
+    let mut separate = |out: &mut String| {
+        if !first {
+            out.push('\n');
+        }
+        first = false;
+    };
+
+    for method in &spec.methods {
+        separate(&mut out);
+        let _ = writeln!(out, "    /// {} method", method.name);
+        if let Some(attribute) = name_attribute(&method.name, &[]) {
+            let _ = writeln!(out, "    {}", attribute);
+        }
+        let output = match &method.outputs {
+            Some(outputs) => rust_tuple(outputs.iter().map(|a| &a.signature)),
+            None => {
+                let _ = writeln!(
+                    out,
+                    "    // No reply was captured, so the return type is a guess"
+                );
+                "()".to_string()
+            }
+        };
+        let _ = writeln!(
+            out,
+            "    fn {}({}) -> zbus::Result<{}>;",
+            identifier(&snake_case(&method.name)),
+            parameters(&method.inputs, true),
+            output
+        );
+    }
+
+    for signal in &spec.signals {
+        separate(&mut out);
+        let _ = writeln!(out, "    /// {} signal", signal.name);
+        match name_attribute(&signal.name, &["signal"]) {
+            Some(attribute) => {
+                let _ = writeln!(out, "    {}", attribute);
+            }
+            None => out.push_str("    #[zbus(signal)]\n"),
+        }
+        let _ = writeln!(
+            out,
+            "    fn {}({}) -> zbus::Result<()>;",
+            identifier(&snake_case(&signal.name)),
+            parameters(&signal.args, false)
+        );
+    }
+
+    for property in &spec.properties {
+        separate(&mut out);
+        let _ = writeln!(out, "    /// {} property", property.name);
+        let attribute =
+            name_attribute(&property.name, &["property"]).unwrap_or("#[zbus(property)]".into());
+        let name = snake_case(&property.name);
+        let _ = writeln!(out, "    {}", attribute);
+        let _ = writeln!(
+            out,
+            "    fn {}(&self) -> zbus::Result<{}>;",
+            identifier(&name),
+            rust_type(&property.signature, false)
+        );
+        if property.writable != Some(false) {
+            if property.writable.is_none() {
+                let _ = writeln!(
+                    out,
+                    "    // Access is not known, the property may be read-only"
+                );
+            }
+            let _ = writeln!(out, "    {}", attribute);
+            let _ = writeln!(
+                out,
+                "    fn set_{}(&self, value: {}) -> zbus::Result<()>;",
+                name,
+                rust_type(&property.signature, true)
+            );
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// A `#[zbus(...)]` attribute with the D-Bus name, if zbus would not derive it from the
+/// snake case method name by itself.
+fn name_attribute(name: &str, flags: &[&str]) -> Option<String> {
+    if pascal_case(&snake_case(name)) == name {
+        return None;
+    }
+    let mut parts: Vec<String> = flags.iter().map(|f| f.to_string()).collect();
+    parts.push(format!("name = \"{}\"", name));
+    Some(format!("#[zbus({})]", parts.join(", ")))
+}
+
+/// Parameters of a method or signal. Signal arguments are deserialized, so they are owned.
+fn parameters(args: &[ArgSpec], input: bool) -> String {
+    let mut parameters = vec!["&self".to_string()];
+    let mut seen: Vec<String> = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        let mut name = snake_case(&arg.name);
+        if name.is_empty() || seen.contains(&name) {
+            name = format!("arg{}", i);
+        }
+        seen.push(name.clone());
+        parameters.push(format!(
+            "{}: {}",
+            identifier(&name),
+            rust_type(&arg.signature, input)
+        ));
+    }
+    parameters.join(", ")
+}
+
+fn rust_tuple<'a>(signatures: impl Iterator<Item = &'a Signature>) -> String {
+    let types: Vec<String> = signatures.map(|s| rust_type(s, false)).collect();
+    match types.len() {
+        1 => types[0].clone(),
+        _ => format!("({})", types.join(", ")),
+    }
+}
+
+/// The Rust type for a D-Bus type, borrowed where that is what zbus takes for arguments.
+fn rust_type(signature: &Signature, input: bool) -> String {
+    match signature {
+        Signature::Unit => "()".to_string(),
+        Signature::U8 => "u8".to_string(),
+        Signature::Bool => "bool".to_string(),
+        Signature::I16 => "i16".to_string(),
+        Signature::U16 => "u16".to_string(),
+        Signature::I32 => "i32".to_string(),
+        Signature::U32 => "u32".to_string(),
+        Signature::I64 => "i64".to_string(),
+        Signature::U64 => "u64".to_string(),
+        Signature::F64 => "f64".to_string(),
+        Signature::Str if input => "&str".to_string(),
+        Signature::Str => "String".to_string(),
+        Signature::Signature if input => "&zbus::zvariant::Signature".to_string(),
+        Signature::Signature => "zbus::zvariant::Signature".to_string(),
+        Signature::ObjectPath if input => "&zbus::zvariant::ObjectPath<'_>".to_string(),
+        Signature::ObjectPath => "zbus::zvariant::OwnedObjectPath".to_string(),
+        Signature::Variant if input => "&zbus::zvariant::Value<'_>".to_string(),
+        Signature::Variant => "zbus::zvariant::OwnedValue".to_string(),
+        #[cfg(unix)]
+        Signature::Fd if input => "zbus::zvariant::Fd<'_>".to_string(),
+        #[cfg(unix)]
+        Signature::Fd => "zbus::zvariant::OwnedFd".to_string(),
+        Signature::Array(child) if input => format!("&[{}]", rust_type(child, true)),
+        Signature::Array(child) => format!("Vec<{}>", rust_type(child, false)),
+        Signature::Dict { key, value } => format!(
+            "std::collections::HashMap<{}, {}>",
+            rust_type(key, input),
+            rust_type(value, input)
+        ),
+        Signature::Structure(fields) => {
+            let types: Vec<String> = fields.iter().map(|f| rust_type(f, input)).collect();
+            match types.len() {
+                1 => format!("({},)", types[0]),
+                _ => format!("({})", types.join(", ")),
+            }
+        }
+    }
+}
+
+/// `org.freedesktop.NetworkManager.Device` becomes `Device`.
+fn trait_name(interface: &str) -> String {
+    let last = interface.rsplit('.').next().unwrap_or(interface);
+    pascal_case(&snake_case(last))
+}
+
+/// `GetNameOwner` becomes `get_name_owner`, the way zbus maps member names.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let previous_lower =
+                i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_ascii_digit());
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            let previous_upper = i > 0 && chars[i - 1].is_uppercase();
+            if previous_lower || (previous_upper && next_lower) {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            out.push(c);
+        } else {
+            out.push('_');
+        }
+    }
+    out
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Escapes Rust keywords as raw identifiers.
+fn identifier(name: &str) -> String {
+    const KEYWORDS: [&str; 37] = [
+        "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type",
+        "unsafe", "use", "where", "while",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name.to_string()
+    }
+}
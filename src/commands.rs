@@ -0,0 +1,286 @@
+use crate::body_tree::body_args;
+use crate::bus::{BusType, Item};
+use crate::gvariant;
+use anyhow::{bail, Result};
+use zbus::message::Type;
+use zbus::zvariant::{Signature, Value};
+
+/// Command line tools a captured call can be turned into.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CommandFormat {
+    Busctl,
+    Gdbus,
+    DbusSend,
+}
+
+impl CommandFormat {
+    pub const ALL: [CommandFormat; 3] = [
+        CommandFormat::Busctl,
+        CommandFormat::Gdbus,
+        CommandFormat::DbusSend,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommandFormat::Busctl => "busctl",
+            CommandFormat::Gdbus => "gdbus",
+            CommandFormat::DbusSend => "dbus-send",
+        }
+    }
+}
+
+/// A runnable command line that repeats the captured method call, arguments included.
+/// Calls on the isolated bus are sent to `isolated_address` as the session bus.
+pub fn command(
+    item: &Item,
+    format: CommandFormat,
+    isolated_address: Option<&str>,
+) -> Result<String> {
+    if item.msg_type != Type::MethodCall {
+        bail!("Only method calls can be turned into a command");
+    }
+    let Some(message) = &item.message else {
+        bail!("The message body was not captured");
+    };
+    if item.interface.is_empty() {
+        bail!(
+            "{} needs the interface, which the call does not name",
+            format.name()
+        );
+    }
+    let args = body_args(message);
+
+    let mut words: Vec<String> = Vec::new();
+    match format {
+        CommandFormat::Busctl => {
+            words.push("busctl".to_string());
+            words.push(bus_flag(item.stream_type, "--user").to_string());
+            words.extend([
+                "call".to_string(),
+                item.receiver.clone(),
+                item.path.clone(),
+                item.interface.clone(),
+                item.member.clone(),
+            ]);
+            if !args.is_empty() {
+                let signature: String = args
+                    .iter()
+                    .map(|arg| arg.value_signature().to_string())
+                    .collect();
+                words.push(signature);
+                for arg in &args {
+                    busctl_words(arg, &mut words)?;
+                }
+            }
+        }
+        CommandFormat::Gdbus => {
+            words.extend([
+                "gdbus".to_string(),
+                "call".to_string(),
+                bus_flag(item.stream_type, "--session").to_string(),
+                "--dest".to_string(),
+                item.receiver.clone(),
+                "--object-path".to_string(),
+                item.path.clone(),
+                "--method".to_string(),
+                format!("{}.{}", item.interface, item.member),
+            ]);
+            for arg in &args {
+                // Without a type annotation gdbus guesses from the text, e.g. int32 for numbers
+                let text = match arg.value_signature() {
+                    Signature::Str | Signature::I32 | Signature::F64 | Signature::Bool => {
+                        gvariant::format(arg)
+                    }
+                    signature => format!("@{} {}", signature, gvariant::format(arg)),
+                };
+                words.push(text);
+            }
+        }
+        CommandFormat::DbusSend => {
+            words.push("dbus-send".to_string());
+            words.push(bus_flag(item.stream_type, "--session").to_string());
+            if item.no_reply_expected {
+                words.push("--type=method_call".to_string());
+            } else {
+                words.push("--print-reply".to_string());
+            }
+            words.extend([
+                format!("--dest={}", item.receiver),
+                item.path.clone(),
+                format!("{}.{}", item.interface, item.member),
+            ]);
+            for arg in &args {
+                words.push(dbus_send_arg(arg)?);
+            }
+        }
+    }
+    let command = words
+        .iter()
+        .map(|word| shell_quote(word))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(match (item.stream_type, isolated_address) {
+        (BusType::Isolated, Some(address)) => format!(
+            "DBUS_SESSION_BUS_ADDRESS={} {}",
+            shell_quote(address),
+            command
+        ),
+        _ => command,
+    })
+}
+
+fn bus_flag(bus: BusType, session: &'static str) -> &'static str {
+    match bus {
+        BusType::System => "--system",
+        _ => session,
+    }
+}
+
+/// busctl takes arguments as separate words: arrays and dicts are preceded by their length,
+/// variants by their signature, and struct fields follow each other.
+fn busctl_words(value: &Value, words: &mut Vec<String>) -> Result<()> {
+    match value {
+        Value::Value(inner) => {
+            words.push(inner.value_signature().to_string());
+            busctl_words(inner, words)?;
+        }
+        Value::Array(array) => {
+            words.push(array.len().to_string());
+            for element in array.iter() {
+                busctl_words(element, words)?;
+            }
+        }
+        Value::Dict(dict) => {
+            let entries: Vec<_> = dict.iter().collect();
+            words.push(entries.len().to_string());
+            for (key, value) in entries {
+                busctl_words(key, words)?;
+                busctl_words(value, words)?;
+            }
+        }
+        Value::Structure(structure) => {
+            for field in structure.fields() {
+                busctl_words(field, words)?;
+            }
+        }
+        #[cfg(unix)]
+        Value::Fd(_) => bail!("File descriptors cannot be passed on the command line"),
+        value => words.push(basic_text(value)),
+    }
+    Ok(())
+}
+
+/// dbus-send only supports basic types, variants of them, and arrays and dicts of them.
+fn dbus_send_arg(value: &Value) -> Result<String> {
+    let unsupported = || {
+        anyhow::anyhow!(
+            "dbus-send cannot express {}, use busctl or gdbus instead",
+            value.value_signature()
+        )
+    };
+    match value {
+        Value::Value(inner) => Ok(format!(
+            "variant:{}",
+            dbus_send_basic(inner).ok_or_else(unsupported)?
+        )),
+        Value::Array(array) => {
+            let element = array.element_signature();
+            let type_name = dbus_send_type(element).ok_or_else(unsupported)?;
+            let elements: Vec<String> = array.iter().map(basic_text).collect();
+            if elements.is_empty() || elements.iter().any(|e| e.contains(',')) {
+                return Err(unsupported());
+            }
+            Ok(format!("array:{}:{}", type_name, elements.join(",")))
+        }
+        Value::Dict(dict) => {
+            let Signature::Dict {
+                key,
+                value: value_signature,
+            } = value.value_signature()
+            else {
+                return Err(unsupported());
+            };
+            let key_type = dbus_send_type(key.signature()).ok_or_else(unsupported)?;
+            let entries: Vec<(String, String)> = dict
+                .iter()
+                .map(|(k, v)| match v {
+                    Value::Value(inner) => dbus_send_basic(inner).map(|v| (basic_text(k), v)),
+                    v => Some((basic_text(k), basic_text(v))),
+                })
+                .collect::<Option<_>>()
+                .ok_or_else(unsupported)?;
+            let value_type = match value_signature.signature() {
+                Signature::Variant => "variant",
+                signature => dbus_send_type(signature).ok_or_else(unsupported)?,
+            };
+            if entries.is_empty()
+                || entries
+                    .iter()
+                    .any(|(k, v)| k.contains(',') || v.contains(','))
+            {
+                return Err(unsupported());
+            }
+            let pairs: Vec<String> = entries
+                .into_iter()
+                .map(|(k, v)| format!("{},{}", k, v))
+                .collect();
+            Ok(format!(
+                "dict:{}:{}:{}",
+                key_type,
+                value_type,
+                pairs.join(",")
+            ))
+        }
+        value => dbus_send_basic(value).ok_or_else(unsupported),
+    }
+}
+
+/// A basic value as `type:value`.
+fn dbus_send_basic(value: &Value) -> Option<String> {
+    let type_name = dbus_send_type(value.value_signature())?;
+    Some(format!("{}:{}", type_name, basic_text(value)))
+}
+
+fn dbus_send_type(signature: &Signature) -> Option<&'static str> {
+    Some(match signature {
+        Signature::Str => "string",
+        Signature::ObjectPath => "objpath",
+        Signature::U8 => "byte",
+        Signature::Bool => "boolean",
+        Signature::I16 => "int16",
+        Signature::U16 => "uint16",
+        Signature::I32 => "int32",
+        Signature::U32 => "uint32",
+        Signature::I64 => "int64",
+        Signature::U64 => "uint64",
+        Signature::F64 => "double",
+        _ => return None,
+    })
+}
+
+fn basic_text(value: &Value) -> String {
+    match value {
+        Value::Str(s) => s.to_string(),
+        Value::ObjectPath(p) => p.to_string(),
+        Value::Signature(s) => s.to_string(),
+        value => gvariant::format(value),
+    }
+}
+
+/// Quotes a word for POSIX shells, leaving plain words as they are.
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c));
+    if plain {
+        word.to_string()
+    } else if !word.contains('\'') {
+        format!("'{}'", word)
+    } else if !word.contains(['"', '$', '`', '\\', '!']) {
+        // GVariant text quotes strings with single quotes, which read better inside double quotes
+        format!("\"{}\"", word)
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
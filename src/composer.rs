@@ -0,0 +1,169 @@
+use crate::bus::{BusType, Item};
+use crate::gvariant;
+use crate::introspect::IntrospectionCache;
+use anyhow::{bail, Result};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tui_input::Input;
+use zbus::message::Type;
+use zbus::zvariant::Signature;
+use zbus::Connection;
+
+/// Labels of the composer input fields, in tab order.
+pub const COMPOSER_FIELDS: [&str; 5] = ["Destination", "Path", "Interface", "Member", "Arguments"];
+pub const ARGUMENTS_FIELD: usize = 4;
+
+/// An argument of the method being composed, as declared by introspection.
+#[derive(Debug, Clone)]
+pub struct ComposerArg {
+    pub name: Option<String>,
+    pub signature: Signature,
+}
+
+/// The method return or error reply of a sent call, or why no reply arrived.
+pub type Reply = std::result::Result<zbus::Message, String>;
+
+/// State of the method call composer popup.
+#[derive(Default)]
+pub struct Composer {
+    /// Bus of the message the call was prefilled from, `None` for the active bus.
+    pub bus: Option<BusType>,
+    pub inputs: [Input; 5],
+    pub focus: usize,
+    /// Input arguments of the method, `None` if the method could not be introspected.
+    pub args: Option<Vec<ComposerArg>>,
+    pub error: String,
+    /// Whether a call is on its way, its reply is picked up by `receive`.
+    pub sending: bool,
+    reply: Arc<Mutex<Option<Reply>>>,
+}
+
+impl Composer {
+    /// Prefills destination, path, interface and member from a captured message.
+    /// Calls are sent back to their destination, anything else to the peer that sent it.
+    pub fn prefill(&mut self, item: &Item) {
+        let destination = if item.msg_type == Type::MethodCall {
+            &item.receiver
+        } else {
+            &item.sender
+        };
+        let member = if item.msg_type == Type::Signal {
+            ""
+        } else {
+            item.member.as_str()
+        };
+        self.bus = Some(item.stream_type);
+        self.inputs = [
+            Input::from(destination.as_str()),
+            Input::from(item.path.as_str()),
+            Input::from(item.interface.as_str()),
+            Input::from(member),
+            Input::default(),
+        ];
+        self.focus = if member.is_empty() {
+            3
+        } else {
+            ARGUMENTS_FIELD
+        };
+        self.args = None;
+        self.error.clear();
+    }
+
+    pub fn value(&self, field: usize) -> &str {
+        self.inputs[field].value().trim()
+    }
+
+    pub fn focused_input(&mut self) -> &mut Input {
+        &mut self.inputs[self.focus]
+    }
+
+    /// Looks up the input arguments of the composed method through introspection.
+    pub async fn refresh_signature(
+        &mut self,
+        conn: &Connection,
+        bus: BusType,
+        cache: &mut IntrospectionCache,
+    ) {
+        self.args = None;
+        let (destination, path, interface, member) =
+            (self.value(0), self.value(1), self.value(2), self.value(3));
+        if destination.is_empty() || path.is_empty() || member.is_empty() {
+            return;
+        }
+        let Some(node) = cache.get(conn, bus, destination, path).await else {
+            return;
+        };
+
+        let method = node
+            .interfaces()
+            .iter()
+            .filter(|iface| interface.is_empty() || iface.name().as_str() == interface)
+            .flat_map(|iface| iface.methods())
+            .find(|method| method.name().as_str() == member);
+        if let Some(method) = method {
+            self.args = Some(
+                method
+                    .args()
+                    .iter()
+                    .filter(|arg| arg.direction() != Some(zbus_xml::ArgDirection::Out))
+                    .map(|arg| ComposerArg {
+                        name: arg.name().map(str::to_string),
+                        signature: arg.ty().inner().clone(),
+                    })
+                    .collect(),
+            );
+        }
+    }
+
+    /// Parses the arguments and sends the call in the background. Arguments that do not parse
+    /// are reported right away.
+    pub fn send(&mut self, conn: &Connection, timeout: Duration) -> Result<()> {
+        let (destination, path, interface, member) =
+            (self.value(0), self.value(1), self.value(2), self.value(3));
+        if destination.is_empty() || path.is_empty() || member.is_empty() {
+            bail!("Destination, path and member are required");
+        }
+
+        let signatures: Option<Vec<Signature>> = self
+            .args
+            .as_ref()
+            .map(|args| args.iter().map(|arg| arg.signature.clone()).collect());
+        let args = gvariant::parse_args(self.value(ARGUMENTS_FIELD), signatures.as_deref())?;
+
+        let conn = conn.clone();
+        let (destination, path, interface, member) = (
+            destination.to_string(),
+            path.to_string(),
+            interface.to_string(),
+            member.to_string(),
+        );
+        let reply = Arc::clone(&self.reply);
+        self.sending = true;
+        tokio::spawn(async move {
+            let result = crate::bus::call_method(
+                &conn,
+                &destination,
+                &path,
+                (!interface.is_empty()).then_some(interface.as_str()),
+                &member,
+                args,
+                timeout,
+            )
+            .await
+            .map_err(|e| e.to_string());
+            *reply.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        });
+        Ok(())
+    }
+
+    /// The reply of the call sent last, once it has arrived.
+    pub fn receive(&mut self) -> Option<Reply> {
+        let reply = self
+            .reply
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()?;
+        self.sending = false;
+        Some(reply)
+    }
+}
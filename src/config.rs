@@ -1,9 +1,16 @@
+use crate::decoders::{DecoderRegistry, EnumMapConfig};
+use anyhow::{Context, Result};
 use ratatui::prelude::*;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // color config
 pub struct Config {
     pub max_messages: usize,
     pub enable_debug_ui: bool,
+    pub method_call_timeout: Duration,
+    pub decoders: DecoderRegistry,
     pub color_dict: Color,
     pub color_struct: Color,
     pub color_default_stripe: Color,
@@ -11,6 +18,7 @@ pub struct Config {
     pub color_timestamp_details: Color,
     pub color_stream_session: Color,
     pub color_stream_system: Color,
+    pub color_stream_isolated: Color,
     pub color_sender_normal: Color,
     pub color_sender_details: Color,
     pub color_member_normal: Color,
@@ -27,6 +35,15 @@ pub struct Config {
     pub color_autofilter_value: Color,
     pub color_ticker: Color,
     pub color_grouping_header: Color,
+    pub color_call_timed_out: Color,
+    pub color_error_name: Color,
+    pub color_property_old: Color,
+    pub color_property_new: Color,
+    pub color_decoded: Color,
+    pub color_type_signal: Color,
+    pub color_type_call: Color,
+    pub color_type_return: Color,
+    pub color_fault: Color,
 }
 
 impl Default for Config {
@@ -34,6 +51,8 @@ impl Default for Config {
         Self {
             max_messages: 10_000,
             enable_debug_ui: false,
+            method_call_timeout: Duration::from_secs(25), // D-Bus default reply timeout
+            decoders: DecoderRegistry::with_builtins(),
             color_dict: Color::Rgb(20, 20, 40),   // Dark Blue
             color_struct: Color::Rgb(40, 20, 40), // Dark Magenta
             color_default_stripe: Color::DarkGray,
@@ -41,6 +60,7 @@ impl Default for Config {
             color_timestamp_details: Color::White,
             color_stream_session: Color::Cyan,
             color_stream_system: Color::LightMagenta,
+            color_stream_isolated: Color::LightGreen,
             color_sender_normal: Color::Green,
             color_sender_details: Color::White,
             color_member_normal: Color::Blue,
@@ -57,6 +77,49 @@ impl Default for Config {
             color_autofilter_value: Color::Green,
             color_ticker: Color::Rgb(255, 255, 0),
             color_grouping_header: Color::Yellow,
+            color_call_timed_out: Color::Red,
+            color_error_name: Color::LightRed,
+            color_property_old: Color::Red,
+            color_property_new: Color::Green,
+            color_decoded: Color::LightCyan,
+            color_type_signal: Color::Yellow,
+            color_type_call: Color::Cyan,
+            color_type_return: Color::Green,
+            color_fault: Color::LightMagenta,
         }
     }
 }
+
+/// Contents of the config file. Every section is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    decoders: Vec<EnumMapConfig>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/d-buddy/config.toml`, falling back to `~/.config/d-buddy/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("d-buddy").join("config.toml"))
+    }
+
+    /// Applies the settings of a config file on top of the defaults.
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let file: ConfigFile =
+            toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        for map in file.decoders {
+            let (interface, member) = (map.interface.clone(), map.member.clone());
+            self.decoders
+                .register(&interface, &member, Box::new(map.into_decoder()?));
+        }
+        Ok(())
+    }
+}
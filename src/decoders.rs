@@ -0,0 +1,590 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use zbus::zvariant::Value;
+
+/// Matches every member of an interface when used as the member of a registration.
+pub const ANY_MEMBER: &str = "*";
+
+/// Gives symbolic names to the values of a D-Bus member.
+pub trait Decoder: Send + Sync {
+    /// Symbolic name of a value. `arg` is the argument index for method and signal arguments
+    /// and `None` for property values, where the member is the property name.
+    fn decode(&self, arg: Option<usize>, value: &Value) -> Option<String>;
+}
+
+/// Decoders keyed by interface and member (or property) name.
+#[derive(Default)]
+pub struct DecoderRegistry {
+    decoders: HashMap<(String, String), Vec<Box<dyn Decoder>>>,
+}
+
+impl DecoderRegistry {
+    /// A registry with the decoders shipped with d-buddy.
+    pub fn with_builtins() -> Self {
+        let mut registry = DecoderRegistry::default();
+        register_systemd(&mut registry);
+        register_network_manager(&mut registry);
+        register_upower(&mut registry);
+        register_logind(&mut registry);
+        register_notifications(&mut registry);
+        registry
+    }
+
+    /// Registers a decoder. Decoders registered later take precedence.
+    pub fn register(&mut self, interface: &str, member: &str, decoder: Box<dyn Decoder>) {
+        self.decoders
+            .entry((interface.to_string(), member.to_string()))
+            .or_default()
+            .insert(0, decoder);
+    }
+
+    /// Symbolic name of argument `arg` of `interface.member`.
+    pub fn decode_arg(
+        &self,
+        interface: &str,
+        member: &str,
+        arg: usize,
+        value: &Value,
+    ) -> Option<String> {
+        self.decode(interface, member, Some(arg), value)
+    }
+
+    /// Symbolic name of the value of property `interface.property`.
+    pub fn decode_property(
+        &self,
+        interface: &str,
+        property: &str,
+        value: &Value,
+    ) -> Option<String> {
+        self.decode(interface, property, None, value)
+    }
+
+    fn decode(
+        &self,
+        interface: &str,
+        member: &str,
+        arg: Option<usize>,
+        value: &Value,
+    ) -> Option<String> {
+        let value = match value {
+            Value::Value(inner) => inner,
+            value => value,
+        };
+        [member, ANY_MEMBER]
+            .iter()
+            .filter_map(|m| self.decoders.get(&(interface.to_string(), m.to_string())))
+            .flatten()
+            .find_map(|decoder| decoder.decode(arg, value))
+    }
+}
+
+/// Maps integer (or boolean) values to names, either as a plain enum or as bit flags.
+pub struct EnumDecoder {
+    /// Argument the map applies to, `None` for property values.
+    pub arg: Option<usize>,
+    pub values: BTreeMap<i64, String>,
+    pub flags: bool,
+}
+
+impl EnumDecoder {
+    pub fn new(arg: Option<usize>, values: &[(i64, &str)]) -> Self {
+        EnumDecoder {
+            arg,
+            values: values
+                .iter()
+                .map(|(value, name)| (*value, name.to_string()))
+                .collect(),
+            flags: false,
+        }
+    }
+}
+
+impl Decoder for EnumDecoder {
+    fn decode(&self, arg: Option<usize>, value: &Value) -> Option<String> {
+        if arg != self.arg {
+            return None;
+        }
+        let number = match value {
+            Value::U8(v) => *v as i64,
+            Value::I16(v) => *v as i64,
+            Value::U16(v) => *v as i64,
+            Value::I32(v) => *v as i64,
+            Value::U32(v) => *v as i64,
+            Value::I64(v) => *v,
+            Value::U64(v) => i64::try_from(*v).ok()?,
+            Value::Bool(v) => *v as i64,
+            _ => return None,
+        };
+
+        if !self.flags {
+            return self.values.get(&number).cloned();
+        }
+        if number == 0 {
+            return self.values.get(&0).cloned();
+        }
+        let mut names: Vec<String> = self
+            .values
+            .iter()
+            .filter(|(bit, _)| **bit != 0 && number & **bit == **bit)
+            .map(|(_, name)| name.clone())
+            .collect();
+        let known = self.values.keys().fold(0, |acc, bit| acc | bit);
+        if number & !known != 0 {
+            names.push(format!("{:#x}", number & !known));
+        }
+        Some(names.join("|"))
+    }
+}
+
+/// Turns escaped systemd unit object paths such as `/org/freedesktop/systemd1/unit/dbus_2eservice`
+/// back into unit names.
+struct SystemdUnitPathDecoder;
+
+impl Decoder for SystemdUnitPathDecoder {
+    fn decode(&self, _arg: Option<usize>, value: &Value) -> Option<String> {
+        let Value::ObjectPath(path) = value else {
+            return None;
+        };
+        let escaped = path
+            .as_str()
+            .strip_prefix("/org/freedesktop/systemd1/unit/")?;
+        let bytes = escaped.as_bytes();
+        let mut name = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'_' && i + 2 < bytes.len() {
+                if let Ok(byte) = u8::from_str_radix(&escaped[i + 1..i + 3], 16) {
+                    name.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+            name.push(bytes[i]);
+            i += 1;
+        }
+        String::from_utf8(name).ok()
+    }
+}
+
+/// A user defined enum map from the `[[decoders]]` tables of the config file.
+#[derive(Debug, Deserialize)]
+pub struct EnumMapConfig {
+    pub interface: String,
+    /// Method or signal name, property name, or `*` for every member of the interface.
+    pub member: String,
+    /// Argument index. Leave out for property values.
+    pub arg: Option<usize>,
+    #[serde(default)]
+    pub flags: bool,
+    pub values: BTreeMap<String, String>,
+}
+
+impl EnumMapConfig {
+    pub fn into_decoder(self) -> anyhow::Result<EnumDecoder> {
+        let values = self
+            .values
+            .into_iter()
+            .map(|(value, name)| {
+                let number = match value.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => value.parse(),
+                }
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "Decoder for {}.{}: '{}' is not an integer",
+                        self.interface,
+                        self.member,
+                        value
+                    )
+                })?;
+                Ok((number, name))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(EnumDecoder {
+            arg: self.arg,
+            values,
+            flags: self.flags,
+        })
+    }
+}
+
+fn register_systemd(registry: &mut DecoderRegistry) {
+    registry.register(
+        "org.freedesktop.systemd1.Manager",
+        ANY_MEMBER,
+        Box::new(SystemdUnitPathDecoder),
+    );
+    // siginfo si_code of the main process exit
+    registry.register(
+        "org.freedesktop.systemd1.Service",
+        "ExecMainCode",
+        Box::new(EnumDecoder::new(
+            None,
+            &[
+                (1, "exited"),
+                (2, "killed"),
+                (3, "dumped"),
+                (4, "trapped"),
+                (5, "stopped"),
+                (6, "continued"),
+            ],
+        )),
+    );
+}
+
+fn register_network_manager(registry: &mut DecoderRegistry) {
+    const NM_STATE: &[(i64, &str)] = &[
+        (0, "UNKNOWN"),
+        (10, "ASLEEP"),
+        (20, "DISCONNECTED"),
+        (30, "DISCONNECTING"),
+        (40, "CONNECTING"),
+        (50, "CONNECTED_LOCAL"),
+        (60, "CONNECTED_SITE"),
+        (70, "CONNECTED_GLOBAL"),
+    ];
+    const NM_DEVICE_STATE: &[(i64, &str)] = &[
+        (0, "UNKNOWN"),
+        (10, "UNMANAGED"),
+        (20, "UNAVAILABLE"),
+        (30, "DISCONNECTED"),
+        (40, "PREPARE"),
+        (50, "CONFIG"),
+        (60, "NEED_AUTH"),
+        (70, "IP_CONFIG"),
+        (80, "IP_CHECK"),
+        (90, "SECONDARIES"),
+        (100, "ACTIVATED"),
+        (110, "DEACTIVATING"),
+        (120, "FAILED"),
+    ];
+    const NM_ACTIVE_CONNECTION_STATE: &[(i64, &str)] = &[
+        (0, "UNKNOWN"),
+        (1, "ACTIVATING"),
+        (2, "ACTIVATED"),
+        (3, "DEACTIVATING"),
+        (4, "DEACTIVATED"),
+    ];
+
+    let nm = "org.freedesktop.NetworkManager";
+    registry.register(
+        nm,
+        "StateChanged",
+        Box::new(EnumDecoder::new(Some(0), NM_STATE)),
+    );
+    registry.register(nm, "State", Box::new(EnumDecoder::new(None, NM_STATE)));
+    registry.register(
+        nm,
+        "Connectivity",
+        Box::new(EnumDecoder::new(
+            None,
+            &[
+                (0, "UNKNOWN"),
+                (1, "NONE"),
+                (2, "PORTAL"),
+                (3, "LIMITED"),
+                (4, "FULL"),
+            ],
+        )),
+    );
+
+    let device = "org.freedesktop.NetworkManager.Device";
+    for arg in [0, 1] {
+        registry.register(
+            device,
+            "StateChanged",
+            Box::new(EnumDecoder::new(Some(arg), NM_DEVICE_STATE)),
+        );
+    }
+    registry.register(
+        device,
+        "State",
+        Box::new(EnumDecoder::new(None, NM_DEVICE_STATE)),
+    );
+
+    let active = "org.freedesktop.NetworkManager.Connection.Active";
+    registry.register(
+        active,
+        "StateChanged",
+        Box::new(EnumDecoder::new(Some(0), NM_ACTIVE_CONNECTION_STATE)),
+    );
+    registry.register(
+        active,
+        "State",
+        Box::new(EnumDecoder::new(None, NM_ACTIVE_CONNECTION_STATE)),
+    );
+}
+
+fn register_upower(registry: &mut DecoderRegistry) {
+    let device = "org.freedesktop.UPower.Device";
+    registry.register(
+        device,
+        "State",
+        Box::new(EnumDecoder::new(
+            None,
+            &[
+                (0, "Unknown"),
+                (1, "Charging"),
+                (2, "Discharging"),
+                (3, "Empty"),
+                (4, "Fully charged"),
+                (5, "Pending charge"),
+                (6, "Pending discharge"),
+            ],
+        )),
+    );
+    registry.register(
+        device,
+        "Type",
+        Box::new(EnumDecoder::new(
+            None,
+            &[
+                (0, "Unknown"),
+                (1, "Line Power"),
+                (2, "Battery"),
+                (3, "Ups"),
+                (4, "Monitor"),
+                (5, "Mouse"),
+                (6, "Keyboard"),
+                (7, "Pda"),
+                (8, "Phone"),
+            ],
+        )),
+    );
+    registry.register(
+        device,
+        "WarningLevel",
+        Box::new(EnumDecoder::new(
+            None,
+            &[
+                (0, "Unknown"),
+                (1, "None"),
+                (2, "Discharging"),
+                (3, "Low"),
+                (4, "Critical"),
+                (5, "Action"),
+            ],
+        )),
+    );
+    registry.register(
+        device,
+        "BatteryLevel",
+        Box::new(EnumDecoder::new(
+            None,
+            &[
+                (0, "Unknown"),
+                (1, "None"),
+                (3, "Low"),
+                (4, "Critical"),
+                (6, "Normal"),
+                (7, "High"),
+                (8, "Full"),
+            ],
+        )),
+    );
+    registry.register(
+        device,
+        "Technology",
+        Box::new(EnumDecoder::new(
+            None,
+            &[
+                (0, "Unknown"),
+                (1, "Lithium ion"),
+                (2, "Lithium polymer"),
+                (3, "Lithium iron phosphate"),
+                (4, "Lead acid"),
+                (5, "Nickel cadmium"),
+                (6, "Nickel metal hydride"),
+            ],
+        )),
+    );
+}
+
+fn register_logind(registry: &mut DecoderRegistry) {
+    let manager = "org.freedesktop.login1.Manager";
+    registry.register(
+        manager,
+        "PrepareForSleep",
+        Box::new(EnumDecoder::new(
+            Some(0),
+            &[(0, "resuming"), (1, "going to sleep")],
+        )),
+    );
+    registry.register(
+        manager,
+        "PrepareForShutdown",
+        Box::new(EnumDecoder::new(
+            Some(0),
+            &[(0, "shutdown cancelled"), (1, "shutting down")],
+        )),
+    );
+}
+
+fn register_notifications(registry: &mut DecoderRegistry) {
+    let notifications = "org.freedesktop.Notifications";
+    registry.register(
+        notifications,
+        "NotificationClosed",
+        Box::new(EnumDecoder::new(
+            Some(1),
+            &[
+                (1, "expired"),
+                (2, "dismissed by user"),
+                (3, "closed by CloseNotification"),
+                (4, "undefined"),
+            ],
+        )),
+    );
+    registry.register(
+        notifications,
+        "Notify",
+        Box::new(EnumDecoder::new(
+            Some(7),
+            &[(-1, "server default"), (0, "never expires")],
+        )),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::ObjectPath;
+
+    fn flags() -> EnumDecoder {
+        EnumDecoder {
+            flags: true,
+            ..EnumDecoder::new(
+                Some(0),
+                &[(0, "none"), (1, "read"), (2, "write"), (8, "exec")],
+            )
+        }
+    }
+
+    fn config(values: &[(&str, &str)]) -> EnumMapConfig {
+        EnumMapConfig {
+            interface: "org.example.Device".to_string(),
+            member: "State".to_string(),
+            arg: None,
+            flags: false,
+            values: values
+                .iter()
+                .map(|(value, name)| (value.to_string(), name.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn enum_values_by_argument() {
+        let decoder = EnumDecoder::new(Some(1), &[(0, "off"), (1, "on")]);
+        assert_eq!(
+            decoder.decode(Some(1), &Value::U32(1)),
+            Some("on".to_string())
+        );
+        assert_eq!(
+            decoder.decode(Some(1), &Value::Bool(false)),
+            Some("off".to_string())
+        );
+        assert_eq!(decoder.decode(Some(1), &Value::U32(2)), None);
+        assert_eq!(decoder.decode(Some(0), &Value::U32(1)), None);
+        assert_eq!(decoder.decode(Some(1), &Value::from("on")), None);
+    }
+
+    #[test]
+    fn flags_are_joined_with_unknown_bits_in_hex() {
+        let decoder = flags();
+        assert_eq!(
+            decoder.decode(Some(0), &Value::U32(0)),
+            Some("none".to_string())
+        );
+        assert_eq!(
+            decoder.decode(Some(0), &Value::U32(3)),
+            Some("read|write".to_string())
+        );
+        assert_eq!(
+            decoder.decode(Some(0), &Value::U32(1 | 8 | 0x30)),
+            Some("read|exec|0x30".to_string())
+        );
+        assert_eq!(
+            decoder.decode(Some(0), &Value::U32(4)),
+            Some("0x4".to_string())
+        );
+    }
+
+    #[test]
+    fn config_keys_are_decimal_or_hex() {
+        let decoder = config(&[("0x10", "sixteen"), ("3", "three"), ("-1", "unknown")])
+            .into_decoder()
+            .unwrap();
+        assert_eq!(decoder.values.get(&16), Some(&"sixteen".to_string()));
+        assert_eq!(decoder.values.get(&3), Some(&"three".to_string()));
+        assert_eq!(decoder.values.get(&-1), Some(&"unknown".to_string()));
+    }
+
+    #[test]
+    fn invalid_config_keys_are_errors() {
+        for key in ["three", "0xzz", "1.5", ""] {
+            let error = config(&[(key, "name")]).into_decoder().err().unwrap();
+            assert!(error.to_string().contains("is not an integer"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn systemd_unit_paths_are_unescaped() {
+        let decode = |path: &str| {
+            SystemdUnitPathDecoder.decode(
+                None,
+                &Value::ObjectPath(ObjectPath::try_from(path).unwrap()),
+            )
+        };
+        assert_eq!(
+            decode("/org/freedesktop/systemd1/unit/dbus_2eservice"),
+            Some("dbus.service".to_string())
+        );
+        assert_eq!(
+            decode("/org/freedesktop/systemd1/unit/systemd_2dlogind_2eservice"),
+            Some("systemd-logind.service".to_string())
+        );
+        assert_eq!(
+            decode("/org/freedesktop/systemd1/unit/getty_40tty1_2eservice"),
+            Some("getty@tty1.service".to_string())
+        );
+        assert_eq!(decode("/org/freedesktop/systemd1/job/42"), None);
+        assert_eq!(
+            SystemdUnitPathDecoder.decode(None, &Value::from("dbus_2eservice")),
+            None
+        );
+    }
+
+    #[test]
+    fn registry_prefers_later_decoders_and_any_member() {
+        let mut registry = DecoderRegistry::default();
+        registry.register(
+            "org.example.Device",
+            ANY_MEMBER,
+            Box::new(EnumDecoder::new(None, &[(1, "any")])),
+        );
+        registry.register(
+            "org.example.Device",
+            "State",
+            Box::new(EnumDecoder::new(None, &[(1, "first")])),
+        );
+        registry.register(
+            "org.example.Device",
+            "State",
+            Box::new(EnumDecoder::new(None, &[(1, "second")])),
+        );
+        let value = Value::Value(Box::new(Value::U32(1)));
+        assert_eq!(
+            registry.decode_property("org.example.Device", "State", &value),
+            Some("second".to_string())
+        );
+        assert_eq!(
+            registry.decode_property("org.example.Device", "Other", &value),
+            Some("any".to_string())
+        );
+        assert_eq!(
+            registry.decode_arg("org.example.Device", "State", 0, &value),
+            None
+        );
+    }
+}
@@ -1,7 +1,11 @@
-use crate::bus::BusType;
+use crate::body_tree::BodyTree;
+use crate::bus::{BusType, Item};
+use crate::codegen::ProxyTarget;
+use crate::commands::CommandFormat;
 use crate::config::Config;
-use crate::state::{App, Mode};
-use anyhow::Result;
+use crate::introspect::MemberArgs;
+use crate::state::{App, DetailTab, Mode};
+use anyhow::{bail, Result};
 use arboard::Clipboard;
 use crossterm::event::{Event, KeyCode};
 use ratatui::prelude::*;
@@ -10,7 +14,7 @@ use std::sync::{Arc, Mutex};
 
 use tokio::fs;
 use tui_input::{backend::crossterm as input_backend, Input};
-use zbus::zvariant::{Structure, Value};
+use zbus::message::Type;
 
 //check for user input/key presses
 pub async fn handle_event(
@@ -37,7 +41,10 @@ pub async fn handle_event(
                         app.stream = match app.stream {
                             BusType::Session => BusType::System,
                             BusType::System => BusType::Both,
-                            BusType::Both => BusType::Session,
+                            BusType::Both if app.messages.contains_key(&BusType::Isolated) => {
+                                BusType::Isolated
+                            }
+                            BusType::Both | BusType::Isolated => BusType::Session,
                         };
                         app.list_state.select(None); // Reset selection
                     }
@@ -61,135 +68,338 @@ pub async fn handle_event(
                     KeyCode::Char('f') => {
                         app.mode = Mode::Filtering;
                     }
-                    KeyCode::Up => {
-                        if !app.filtered_and_sorted_items.is_empty() {
-                            let i = match app.list_state.selected() {
-                                Some(i) => i.saturating_sub(1),
-                                None => 0,
-                            };
-                            app.list_state.select(Some(i));
-                            if app.show_details {
-                                update_detail_text(app, config);
+                    KeyCode::Char('H') => {
+                        app.hide_returns = !app.hide_returns;
+                        app.list_state.select(None);
+                    }
+                    KeyCode::Char('S') => {
+                        let file_name = format!(
+                            "d-buddy-{}.jsonl",
+                            chrono::Local::now().format("%Y%m%d-%H%M%S")
+                        );
+                        app.status_message = match crate::capture::save(
+                            std::path::Path::new(&file_name),
+                            &app.filtered_and_sorted_items,
+                        ) {
+                            Ok(count) => format!("Saved {} messages to {}", count, file_name),
+                            Err(e) => format!("Failed to save capture: {:#}", e),
+                        };
+                    }
+                    KeyCode::Char('F') => {
+                        if app.faults.proxying {
+                            app.mode = Mode::Faults;
+                            if app.faults.state.selected().is_none() {
+                                app.faults.state.select(Some(0));
                             }
+                        } else {
+                            app.status_message =
+                                "Fault rules only apply in proxy mode (d-buddy proxy)".to_string();
                         }
                     }
-                    KeyCode::Down => {
-                        if !app.filtered_and_sorted_items.is_empty() {
-                            let current_selected_before = app.list_state.selected();
-                            tracing::debug!(
-                                "Down: current_selected_before = {:?}",
-                                current_selected_before
-                            );
-
-                            let i = match current_selected_before {
-                                Some(val) => (val + 1).min(app.filtered_and_sorted_items.len() - 1),
-                                None => 0,
-                            };
-                            tracing::debug!("Down: calculated_i = {}", i);
-
-                            app.list_state.select(Some(i));
-                            tracing::debug!(
-                                "Down: current_selected_after_select = {:?}",
-                                app.list_state.selected()
+                    KeyCode::Char('L') => match &mut app.launch {
+                        Some(launch) if launch.summary.is_some() => {
+                            if launch.summary_state.selected().is_none() {
+                                launch.summary_state.select(Some(0));
+                            }
+                            app.mode = Mode::LaunchSummary;
+                        }
+                        Some(launch) => {
+                            app.status_message = format!(
+                                "{} is still running, the summary shows when it exits",
+                                launch.command[0]
                             );
-
-                            if app.show_details {
-                                update_detail_text(app, config);
+                        }
+                        None => {
+                            app.status_message =
+                                "No command launched, start one with d-buddy -- <command>"
+                                    .to_string();
+                        }
+                    },
+                    KeyCode::Char('E') => {
+                        app.only_errors = !app.only_errors;
+                        app.list_state.select(None);
+                    }
+                    KeyCode::Char('p') => {
+                        app.mode = Mode::PendingCalls;
+                        app.pending_calls_state.select(Some(0));
+                    }
+                    KeyCode::Char('e') => {
+                        app.mode = Mode::Errors;
+                        app.error_summaries_state.select(Some(0));
+                    }
+                    KeyCode::Char('b') => {
+                        let bus = app.active_bus();
+                        if let Some(conn) = app.connections.get(&bus) {
+                            match app.browser.open(conn, bus).await {
+                                Ok(()) => app.mode = Mode::Browser,
+                                Err(e) => {
+                                    app.status_message = format!("Failed to list names: {}", e)
+                                }
                             }
                         }
                     }
-                    KeyCode::Char('s') | KeyCode::Char(' ') => {
-                        if app.show_details {
-                            app.show_details = false;
+                    KeyCode::Char('n') => {
+                        let bus = app.active_bus();
+                        match open_names(app, bus) {
+                            Ok(()) => {
+                                app.show_details = false;
+                                app.mode = Mode::Names;
+                            }
+                            Err(e) => app.status_message = format!("Failed to list names: {}", e),
+                        }
+                    }
+                    KeyCode::Char('i') => {
+                        if let Some(item) = app
+                            .list_state
+                            .selected()
+                            .and_then(|i| app.filtered_and_sorted_items.get(i))
+                        {
+                            app.composer.prefill(item);
                         } else {
-                            update_detail_text(app, config);
-                            app.show_details = true;
+                            app.composer = Default::default();
                         }
+                        let bus = app.composer.bus.unwrap_or(app.active_bus());
+                        if let Some(conn) = app.connections.get(&bus) {
+                            app.composer
+                                .refresh_signature(conn, bus, &mut app.introspection_cache)
+                                .await;
+                        }
+                        app.show_details = false;
+                        app.mode = Mode::Composer;
                     }
-                    KeyCode::Esc => {
-                        if app.show_details {
-                            app.show_details = false;
+                    KeyCode::Char('R') => {
+                        if let Some(item) = app
+                            .list_state
+                            .selected()
+                            .and_then(|i| app.filtered_and_sorted_items.get(i))
+                        {
+                            match app.repeater.open(item) {
+                                Ok(()) => {
+                                    app.show_details = false;
+                                    app.mode = Mode::Repeater;
+                                }
+                                Err(e) => app.status_message = e.to_string(),
+                            }
                         }
                     }
-                    KeyCode::Char('r') => {
-                        if let Some(selected) = app.list_state.selected() {
-                            if let Some(item) = app.filtered_and_sorted_items.get(selected) {
-                                let bus_type = match app.stream {
-                                    BusType::Session => "--session",
-                                    BusType::System => "--system",
-                                    BusType::Both => "--session", // Default
-                                };
-                                let command = format!(
-                                    "dbus-send {} --dest={} {} <interface>.<member>",
-                                    bus_type, item.sender, item.path
-                                );
-
-                                let clipboard_arc_clone = clipboard_arc.clone();
-                                let command_clone = command.clone();
-                                let result = tokio::task::spawn_blocking(move || {
-                                    clipboard_arc_clone.lock().unwrap().set_text(command_clone)
-                                })
-                                .await;
-
-                                match result {
-                                    Ok(Ok(_)) => {
-                                        app.status_message =
-                                            format!("Copied to clipboard: {}", command);
+                    KeyCode::Char('o') => {
+                        if let Some(item) = app
+                            .list_state
+                            .selected()
+                            .and_then(|i| app.filtered_and_sorted_items.get(i))
+                        {
+                            if let Some(conn) = app.connections.get(&item.stream_type) {
+                                match app.properties.open(conn, &app.introspection_cache, item) {
+                                    Ok(()) => {
+                                        app.show_details = false;
+                                        app.mode = Mode::Properties;
                                     }
-                                    Ok(Err(e)) => {
+                                    Err(e) => {
                                         app.status_message =
-                                            format!("Failed to copy to clipboard: {}", e);
+                                            format!("Failed to read properties: {}", e)
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    KeyCode::Char('m') => {
+                        if let Some(item) = app
+                            .list_state
+                            .selected()
+                            .and_then(|i| app.filtered_and_sorted_items.get(i))
+                        {
+                            if let Some(conn) = app.connections.get(&item.stream_type) {
+                                match app.objects.open(conn, &app.introspection_cache, item) {
+                                    Ok(()) => {
+                                        app.show_details = false;
+                                        app.mode = Mode::Objects;
                                     }
                                     Err(e) => {
-                                        app.status_message = format!("Copy task failed: {}", e);
+                                        app.status_message =
+                                            format!("Failed to load managed objects: {}", e)
                                     }
                                 }
                             }
                         }
                     }
-                    KeyCode::Char('c') => {
-                        if app.show_details {
-                            let text_to_copy = app.detail_text.to_string();
-                            let file_path = "/tmp/d-buddy-details.txt";
-                            let file_write_status =
-                                match fs::write(file_path, text_to_copy.as_bytes()).await {
-                                    Ok(_) => format!("Saved to {}", file_path),
-                                    Err(e) => format!("Failed to save to file: {}", e),
-                                };
-
-                            let clipboard_arc_clone = clipboard_arc.clone();
-                            let result = tokio::task::spawn_blocking(move || {
-                                clipboard_arc_clone.lock().unwrap().set_text(text_to_copy)
-                            })
-                            .await;
-
-                            let clipboard_status = match result {
-                                Ok(Ok(_)) => "Copied to clipboard!".to_string(),
-                                Ok(Err(e)) => format!("Copy failed: {}", e),
-                                Err(e) => format!("Copy task failed: {}", e),
+                    KeyCode::Char('G') => {
+                        if let Some(item) = app
+                            .list_state
+                            .selected()
+                            .and_then(|i| app.filtered_and_sorted_items.get(i))
+                        {
+                            let destination = if item.msg_type == Type::MethodCall {
+                                item.receiver.clone()
+                            } else {
+                                item.sender.clone()
                             };
-                            app.status_message =
-                                format!("{} | {}", file_write_status, clipboard_status);
+                            let target = ProxyTarget {
+                                bus: item.stream_type,
+                                destination,
+                                path: item.path.clone(),
+                                interface: item.interface.clone(),
+                            };
+                            open_proxy_export(app, target).await;
                         }
                     }
-                    KeyCode::Char('j') => {
+                    KeyCode::Up if !app.filtered_and_sorted_items.is_empty() => {
+                        let i = match app.list_state.selected() {
+                            Some(i) => i.saturating_sub(1),
+                            None => 0,
+                        };
+                        app.list_state.select(Some(i));
                         if app.show_details {
-                            app.detail_scroll_request = Some(1);
+                            update_detail_text(app, config).await;
                         }
                     }
-                    KeyCode::Char('k') => {
+                    KeyCode::Down if !app.filtered_and_sorted_items.is_empty() => {
+                        let current_selected_before = app.list_state.selected();
+                        tracing::debug!(
+                            "Down: current_selected_before = {:?}",
+                            current_selected_before
+                        );
+
+                        let i = match current_selected_before {
+                            Some(val) => (val + 1).min(app.filtered_and_sorted_items.len() - 1),
+                            None => 0,
+                        };
+                        tracing::debug!("Down: calculated_i = {}", i);
+
+                        app.list_state.select(Some(i));
+                        tracing::debug!(
+                            "Down: current_selected_after_select = {:?}",
+                            app.list_state.selected()
+                        );
+
                         if app.show_details {
-                            app.detail_scroll_request = Some(-1);
+                            update_detail_text(app, config).await;
                         }
                     }
-                    KeyCode::PageDown => {
+                    KeyCode::Char('s') | KeyCode::Char(' ') => {
                         if app.show_details {
-                            app.detail_scroll_request = Some(10);
+                            app.show_details = false;
+                        } else {
+                            update_detail_text(app, config).await;
+                            app.show_details = true;
                         }
                     }
-                    KeyCode::PageUp => {
-                        if app.show_details {
-                            app.detail_scroll_request = Some(-10);
+                    KeyCode::Esc if app.show_details => {
+                        app.show_details = false;
+                    }
+                    KeyCode::Char('r') => {
+                        if let Some(item) = app
+                            .list_state
+                            .selected()
+                            .and_then(|i| app.filtered_and_sorted_items.get(i))
+                        {
+                            if item.msg_type == Type::MethodCall {
+                                app.command_choices = CommandFormat::ALL
+                                    .iter()
+                                    .map(|&format| {
+                                        let command = crate::commands::command(
+                                            item,
+                                            format,
+                                            app.isolated_address.as_deref(),
+                                        )
+                                        .map_err(|e| e.to_string());
+                                        (format, command)
+                                    })
+                                    .collect();
+                                app.command_choice_state.select(Some(0));
+                                app.mode = Mode::CommandFormat;
+                            } else {
+                                app.status_message =
+                                    "Only method calls can be copied as a command".to_string();
+                            }
+                        }
+                    }
+                    KeyCode::Char('c') if app.show_details => {
+                        // The header and, below it, the body from the tree
+                        let mut text_to_copy = app.detail_text.to_string();
+                        if !app.body_tree.roots.is_empty() {
+                            text_to_copy.push('\n');
+                            text_to_copy.push_str(&app.body_tree.to_text());
+                        }
+                        let file_path = "/tmp/d-buddy-details.txt";
+                        let file_write_status =
+                            match fs::write(file_path, text_to_copy.as_bytes()).await {
+                                Ok(_) => format!("Saved to {}", file_path),
+                                Err(e) => format!("Failed to save to file: {}", e),
+                            };
+
+                        let clipboard_arc_clone = clipboard_arc.clone();
+                        let result = tokio::task::spawn_blocking(move || {
+                            clipboard_arc_clone.lock().unwrap().set_text(text_to_copy)
+                        })
+                        .await;
+
+                        let clipboard_status = match result {
+                            Ok(Ok(_)) => "Copied to clipboard!".to_string(),
+                            Ok(Err(e)) => format!("Copy failed: {}", e),
+                            Err(e) => format!("Copy task failed: {}", e),
+                        };
+                        app.status_message =
+                            format!("{} | {}", file_write_status, clipboard_status);
+                    }
+                    KeyCode::Char('j')
+                    | KeyCode::Char('k')
+                    | KeyCode::PageDown
+                    | KeyCode::PageUp
+                        if app.show_details =>
+                    {
+                        let delta = match key.code {
+                            KeyCode::Char('j') => 1,
+                            KeyCode::Char('k') => -1,
+                            KeyCode::PageDown => 10,
+                            _ => -10,
+                        };
+                        if app.body_tree.roots.is_empty() || app.detail_tab == DetailTab::Wire {
+                            app.detail_scroll_request = Some(delta);
+                        } else {
+                            app.body_tree.move_cursor(delta);
+                        }
+                    }
+                    KeyCode::Char('w') if app.show_details => {
+                        app.detail_tab = match app.detail_tab {
+                            DetailTab::Body => DetailTab::Wire,
+                            DetailTab::Wire => DetailTab::Body,
+                        };
+                        app.detail_scroll = 0;
+                    }
+                    KeyCode::Right | KeyCode::Char('l') if app.show_details => {
+                        app.body_tree.expand();
+                    }
+                    KeyCode::Left | KeyCode::Char('h') if app.show_details => {
+                        app.body_tree.collapse();
+                    }
+                    KeyCode::Enter if app.show_details => {
+                        app.body_tree.toggle();
+                    }
+                    KeyCode::Char('y') | KeyCode::Char('Y') if app.show_details => {
+                        if let Some(node) = app.body_tree.selected() {
+                            let text = if key.code == KeyCode::Char('Y') {
+                                crate::body_tree::to_json(&node.value).to_string()
+                            } else {
+                                node.to_text()
+                            };
+                            app.status_message = copy_to_clipboard(&clipboard_arc, text).await;
+                        }
+                    }
+                    KeyCode::Char('v') if app.show_details => {
+                        // Narrow the list down to messages with the same value at this body path
+                        match app.body_tree.selected() {
+                            Some(node) if node.hexdump.is_none() => {
+                                let value = crate::body_tree::filter_text(&node.value);
+                                app.status_message =
+                                    format!("Filtering on {}={}", node.path, value);
+                                app.filter_criteria.insert(node.path.clone(), value);
+                                app.show_details = false;
+                            }
+                            Some(_) => {
+                                app.status_message =
+                                    "Select the byte array itself to filter on it".to_string();
+                            }
+                            None => {}
                         }
                     }
                     _ => {} // Ignore other keys
@@ -226,7 +436,7 @@ pub async fn handle_event(
                         if app.list_state.selected().is_some() {
                             app.mode = Mode::AutoFilterSelection;
                             // Ensure a selection is made when entering the autofilter selection mode
-                            // Default to the first option (sender)
+                            // Default to the first option (type)
                             app.autofilter_selection_state.select(Some(0));
                         }
                     }
@@ -243,13 +453,7 @@ pub async fn handle_event(
                 }
             }
             Mode::GroupingSelection => {
-                let all_grouping_options = [
-                    crate::bus::GroupingType::Sender,
-                    crate::bus::GroupingType::Member,
-                    crate::bus::GroupingType::Path,
-                    crate::bus::GroupingType::Serial,
-                    crate::bus::GroupingType::None,
-                ];
+                let all_grouping_options = crate::bus::GroupingType::ALL;
                 let max_index = all_grouping_options.len() - 1;
 
                 match key.code {
@@ -300,12 +504,10 @@ pub async fn handle_event(
                             }
 
                             // Sort grouping keys for consistent order (e.g., Sender, Member, Path, Serial)
-                            app.grouping_keys.sort_by_key(|&gt| match gt {
-                                crate::bus::GroupingType::Sender => 0,
-                                crate::bus::GroupingType::Member => 1,
-                                crate::bus::GroupingType::Path => 2,
-                                crate::bus::GroupingType::Serial => 3,
-                                crate::bus::GroupingType::None => 4,
+                            app.grouping_keys.sort_by_key(|&gt| {
+                                crate::bus::GroupingType::ALL
+                                    .iter()
+                                    .position(|&option| option == gt)
                             });
                             // Make sure None is always at the end if it's present with other keys
                             if app.grouping_keys.len() > 1
@@ -325,8 +527,7 @@ pub async fn handle_event(
                 }
             }
             Mode::AutoFilterSelection => {
-                let autofilter_options = ["sender", "member", "path", "serial", "reply_serial"];
-                let max_index = autofilter_options.len() - 1;
+                let max_index = crate::bus::FILTER_FIELDS.len() - 1;
 
                 match key.code {
                     KeyCode::Up => {
@@ -351,15 +552,10 @@ pub async fn handle_event(
                                 if let Some(item) =
                                     app.filtered_and_sorted_items.get(selected_message_index)
                                 {
-                                    let field_name = autofilter_options[selected_option_index];
-                                    let field_value: std::borrow::Cow<'_, str> = match field_name {
-                                        "sender" => item.sender_display(),
-                                        "member" => item.member.as_str().into(),
-                                        "path" => item.path.as_str().into(),
-                                        "serial" => item.serial.as_str().into(),
-                                        "reply_serial" => item.reply_serial.as_str().into(),
-                                        _ => "".into(),
-                                    };
+                                    let field_name =
+                                        crate::bus::FILTER_FIELDS[selected_option_index];
+                                    let field_value =
+                                        item.field_value(field_name).unwrap_or_default();
                                     app.input =
                                         Input::from(format!("{}={}", field_name, field_value));
                                 }
@@ -375,6 +571,495 @@ pub async fn handle_event(
                     _ => {} // Ignore other keys
                 }
             }
+            Mode::PendingCalls => match key.code {
+                KeyCode::Up => {
+                    let i = match app.pending_calls_state.selected() {
+                        Some(i) => i.saturating_sub(1),
+                        None => 0,
+                    };
+                    app.pending_calls_state.select(Some(i));
+                }
+                KeyCode::Down => {
+                    let max_index = app.pending_calls.len().saturating_sub(1);
+                    let i = match app.pending_calls_state.selected() {
+                        Some(i) => (i + 1).min(max_index),
+                        None => 0,
+                    };
+                    app.pending_calls_state.select(Some(i));
+                }
+                KeyCode::Enter => {
+                    if let Some(call) = app
+                        .pending_calls_state
+                        .selected()
+                        .and_then(|i| app.pending_calls.get(i))
+                    {
+                        app.thread_serial = Some(call.serial.clone());
+                        app.list_state.select(None);
+                        app.mode = Mode::ThreadView;
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('p') => {
+                    app.mode = Mode::Normal;
+                }
+                _ => {} // Ignore other keys
+            },
+            Mode::Errors => match key.code {
+                KeyCode::Up => {
+                    let i = match app.error_summaries_state.selected() {
+                        Some(i) => i.saturating_sub(1),
+                        None => 0,
+                    };
+                    app.error_summaries_state.select(Some(i));
+                }
+                KeyCode::Down => {
+                    let max_index = app.error_summaries.len().saturating_sub(1);
+                    let i = match app.error_summaries_state.selected() {
+                        Some(i) => (i + 1).min(max_index),
+                        None => 0,
+                    };
+                    app.error_summaries_state.select(Some(i));
+                }
+                KeyCode::Enter => {
+                    // The thread of the call serial holds both the originating call and the error
+                    if let Some(summary) = app
+                        .error_summaries_state
+                        .selected()
+                        .and_then(|i| app.error_summaries.get(i))
+                    {
+                        app.thread_serial = Some(summary.latest.reply_serial.clone());
+                        app.list_state.select(None);
+                        app.mode = Mode::ThreadView;
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('e') => {
+                    app.mode = Mode::Normal;
+                }
+                _ => {} // Ignore other keys
+            },
+            Mode::Names => {
+                if !app.status_message.is_empty() {
+                    app.status_message.clear();
+                }
+
+                match key.code {
+                    KeyCode::Up => {
+                        let i = match app.names.state.selected() {
+                            Some(i) => i.saturating_sub(1),
+                            None => 0,
+                        };
+                        app.names.state.select(Some(i));
+                    }
+                    KeyCode::Down => {
+                        let max_index = app.names.names.len().saturating_sub(1);
+                        let i = match app.names.state.selected() {
+                            Some(i) => (i + 1).min(max_index),
+                            None => 0,
+                        };
+                        app.names.state.select(Some(i));
+                    }
+                    KeyCode::Enter => {
+                        // Narrow the live list down to the messages from and to the connection
+                        if let Some(i) = app.names.state.selected() {
+                            let peer = app.names.peer_names(i).join(",");
+                            app.filter_criteria.clear();
+                            app.input.reset();
+                            app.filter_criteria.insert("peer".to_string(), peer);
+                            app.list_state.select(None);
+                            app.mode = Mode::Normal;
+                        }
+                    }
+                    KeyCode::Char('a') => {
+                        if let Some(bus) = app.names.bus {
+                            if let Some(conn) = app.connections.get(&bus) {
+                                if let Err(e) = app.names.start_selected(conn) {
+                                    app.status_message = format!("Failed to start: {}", e);
+                                }
+                            }
+                        }
+                    }
+                    KeyCode::Char('r') => {
+                        if let Some(bus) = app.names.bus {
+                            if let Err(e) = open_names(app, bus) {
+                                app.status_message = format!("Failed to list names: {}", e);
+                            }
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('n') => {
+                        app.mode = Mode::Normal;
+                    }
+                    _ => {} // Ignore other keys
+                }
+            }
+            Mode::Browser => {
+                if !app.status_message.is_empty() {
+                    app.status_message.clear();
+                }
+
+                match key.code {
+                    KeyCode::Up => {
+                        let i = match app.browser.state.selected() {
+                            Some(i) => i.saturating_sub(1),
+                            None => 0,
+                        };
+                        app.browser.state.select(Some(i));
+                    }
+                    KeyCode::Down => {
+                        let max_index = app.browser.nodes.len().saturating_sub(1);
+                        let i = match app.browser.state.selected() {
+                            Some(i) => (i + 1).min(max_index),
+                            None => 0,
+                        };
+                        app.browser.state.select(Some(i));
+                    }
+                    KeyCode::Enter | KeyCode::Right => {
+                        let expanded = app.browser.selected().is_some_and(|node| node.expanded);
+                        if expanded && key.code == KeyCode::Enter {
+                            app.browser.collapse();
+                        } else if let Some(bus) = app.browser.bus {
+                            if let Some(conn) = app.connections.get(&bus) {
+                                if let Err(e) =
+                                    app.browser.expand(conn, &mut app.introspection_cache).await
+                                {
+                                    app.status_message = e.to_string();
+                                }
+                            }
+                        }
+                    }
+                    KeyCode::Left => {
+                        app.browser.collapse();
+                    }
+                    KeyCode::Char('f') => {
+                        // Narrow the live list down to the selected object, interface or member
+                        if let Some(node) = app.browser.selected() {
+                            if !node.path.is_empty() {
+                                let mut criteria = vec![("path", node.path.clone())];
+                                if !node.interface.is_empty() {
+                                    criteria.push(("interface", node.interface.clone()));
+                                }
+                                if !node.member.is_empty() {
+                                    criteria.push(("member", node.member.clone()));
+                                }
+                                app.filter_criteria.clear();
+                                app.input.reset();
+                                for (field, value) in criteria {
+                                    app.filter_criteria.insert(field.to_string(), value);
+                                }
+                                app.list_state.select(None);
+                                app.mode = Mode::Normal;
+                            }
+                        }
+                    }
+                    KeyCode::Char('G') => {
+                        let target =
+                            app.browser
+                                .bus
+                                .zip(app.browser.selected())
+                                .map(|(bus, node)| ProxyTarget {
+                                    bus,
+                                    destination: node.name.clone(),
+                                    path: node.path.clone(),
+                                    interface: node.interface.clone(),
+                                });
+                        match target {
+                            Some(target) if !target.interface.is_empty() => {
+                                open_proxy_export(app, target).await;
+                            }
+                            _ => {
+                                app.status_message =
+                                    "Select an interface or one of its members".to_string();
+                            }
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('b') => {
+                        app.mode = Mode::Normal;
+                    }
+                    _ => {} // Ignore other keys
+                }
+            }
+            Mode::Composer => match key.code {
+                KeyCode::Tab | KeyCode::Down | KeyCode::BackTab | KeyCode::Up => {
+                    let count = crate::composer::COMPOSER_FIELDS.len();
+                    let previous = app.composer.focus;
+                    app.composer.focus = match key.code {
+                        KeyCode::Tab | KeyCode::Down => (previous + 1) % count,
+                        _ => (previous + count - 1) % count,
+                    };
+                    // The signature depends on the other fields, look it up again once they are left
+                    if previous != crate::composer::ARGUMENTS_FIELD {
+                        let bus = app.composer.bus.unwrap_or(app.active_bus());
+                        if let Some(conn) = app.connections.get(&bus) {
+                            app.composer
+                                .refresh_signature(conn, bus, &mut app.introspection_cache)
+                                .await;
+                        }
+                    }
+                }
+                KeyCode::Enter => {
+                    let bus = app.composer.bus.unwrap_or(app.active_bus());
+                    if let Some(conn) = app.connections.get(&bus) {
+                        // One call at a time, the popup closes when its reply arrives
+                        if !app.composer.sending {
+                            if let Err(e) = app.composer.send(conn, config.method_call_timeout) {
+                                app.composer.error = e.to_string();
+                            }
+                        }
+                    }
+                }
+                KeyCode::Esc => {
+                    app.mode = Mode::Normal;
+                }
+                _ => {
+                    if let Some(req) = input_backend::to_input_request(&event) {
+                        app.composer.focused_input().handle(req);
+                        app.composer.error.clear();
+                    }
+                }
+            },
+            Mode::Repeater => match key.code {
+                KeyCode::Up => {
+                    let i = match app.repeater.history_state.selected() {
+                        Some(i) => i.saturating_sub(1),
+                        None => 0,
+                    };
+                    app.repeater.history_state.select(Some(i));
+                }
+                KeyCode::Down => {
+                    let max_index = app.repeater.history.len().saturating_sub(1);
+                    let i = match app.repeater.history_state.selected() {
+                        Some(i) => (i + 1).min(max_index),
+                        None => 0,
+                    };
+                    app.repeater.history_state.select(Some(i));
+                }
+                KeyCode::Enter => {
+                    // Resend on the bus the call was captured on
+                    let bus = app.repeater.call.as_ref().map(|call| call.stream_type);
+                    if let Some(conn) = bus.and_then(|bus| app.connections.get(&bus)) {
+                        app.repeater.send(conn, config.method_call_timeout);
+                    }
+                }
+                KeyCode::Esc => {
+                    app.mode = Mode::Normal;
+                }
+                _ => {
+                    if let Some(req) = input_backend::to_input_request(&event) {
+                        app.repeater.input.handle(req);
+                        app.repeater.error.clear();
+                    }
+                }
+            },
+            Mode::Properties if app.properties.editing.is_some() => match key.code {
+                KeyCode::Enter => {
+                    let bus = app.properties.bus;
+                    if let Some(conn) = bus.and_then(|bus| app.connections.get(&bus)) {
+                        // One value at a time, the editor closes once it is set
+                        if !app.properties.saving {
+                            if let Err(e) = app.properties.set(conn) {
+                                app.properties.error = e.to_string();
+                            }
+                        }
+                    }
+                }
+                KeyCode::Esc => {
+                    app.properties.editing = None;
+                    app.properties.error.clear();
+                }
+                _ => {
+                    if let Some(input) = app.properties.editing.as_mut() {
+                        if let Some(req) = input_backend::to_input_request(&event) {
+                            input.handle(req);
+                            app.properties.error.clear();
+                        }
+                    }
+                }
+            },
+            Mode::Properties => match key.code {
+                KeyCode::Up => {
+                    let i = match app.properties.state.selected() {
+                        Some(i) => i.saturating_sub(1),
+                        None => 0,
+                    };
+                    app.properties.state.select(Some(i));
+                }
+                KeyCode::Down => {
+                    let max_index = app.properties.entries.len().saturating_sub(1);
+                    let i = match app.properties.state.selected() {
+                        Some(i) => (i + 1).min(max_index),
+                        None => 0,
+                    };
+                    app.properties.state.select(Some(i));
+                }
+                KeyCode::Enter => {
+                    if let Err(e) = app.properties.start_edit() {
+                        app.properties.error = e.to_string();
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('o') => {
+                    app.properties.error.clear();
+                    app.mode = Mode::Normal;
+                }
+                _ => {} // Ignore other keys
+            },
+            Mode::Objects => match key.code {
+                KeyCode::Up => {
+                    app.objects.scroll = app.objects.scroll.saturating_sub(1);
+                }
+                KeyCode::Down => {
+                    app.objects.scroll = app.objects.scroll.saturating_add(1);
+                }
+                KeyCode::PageUp => {
+                    app.objects.scroll = app.objects.scroll.saturating_sub(10);
+                }
+                KeyCode::PageDown => {
+                    app.objects.scroll = app.objects.scroll.saturating_add(10);
+                }
+                KeyCode::Char('[') | KeyCode::Char(']')
+                    if !app.filtered_and_sorted_items.is_empty() =>
+                {
+                    // Scrub through the message list, showing the objects as they were at each message
+                    let max_index = app.filtered_and_sorted_items.len() - 1;
+                    let i = match (app.list_state.selected(), key.code) {
+                        (Some(i), KeyCode::Char('[')) => i.saturating_sub(1),
+                        (Some(i), _) => (i + 1).min(max_index),
+                        (None, _) => max_index,
+                    };
+                    app.list_state.select(Some(i));
+                    app.objects.at = Some(app.filtered_and_sorted_items[i].timestamp);
+                }
+                KeyCode::Char('l') => {
+                    app.objects.at = None;
+                }
+                KeyCode::Esc | KeyCode::Char('m') => {
+                    app.mode = Mode::Normal;
+                }
+                _ => {} // Ignore other keys
+            },
+            Mode::ProxyExport => match key.code {
+                KeyCode::Up => {
+                    app.proxy_export.scroll = app.proxy_export.scroll.saturating_sub(1);
+                }
+                KeyCode::Down => {
+                    app.proxy_export.scroll = app.proxy_export.scroll.saturating_add(1);
+                }
+                KeyCode::PageUp => {
+                    app.proxy_export.scroll = app.proxy_export.scroll.saturating_sub(10);
+                }
+                KeyCode::PageDown => {
+                    app.proxy_export.scroll = app.proxy_export.scroll.saturating_add(10);
+                }
+                KeyCode::Enter => match app.proxy_export.write().await {
+                    Ok(status) => {
+                        app.status_message = status;
+                        app.mode = Mode::Normal;
+                    }
+                    Err(e) => app.proxy_export.error = e.to_string(),
+                },
+                KeyCode::Esc => {
+                    app.mode = Mode::Normal;
+                }
+                _ => {
+                    if let Some(req) = input_backend::to_input_request(&event) {
+                        app.proxy_export.input.handle(req);
+                        app.proxy_export.error.clear();
+                    }
+                }
+            },
+            Mode::LaunchSummary => {
+                if let Some(launch) = &mut app.launch {
+                    let max_index = launch
+                        .summary
+                        .as_ref()
+                        .map_or(0, |lines| lines.len().saturating_sub(1));
+                    match key.code {
+                        KeyCode::Up => {
+                            let i = match launch.summary_state.selected() {
+                                Some(i) => i.saturating_sub(1),
+                                None => 0,
+                            };
+                            launch.summary_state.select(Some(i));
+                        }
+                        KeyCode::Down => {
+                            let i = match launch.summary_state.selected() {
+                                Some(i) => (i + 1).min(max_index),
+                                None => 0,
+                            };
+                            launch.summary_state.select(Some(i));
+                        }
+                        KeyCode::Esc | KeyCode::Char('L') => app.mode = Mode::Normal,
+                        _ => {}
+                    }
+                } else {
+                    app.mode = Mode::Normal;
+                }
+            }
+            Mode::Faults => match key.code {
+                KeyCode::Up => {
+                    let i = match app.faults.state.selected() {
+                        Some(i) => i.saturating_sub(1),
+                        None => 0,
+                    };
+                    app.faults.state.select(Some(i));
+                }
+                KeyCode::Down => {
+                    let max_index = app.faults.rules().len().saturating_sub(1);
+                    let i = match app.faults.state.selected() {
+                        Some(i) => (i + 1).min(max_index),
+                        None => 0,
+                    };
+                    app.faults.state.select(Some(i));
+                }
+                KeyCode::Enter => app.faults.add(),
+                KeyCode::Tab => app.faults.toggle_selected(),
+                KeyCode::Delete => app.faults.remove_selected(),
+                KeyCode::Esc => {
+                    app.faults.error.clear();
+                    app.mode = Mode::Normal;
+                }
+                _ => {
+                    if let Some(req) = input_backend::to_input_request(&event) {
+                        app.faults.input.handle(req);
+                        app.faults.error.clear();
+                    }
+                }
+            },
+            Mode::CommandFormat => match key.code {
+                KeyCode::Up => {
+                    let i = app
+                        .command_choice_state
+                        .selected()
+                        .map_or(0, |i| i.saturating_sub(1));
+                    app.command_choice_state.select(Some(i));
+                }
+                KeyCode::Down => {
+                    let max_index = app.command_choices.len().saturating_sub(1);
+                    let i = app
+                        .command_choice_state
+                        .selected()
+                        .map_or(0, |i| (i + 1).min(max_index));
+                    app.command_choice_state.select(Some(i));
+                }
+                KeyCode::Enter => {
+                    let choice = app
+                        .command_choice_state
+                        .selected()
+                        .and_then(|i| app.command_choices.get(i));
+                    match choice {
+                        Some((_, Ok(command))) => {
+                            let status = copy_to_clipboard(&clipboard_arc, command.clone()).await;
+                            app.status_message = format!("{} {}", status, command);
+                            app.mode = Mode::Normal;
+                        }
+                        Some((format, Err(e))) => {
+                            app.status_message = format!("{}: {}", format.name(), e);
+                        }
+                        None => {}
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('r') => {
+                    app.mode = Mode::Normal;
+                }
+                _ => {} // Ignore other keys
+            },
             Mode::ThreadView => {
                 if key.code == KeyCode::Esc {
                     app.thread_serial = None;
@@ -393,10 +1078,143 @@ pub async fn handle_event(
     Ok(false)
 }
 
+/// Looks up the declared argument labels of a message: the signal or method arguments of the
+/// sender, or of the destination for method calls. Replies use the output arguments of their call.
+/// Objects that were not introspected yet are looked up in the background, and `None` is
+/// returned until they are.
+async fn message_argument_labels(app: &mut App, item: &Item) -> Option<Vec<String>> {
+    let (name, path, interface, member, kind) = match item.msg_type {
+        Type::MethodCall => (
+            item.receiver.clone(),
+            item.path.clone(),
+            item.interface.clone(),
+            item.member.clone(),
+            MemberArgs::MethodIn,
+        ),
+        Type::Signal => (
+            item.sender.clone(),
+            item.path.clone(),
+            item.interface.clone(),
+            item.member.clone(),
+            MemberArgs::Signal,
+        ),
+        Type::MethodReturn => {
+            let Some(messages) = app.messages.get(&item.stream_type) else {
+                return Some(Vec::new());
+            };
+            let messages = messages.lock().await;
+            let Some(call) = messages.iter().rev().find(|call| {
+                call.msg_type == Type::MethodCall
+                    && call.serial == item.reply_serial
+                    && call.sender == item.receiver
+            }) else {
+                return Some(Vec::new());
+            };
+            (
+                item.sender.clone(),
+                call.path.clone(),
+                call.interface.clone(),
+                call.member.clone(),
+                MemberArgs::MethodOut,
+            )
+        }
+        _ => return Some(Vec::new()),
+    };
+
+    if name.is_empty() || path.is_empty() || member.is_empty() {
+        return Some(Vec::new());
+    }
+    match app
+        .introspection_cache
+        .cached(item.stream_type, &name, &path)
+    {
+        Some(node) => Some(
+            node.and_then(|node| {
+                crate::introspect::argument_labels(&node, &interface, &member, kind)
+            })
+            .unwrap_or_default(),
+        ),
+        None => {
+            if let Some(conn) = app.connections.get(&item.stream_type) {
+                app.introspection_cache
+                    .fetch(conn, item.stream_type, &name, &path);
+            }
+            None
+        }
+    }
+}
+
+/// Generates a proxy for an interface of a service and opens the popup to write it to a file.
+async fn open_proxy_export(app: &mut App, target: ProxyTarget) {
+    let Some(conn) = app.connections.get(&target.bus) else {
+        return;
+    };
+    let items = match app.messages.get(&target.bus) {
+        Some(messages) => messages.lock().await.clone(),
+        None => Vec::new(),
+    };
+    match app
+        .proxy_export
+        .open(conn, &mut app.introspection_cache, target, &items)
+        .await
+    {
+        Ok(()) => {
+            app.show_details = false;
+            app.mode = Mode::ProxyExport;
+        }
+        Err(e) => app.status_message = format!("Failed to generate proxy: {}", e),
+    }
+}
+
+/// Starts listing the names on `bus`, their captured messages are counted once they are in.
+fn open_names(app: &mut App, bus: BusType) -> Result<()> {
+    let Some(conn) = app.connections.get(&bus) else {
+        bail!("Not connected to the {:?} bus", bus);
+    };
+    app.names.open(conn, bus);
+    Ok(())
+}
+
+/// Copies `text` to the clipboard and returns a status message.
+async fn copy_to_clipboard(clipboard_arc: &Arc<Mutex<Clipboard>>, text: String) -> String {
+    let clipboard_arc_clone = clipboard_arc.clone();
+    let result =
+        tokio::task::spawn_blocking(move || clipboard_arc_clone.lock().unwrap().set_text(text))
+            .await;
+    match result {
+        Ok(Ok(_)) => "Copied to clipboard!".to_string(),
+        Ok(Err(e)) => format!("Copy failed: {}", e),
+        Err(e) => format!("Copy task failed: {}", e),
+    }
+}
+
+/// Shows the reply of a composed call in the details once it arrives, closing the composer.
+pub fn receive_composer_reply(app: &mut App, config: &Config) {
+    match app.composer.receive() {
+        Some(Ok(reply)) => {
+            app.detail_text = Text::from(crate::ui::format_reply_header(&reply, config));
+            app.body_tree = BodyTree::from_message(&reply, &[], &config.decoders);
+            app.wire_text = crate::ui::format_wire(&reply, config);
+            app.detail_scroll = 0;
+            app.show_details = true;
+            // Labels of the selected message arriving later must not replace the reply
+            app.detail_labels_pending = false;
+            if app.mode == Mode::Composer {
+                app.mode = Mode::Normal;
+            }
+        }
+        Some(Err(e)) => app.composer.error = e,
+        None => {}
+    }
+}
+
 /// A helper function to generate the detail text for the currently selected message.
-fn update_detail_text(app: &mut App, config: &Config) {
+pub async fn update_detail_text(app: &mut App, config: &Config) {
     if let Some(selected) = app.list_state.selected() {
-        if let Some(item) = app.filtered_and_sorted_items.get(selected) {
+        if let Some(item) = app.filtered_and_sorted_items.get(selected).cloned() {
+            let labels = message_argument_labels(app, &item).await;
+            app.detail_labels_pending = labels.is_none();
+            let labels = labels.unwrap_or_default();
             let mut header_lines: Vec<Line> = Vec::new();
 
             let recipient_info = if item.receiver.is_empty() {
@@ -422,6 +1240,7 @@ fn update_detail_text(app: &mut App, config: &Config) {
                     match item.stream_type {
                         BusType::Session => Style::default().fg(config.color_stream_session),
                         BusType::System => Style::default().fg(config.color_stream_system),
+                        BusType::Isolated => Style::default().fg(config.color_stream_isolated),
                         BusType::Both => Style::default().fg(config.color_timestamp_normal), // Fallback
                     },
                 ),
@@ -439,6 +1258,16 @@ fn update_detail_text(app: &mut App, config: &Config) {
             ]));
             header_lines.push(Line::from(vec![Span::raw("")])); // Empty line for spacing
 
+            if !item.error_name.is_empty() {
+                header_lines.push(Line::from(vec![
+                    Span::raw("Error: "),
+                    Span::styled(
+                        item.error_name.clone(),
+                        Style::default().fg(config.color_error_name),
+                    ),
+                ]));
+            }
+
             if !item.app_path.is_empty() {
                 header_lines.push(Line::from(vec![
                     Span::raw("Sender Path: "),
@@ -458,6 +1287,47 @@ fn update_detail_text(app: &mut App, config: &Config) {
                     ),
                 ]));
             }
+            if let Some(pid) = item.pid {
+                header_lines.push(Line::from(vec![
+                    Span::raw("Sender Process: "),
+                    Span::styled(
+                        crate::bus::ancestry(&item.ancestors, &item.app_name, pid),
+                        Style::default().fg(config.color_sender_normal),
+                    ),
+                ]));
+            }
+            if item.sandbox != crate::sandbox::Sandbox::None {
+                header_lines.push(Line::from(vec![
+                    Span::raw("Sandbox: "),
+                    Span::styled(
+                        format!("{} {}", item.sandbox, item.app_id),
+                        Style::default().fg(config.color_sender_normal),
+                    ),
+                ]));
+            }
+            if !item.exe.is_empty() {
+                header_lines.push(Line::from(vec![
+                    Span::raw("Sender Exe: "),
+                    Span::styled(
+                        item.exe.clone(),
+                        Style::default().fg(config.color_path_normal),
+                    ),
+                ]));
+            }
+            if !item.unit.is_empty() || !item.slice.is_empty() {
+                header_lines.push(Line::from(vec![
+                    Span::raw("Sender Unit: "),
+                    Span::styled(
+                        item.unit.clone(),
+                        Style::default().fg(config.color_member_normal),
+                    ),
+                    Span::raw(" in "),
+                    Span::styled(
+                        item.slice.clone(),
+                        Style::default().fg(config.color_path_normal),
+                    ),
+                ]));
+            }
             if !item.receiver_app_path.is_empty() {
                 header_lines.push(Line::from(vec![
                     Span::raw("Receiver Path: "),
@@ -477,29 +1347,37 @@ fn update_detail_text(app: &mut App, config: &Config) {
                     ),
                 ]));
             }
+            if let Some(pid) = item.receiver_pid {
+                header_lines.push(Line::from(vec![
+                    Span::raw("Receiver Process: "),
+                    Span::styled(
+                        crate::bus::ancestry(
+                            &item.receiver_ancestors,
+                            &item.receiver_app_name,
+                            pid,
+                        ),
+                        Style::default().fg(config.color_sender_normal),
+                    ),
+                ]));
+            }
             header_lines.push(Line::from(vec![Span::raw("")])); // Empty line for spacing
 
-            let detail_text = if let Some(message) = &item.message {
-                let body = message.body();
-                let body_sig = body.signature();
-
-                if body_sig.to_string().is_empty() {
-                    Text::from("[No message body]")
-                } else {
-                    match body.deserialize::<Structure>() {
-                        Ok(structure) => crate::ui::format_value(&Value::from(structure), config),
-                        Err(_) => match body.deserialize::<Value>() {
-                            Ok(value) => crate::ui::format_value(&value, config),
-                            Err(e) => Text::from(format!(
-                                "Failed to deserialize body.\n\nSignature: {}
-Error: {:#?}",
-                                body_sig, e
-                            )),
-                        },
-                    }
-                }
-            } else {
+            // Message bodies are shown as a tree below the header, everything else as text
+            app.body_tree = BodyTree::default();
+            app.wire_text = match &item.message {
+                Some(message) => crate::ui::format_wire(message, config),
+                None => Text::from("[Message was not captured]"),
+            };
+            if let Some(message) = &item.message {
+                app.body_tree = BodyTree::from_message(message, &labels, &config.decoders);
+            }
+            // Property changes are shown as a diff above the tree of the signal
+            let detail_text = if !item.property_changes.is_empty() {
+                crate::ui::format_property_changes(&item.property_changes, config)
+            } else if app.body_tree.roots.is_empty() {
                 Text::from("[No message body]")
+            } else {
+                Text::default()
             };
 
             // Prepend header to detail_text
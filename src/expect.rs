@@ -0,0 +1,530 @@
+use crate::body_tree::body_args;
+use crate::bus::{self, BusType, Item, PropertyStore, FILTER_FIELDS};
+use crate::faults::parse_duration;
+use crate::gvariant;
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use zbus::MessageStream;
+
+/// A condition on a message, parsed from an expression such as
+/// `member == "JobRemoved" and arg3 == "done"`.
+///
+/// Comparisons are `field == value`, `field != value` and `field=value` (contains), values can
+/// be quoted. Terms are combined with `and`, `or`, `not` and parentheses, terms next to each
+/// other must all match. Plain text matches the sender, member or path, as in the filter bar.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Equals(String, String),
+    NotEquals(String, String),
+    Contains(String, String),
+    Text(String),
+    Not(Box<Condition>),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Equals,
+    NotEquals,
+    Contains,
+    Open,
+    Close,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let condition = parser.any()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(condition),
+            Some(token) => bail!("Unexpected {:?} in '{}'", token, text),
+        }
+    }
+
+    /// Whether any comparison in the condition is on one of `fields`.
+    pub fn uses_field(&self, fields: &[&str]) -> bool {
+        match self {
+            Condition::Equals(field, _)
+            | Condition::NotEquals(field, _)
+            | Condition::Contains(field, _) => fields.contains(&field.as_str()),
+            Condition::Text(_) => false,
+            Condition::Not(condition) => condition.uses_field(fields),
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                conditions.iter().any(|c| c.uses_field(fields))
+            }
+        }
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        match self {
+            Condition::Equals(field, value) => item.field_value(field).is_some_and(|v| v == *value),
+            Condition::NotEquals(field, value) => {
+                item.field_value(field).is_none_or(|v| v != *value)
+            }
+            Condition::Contains(field, value) => item.matches_field(field, value),
+            Condition::Text(text) => item.matches_text(text),
+            Condition::Not(condition) => !condition.matches(item),
+            Condition::All(conditions) => conditions.iter().all(|c| c.matches(item)),
+            Condition::Any(conditions) => conditions.iter().any(|c| c.matches(item)),
+        }
+    }
+}
+
+/// Fields that describe the sending process rather than the message, filled in by looking the
+/// sender up on the bus.
+pub const PROCESS_FIELDS: [&str; 7] = [
+    "exe", "unit", "slice", "ppid", "ancestor", "sandbox", "app_id",
+];
+
+/// Splits an expression into words, quoted strings, operators and parentheses. Brackets of body
+/// paths such as `arg1['Metadata']` stay part of their word.
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '=' | '!' => {
+                chars.next();
+                let double = chars.next_if_eq(&'=').is_some();
+                tokens.push(match (c, double) {
+                    ('=', true) => Token::Equals,
+                    ('=', false) => Token::Contains,
+                    ('!', true) => Token::NotEquals,
+                    _ => bail!("Expected != in '{}'", text),
+                });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => value.extend(chars.next()),
+                        Some(q) if q == c => break,
+                        Some(ch) => value.push(ch),
+                        None => bail!("Unterminated string in '{}'", text),
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            _ => {
+                let mut word = String::new();
+                let mut depth = 0;
+                while let Some(&ch) = chars.peek() {
+                    if depth == 0 && (ch.is_whitespace() || "()=!".contains(ch)) {
+                        break;
+                    }
+                    match ch {
+                        '[' => depth += 1,
+                        ']' if depth == 0 => bail!("Unbalanced ] in '{}'", text),
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word == keyword)
+    }
+
+    /// Terms separated by `or`.
+    fn any(&mut self) -> Result<Condition> {
+        let mut conditions = vec![self.all()?];
+        while self.is_keyword("or") {
+            self.pos += 1;
+            conditions.push(self.all()?);
+        }
+        Ok(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => Condition::Any(conditions),
+        })
+    }
+
+    /// Terms separated by `and`, or just next to each other.
+    fn all(&mut self) -> Result<Condition> {
+        let mut conditions = vec![self.term()?];
+        loop {
+            if self.is_keyword("and") {
+                self.pos += 1;
+            } else if self.peek().is_none()
+                || self.is_keyword("or")
+                || self.peek() == Some(&Token::Close)
+            {
+                break;
+            }
+            conditions.push(self.term()?);
+        }
+        Ok(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => Condition::All(conditions),
+        })
+    }
+
+    fn term(&mut self) -> Result<Condition> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Word(word)) if word == "not" => Ok(Condition::Not(Box::new(self.term()?))),
+            Some(Token::Open) => {
+                let condition = self.any()?;
+                if self.peek() != Some(&Token::Close) {
+                    bail!("Missing )");
+                }
+                self.pos += 1;
+                Ok(condition)
+            }
+            Some(Token::Word(word)) => {
+                let operator = match self.peek() {
+                    Some(Token::Equals | Token::NotEquals | Token::Contains) => {
+                        self.peek().cloned()
+                    }
+                    _ => None,
+                };
+                let Some(operator) = operator else {
+                    return Ok(Condition::Text(word));
+                };
+                if !FILTER_FIELDS.contains(&word.as_str()) && !word.starts_with("arg") {
+                    bail!(
+                        "Unknown field '{}', expected one of {} or a body path such as arg0",
+                        word,
+                        FILTER_FIELDS.join(", ")
+                    );
+                }
+                self.pos += 1;
+                let value = match self.peek() {
+                    Some(Token::Word(value) | Token::Quoted(value)) => value.clone(),
+                    _ => bail!("Expected a value after {}", word),
+                };
+                self.pos += 1;
+                Ok(match operator {
+                    Token::Equals => Condition::Equals(word, value),
+                    Token::NotEquals => Condition::NotEquals(word, value),
+                    _ => Condition::Contains(word, value),
+                })
+            }
+            Some(Token::Quoted(text)) => Ok(Condition::Text(text)),
+            Some(token) => bail!("Unexpected {:?}", token),
+            None => bail!("Expected a condition"),
+        }
+    }
+}
+
+/// One step of an expectation file.
+#[derive(Debug, Deserialize)]
+struct Step {
+    filter: String,
+    #[serde(default = "default_count")]
+    count: usize,
+    /// Overrides `--timeout` for this step, ie `"5s"`.
+    timeout: Option<String>,
+}
+
+fn default_count() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+struct ExpectationFile {
+    expect: Vec<Step>,
+}
+
+/// A condition that has to match `count` messages within `timeout`.
+pub struct Expectation {
+    pub filter: String,
+    pub condition: Condition,
+    pub count: usize,
+    pub timeout: Duration,
+}
+
+impl Expectation {
+    pub fn new(filter: &str, count: usize, timeout: Duration) -> Result<Self> {
+        Ok(Expectation {
+            filter: filter.to_string(),
+            condition: Condition::parse(filter)
+                .with_context(|| format!("Invalid filter '{}'", filter))?,
+            count,
+            timeout,
+        })
+    }
+}
+
+/// Reads an ordered list of expectations from a TOML file with `[[expect]]` tables, or a YAML
+/// file (`.yaml` or `.yml`) with an `expect` list. Steps without a timeout of their own use
+/// `timeout`.
+pub fn load(path: &Path, timeout: Duration) -> Result<Vec<Expectation>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file =
+        parse_file(&text, path).with_context(|| format!("Failed to parse {}", path.display()))?;
+    file.expect
+        .iter()
+        .map(|step| {
+            let timeout = match &step.timeout {
+                Some(text) => parse_duration(text)?,
+                None => timeout,
+            };
+            Expectation::new(&step.filter, step.count, timeout)
+        })
+        .collect::<Result<_>>()
+        .with_context(|| format!("Invalid step in {}", path.display()))
+}
+
+fn parse_file(text: &str, path: &Path) -> Result<ExpectationFile> {
+    Ok(match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml" | "yml") => serde_yaml::from_str(text)?,
+        _ => toml::from_str(text)?,
+    })
+}
+
+/// Watches `bus` until every expectation has matched in order, printing the matching messages.
+/// Each step's timeout starts when the step before it is met. Fails on the first step that
+/// times out.
+pub async fn run(bus: &str, expectations: &[Expectation]) -> Result<()> {
+    let conn = bus::connection_builder(bus)?.build().await?;
+    let bus_type = match bus {
+        "system" => BusType::System,
+        _ => BusType::Session,
+    };
+    bus::subscribe(&conn).await?;
+    let mut stream = MessageStream::from(&conn);
+    let mut property_store = PropertyStore::new();
+    // Looking up every sender is only worth it when a condition asks about the process
+    let lookup = expectations
+        .iter()
+        .any(|e| e.condition.uses_field(&PROCESS_FIELDS));
+    let cache = Arc::new(Mutex::new(HashMap::new()));
+    let started = tokio::time::Instant::now();
+
+    for (i, expectation) in expectations.iter().enumerate() {
+        let deadline = tokio::time::Instant::now() + expectation.timeout;
+        let mut matched = 0;
+        while matched < expectation.count {
+            let msg = match tokio::time::timeout_at(deadline, stream.next()).await {
+                Ok(Some(msg)) => msg?,
+                Ok(None) => bail!("The connection to {} closed", bus),
+                Err(_) => bail!(
+                    "Timed out after {:.1}s at step {} of {}: '{}' matched {} of {} messages",
+                    expectation.timeout.as_secs_f64(),
+                    i + 1,
+                    expectations.len(),
+                    expectation.filter,
+                    matched,
+                    expectation.count
+                ),
+            };
+            let sender = msg.header().sender().map(|s| s.to_string());
+            let sender_info = match sender {
+                Some(sender) if lookup && sender.starts_with(':') => {
+                    bus::get_process_info(&conn, &sender, &cache).await
+                }
+                _ => None,
+            };
+            let item = bus::new_item(msg, bus_type, sender_info, None, &mut property_store);
+            if !expectation.condition.matches(&item) {
+                continue;
+            }
+            matched += 1;
+            let args = item
+                .message
+                .as_ref()
+                .map(|message| gvariant::format_args(&body_args(message)))
+                .unwrap_or_default();
+            println!(
+                "+{:.3}s {} {} {}.{}({}) {}",
+                started.elapsed().as_secs_f64(),
+                item.type_name(),
+                item.sender,
+                item.interface,
+                item.member,
+                args,
+                item.path
+            );
+        }
+    }
+    println!(
+        "All {} expectation(s) met in {:.3}s",
+        expectations.len(),
+        started.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::Value;
+
+    fn eq(field: &str, value: &str) -> Condition {
+        Condition::Equals(field.to_string(), value.to_string())
+    }
+
+    fn item(member: &str, body: &(impl serde::Serialize + zbus::zvariant::DynamicType)) -> Item {
+        let msg = zbus::Message::signal("/org/example", "org.example.Jobs", member)
+            .unwrap()
+            .build(body)
+            .unwrap();
+        bus::new_item(msg, BusType::Session, None, None, &mut PropertyStore::new())
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(
+            tokenize(r#"member == "Job Removed" path='it\'s'"#).unwrap(),
+            vec![
+                Token::Word("member".to_string()),
+                Token::Equals,
+                Token::Quoted("Job Removed".to_string()),
+                Token::Word("path".to_string()),
+                Token::Contains,
+                Token::Quoted("it's".to_string()),
+            ]
+        );
+        assert_eq!(
+            Condition::parse(r#"member == "a or b""#).unwrap(),
+            eq("member", "a or b")
+        );
+        assert!(tokenize("member == 'unterminated").is_err());
+        assert!(tokenize("member ! x").is_err());
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            Condition::parse("member == A or member == B and path == /c").unwrap(),
+            Condition::Any(vec![
+                eq("member", "A"),
+                Condition::All(vec![eq("member", "B"), eq("path", "/c")]),
+            ])
+        );
+    }
+
+    #[test]
+    fn not_applies_to_the_next_term() {
+        assert_eq!(
+            Condition::parse("not member == A and path == /b").unwrap(),
+            Condition::All(vec![
+                Condition::Not(Box::new(eq("member", "A"))),
+                eq("path", "/b"),
+            ])
+        );
+    }
+
+    #[test]
+    fn parentheses_and_implicit_and() {
+        assert_eq!(
+            Condition::parse("(member == A or member == B) path=/org hello").unwrap(),
+            Condition::All(vec![
+                Condition::Any(vec![eq("member", "A"), eq("member", "B")]),
+                Condition::Contains("path".to_string(), "/org".to_string()),
+                Condition::Text("hello".to_string()),
+            ])
+        );
+        assert!(Condition::parse("(member == A").is_err());
+        assert!(Condition::parse("member == A)").is_err());
+    }
+
+    #[test]
+    fn bracketed_body_paths_stay_one_word() {
+        assert_eq!(
+            Condition::parse("arg1['Meta data'][0] == x").unwrap(),
+            eq("arg1['Meta data'][0]", "x")
+        );
+        assert!(tokenize("arg1] == x").is_err());
+        assert!(tokenize("arg1['a']] == x").is_err());
+    }
+
+    #[test]
+    fn process_fields_are_found_anywhere() {
+        let uses = |text: &str| Condition::parse(text).unwrap().uses_field(&PROCESS_FIELDS);
+        assert!(uses("member == A and (path == /b or not exe=firefox)"));
+        assert!(uses("ancestor != bash"));
+        assert!(!uses("member == A exe"));
+        assert!(!uses("sender == :1.4 arg0 == unit"));
+    }
+
+    #[test]
+    fn step_files_in_toml_and_yaml() {
+        let toml = "[[expect]]\nfilter = 'member == A'\n\n[[expect]]\nfilter = 'member == B'\ncount = 2\ntimeout = \"5s\"\n";
+        let yaml = "expect:\n  - filter: 'member == A'\n  - filter: 'member == B'\n    count: 2\n    timeout: 5s\n";
+        for (text, name) in [
+            (toml, "steps.toml"),
+            (yaml, "steps.yaml"),
+            (yaml, "steps.yml"),
+        ] {
+            let file = parse_file(text, Path::new(name)).unwrap();
+            let steps: Vec<_> = file
+                .expect
+                .iter()
+                .map(|step| (step.filter.as_str(), step.count, step.timeout.as_deref()))
+                .collect();
+            assert_eq!(
+                steps,
+                vec![("member == A", 1, None), ("member == B", 2, Some("5s"))],
+                "{}",
+                name
+            );
+        }
+        assert!(parse_file(yaml, Path::new("steps.toml")).is_err());
+    }
+
+    #[test]
+    fn unknown_fields_and_missing_values_are_errors() {
+        assert!(Condition::parse("color == red").is_err());
+        assert!(Condition::parse("member ==").is_err());
+        assert!(Condition::parse("").is_err());
+    }
+
+    #[test]
+    fn conditions_match_header_and_body() {
+        let job = item("JobRemoved", &(7u32, "/job/7", "foo.service", "done"));
+        assert!(
+            Condition::parse(r#"member == "JobRemoved" and arg3 == "done""#)
+                .unwrap()
+                .matches(&job)
+        );
+        assert!(!Condition::parse("arg3 == failed").unwrap().matches(&job));
+        assert!(Condition::parse("arg9 != x").unwrap().matches(&job));
+        assert!(Condition::parse("not member == Other")
+            .unwrap()
+            .matches(&job));
+
+        let metadata = HashMap::from([("Metadata", Value::from("song"))]);
+        let changed = item("Changed", &("player", metadata));
+        assert!(Condition::parse("arg1['Metadata'] == song")
+            .unwrap()
+            .matches(&changed));
+    }
+}
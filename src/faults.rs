@@ -0,0 +1,279 @@
+use crate::bus::Item;
+use anyhow::{bail, Context, Result};
+use ratatui::widgets::ListState;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tui_input::Input;
+use zbus::names::ErrorName;
+
+/// What a fault rule does to a matching message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FaultAction {
+    /// Holds the message back before forwarding it. Later messages do not wait and overtake it.
+    Delay(Duration),
+    Drop,
+    Duplicate,
+    /// Replaces the method return of a matching call with this error.
+    Error(String),
+}
+
+impl FaultAction {
+    /// How a message the action was applied to is marked in the list.
+    pub fn marker(&self) -> String {
+        match self {
+            FaultAction::Delay(delay) => format!("delayed {}ms", delay.as_millis()),
+            FaultAction::Drop => "dropped".to_string(),
+            FaultAction::Duplicate => "duplicated".to_string(),
+            FaultAction::Error(name) => format!("replaced by {}", name),
+        }
+    }
+}
+
+/// A fault to inject into proxied messages that match a filter.
+#[derive(Clone, Debug)]
+pub struct FaultRule {
+    pub action: FaultAction,
+    /// Filter in the filter bar syntax: `field=value` terms and plain text, all of which must match.
+    pub filter: String,
+    pub enabled: bool,
+}
+
+impl FaultRule {
+    /// Parses `<action> <filter>`, where the action is `delay <duration>`, `drop`, `duplicate`
+    /// or `error <error name>`, ie `delay 500ms member=GetId`.
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (action, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let (action, filter) = match action {
+            "drop" => (FaultAction::Drop, rest),
+            "duplicate" => (FaultAction::Duplicate, rest),
+            "delay" => {
+                let (duration, filter) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                (FaultAction::Delay(parse_duration(duration)?), filter)
+            }
+            "error" => {
+                let (name, filter) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                ErrorName::try_from(name)
+                    .with_context(|| format!("'{}' is not a valid error name", name))?;
+                (FaultAction::Error(name.to_string()), filter)
+            }
+            "" => bail!("Expected a rule such as: delay 500ms member=GetId"),
+            action => bail!(
+                "Unknown action '{}', expected delay, drop, duplicate or error",
+                action
+            ),
+        };
+        Ok(FaultRule {
+            action,
+            filter: filter.trim().to_string(),
+            enabled: true,
+        })
+    }
+
+    /// Whether the rule applies to a message. A rule without a filter matches everything.
+    pub fn matches(&self, item: &Item) -> bool {
+        item.matches_filter(&self.filter)
+    }
+
+    /// The rule in the syntax `parse` accepts.
+    pub fn text(&self) -> String {
+        let action = match &self.action {
+            FaultAction::Delay(delay) => format!("delay {}ms", delay.as_millis()),
+            FaultAction::Drop => "drop".to_string(),
+            FaultAction::Duplicate => "duplicate".to_string(),
+            FaultAction::Error(name) => format!("error {}", name),
+        };
+        if self.filter.is_empty() {
+            action
+        } else {
+            format!("{} {}", action, self.filter)
+        }
+    }
+}
+
+/// Accepts `250ms`, `2s` or a plain number of milliseconds.
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let parsed = if let Some(ms) = text.strip_suffix("ms") {
+        ms.parse().map(Duration::from_millis)
+    } else if let Some(s) = text.strip_suffix('s') {
+        s.parse().map(Duration::from_secs)
+    } else {
+        text.parse().map(Duration::from_millis)
+    };
+    parsed.with_context(|| format!("'{}' is not a duration such as 500ms or 2s", text))
+}
+
+/// Reads fault rules from a file, one per line. Empty lines and `#` comments, at the start of
+/// a line or after a rule, are skipped.
+pub fn load_rules(path: &Path) -> Result<Vec<FaultRule>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    text.lines()
+        .map(strip_comment)
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            FaultRule::parse(line).with_context(|| format!("{}:{}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// The line up to a `#` that starts it or follows whitespace.
+fn strip_comment(line: &str) -> &str {
+    let end = line
+        .char_indices()
+        .find(|&(i, c)| c == '#' && line[..i].chars().last().is_none_or(char::is_whitespace))
+        .map_or(line.len(), |(i, _)| i);
+    &line[..end]
+}
+
+/// Rules shared between the fault panel and the proxy.
+pub type FaultRules = Arc<Mutex<Vec<FaultRule>>>;
+
+/// The action of the first enabled rule matching the message.
+pub fn find(rules: &FaultRules, item: &Item) -> Option<FaultAction> {
+    rules
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|rule| rule.enabled && rule.matches(item))
+        .map(|rule| rule.action.clone())
+}
+
+/// State of the fault rules panel.
+#[derive(Default)]
+pub struct FaultPanel {
+    pub rules: FaultRules,
+    pub state: ListState,
+    pub input: Input,
+    pub error: String,
+    /// Whether d-buddy runs as a proxy, the only place rules apply.
+    pub proxying: bool,
+}
+
+impl FaultPanel {
+    /// Adds the rule typed into the input, or reports why it does not parse.
+    pub fn add(&mut self) {
+        match FaultRule::parse(self.input.value()) {
+            Ok(rule) => {
+                let mut rules = self.rules.lock().unwrap_or_else(|e| e.into_inner());
+                rules.push(rule);
+                self.state.select(Some(rules.len() - 1));
+                self.input.reset();
+                self.error.clear();
+            }
+            Err(e) => self.error = e.to_string(),
+        }
+    }
+
+    pub fn remove_selected(&mut self) {
+        let mut rules = self.rules.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = self.state.selected().filter(|&i| i < rules.len()) {
+            rules.remove(i);
+            self.state
+                .select((!rules.is_empty()).then(|| i.min(rules.len() - 1)));
+        }
+    }
+
+    pub fn toggle_selected(&mut self) {
+        let mut rules = self.rules.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(rule) = self.state.selected().and_then(|i| rules.get_mut(i)) {
+            rule.enabled = !rule.enabled;
+        }
+    }
+
+    /// A snapshot of the rules for rendering.
+    pub fn rules(&self) -> Vec<FaultRule> {
+        self.rules.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_parse_and_print_back() {
+        for text in [
+            "delay 500ms member=GetId",
+            "drop type=signal interface=org.example.Device",
+            "duplicate member=Changed",
+            "error org.freedesktop.DBus.Error.Failed member=GetId",
+            "drop",
+        ] {
+            assert_eq!(FaultRule::parse(text).unwrap().text(), text);
+        }
+        // Durations are printed in milliseconds, extra spaces are dropped
+        assert_eq!(
+            FaultRule::parse("  delay 2s   member=A ").unwrap().text(),
+            "delay 2000ms member=A"
+        );
+    }
+
+    #[test]
+    fn rule_actions_and_filters() {
+        let rule = FaultRule::parse("error org.example.Error.Busy path=/a member=B").unwrap();
+        assert_eq!(
+            rule.action,
+            FaultAction::Error("org.example.Error.Busy".to_string())
+        );
+        assert_eq!(rule.filter, "path=/a member=B");
+        assert!(rule.enabled);
+
+        let rule = FaultRule::parse("delay 250").unwrap();
+        assert_eq!(rule.action, FaultAction::Delay(Duration::from_millis(250)));
+        assert!(rule.filter.is_empty());
+    }
+
+    #[test]
+    fn invalid_rules_are_errors() {
+        for text in [
+            "",
+            "explode member=A",
+            "delay soon member=A",
+            "error not-an-error-name member=A",
+        ] {
+            assert!(FaultRule::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("40").unwrap(), Duration::from_millis(40));
+        for text in ["", "ms", "1.5s", "-1", "2m"] {
+            assert!(parse_duration(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn comments_start_at_a_hash_after_whitespace() {
+        assert_eq!(strip_comment("# whole line"), "");
+        assert_eq!(strip_comment("drop member=A  # why"), "drop member=A  ");
+        assert_eq!(strip_comment("drop member=A\t# why"), "drop member=A\t");
+        assert_eq!(strip_comment("drop member=A#B"), "drop member=A#B");
+        assert_eq!(strip_comment("drop member=A"), "drop member=A");
+    }
+
+    #[test]
+    fn rule_files_skip_comments_and_report_lines() {
+        let path = std::env::temp_dir().join(format!("d-buddy-faults-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "# rules\n\ndrop member=A   # noisy\n  # indented\nduplicate member=B#2\n",
+        )
+        .unwrap();
+        let rules = load_rules(&path).unwrap();
+        assert_eq!(
+            rules.iter().map(FaultRule::text).collect::<Vec<_>>(),
+            vec!["drop member=A", "duplicate member=B#2"]
+        );
+
+        std::fs::write(&path, "drop member=A\nexplode\n").unwrap();
+        let error = format!("{:#}", load_rules(&path).unwrap_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains(":2"), "{}", error);
+    }
+}
@@ -0,0 +1,561 @@
+use anyhow::{anyhow, bail, Result};
+use zbus::zvariant::{Array, Dict, ObjectPath, Signature, StructureBuilder, Value};
+
+/// Parses comma separated arguments in GVariant text syntax (as accepted by `gdbus call`).
+///
+/// When `signatures` is given, every argument is checked against its declared type, otherwise
+/// types are inferred from the literals the same way GLib does (integers are `i`, floats are `d`).
+pub fn parse_args(text: &str, signatures: Option<&[Signature]>) -> Result<Vec<Value<'static>>> {
+    let mut parser = Parser::new(text);
+    let mut args = Vec::new();
+
+    parser.skip_whitespace();
+    if !parser.at_end() {
+        loop {
+            let expected =
+                match signatures {
+                    Some(signatures) => Some(signatures.get(args.len()).ok_or_else(|| {
+                        anyhow!("Too many arguments, expected {}", signatures.len())
+                    })?),
+                    None => None,
+                };
+            args.push(parser.parse_value(expected)?);
+            parser.skip_whitespace();
+            if parser.at_end() {
+                break;
+            }
+            parser.expect(',')?;
+        }
+    }
+
+    if let Some(signatures) = signatures {
+        if args.len() != signatures.len() {
+            bail!(
+                "Expected {} argument(s) of type '{}', got {}",
+                signatures.len(),
+                signatures.iter().map(|s| s.to_string()).collect::<String>(),
+                args.len()
+            );
+        }
+    }
+    Ok(args)
+}
+
+/// Formats arguments in the GVariant text syntax understood by [`parse_args`].
+pub fn format_args(args: &[Value]) -> String {
+    args.iter().map(format).collect::<Vec<_>>().join(", ")
+}
+
+/// Formats a single value. Values inside variants carry a type annotation unless their type is
+/// what the parser would infer anyway, so the text parses back to the same types.
+pub fn format(value: &Value) -> String {
+    match value {
+        Value::U8(v) => v.to_string(),
+        Value::Bool(v) => v.to_string(),
+        Value::I16(v) => v.to_string(),
+        Value::U16(v) => v.to_string(),
+        Value::I32(v) => v.to_string(),
+        Value::U32(v) => v.to_string(),
+        Value::I64(v) => v.to_string(),
+        Value::U64(v) => v.to_string(),
+        Value::F64(v) => format!("{:?}", v),
+        Value::Str(v) => quote(v.as_str()),
+        Value::Signature(v) => quote(&v.to_string()),
+        Value::ObjectPath(v) => quote(v.as_str()),
+        Value::Value(inner) => match inner.value_signature() {
+            Signature::Str | Signature::I32 | Signature::F64 | Signature::Bool => {
+                format!("<{}>", format(inner))
+            }
+            signature => format!("<@{} {}>", signature, format(inner)),
+        },
+        Value::Array(array) => format!(
+            "[{}]",
+            array.iter().map(format).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Dict(dict) => format!(
+            "{{{}}}",
+            dict.iter()
+                .map(|(k, v)| format!("{}: {}", format(k), format(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Structure(structure) => {
+            let fields = structure.fields();
+            let trailing = if fields.len() == 1 { "," } else { "" };
+            format!(
+                "({}{})",
+                fields.iter().map(format).collect::<Vec<_>>().join(", "),
+                trailing
+            )
+        }
+        #[cfg(unix)]
+        Value::Fd(fd) => format!("{:?}", fd),
+    }
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('\'');
+    for c in text.chars() {
+        match c {
+            '\'' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\0' => quoted.push_str("\\0"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(found) if found == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(found) => bail!("Expected '{}' at {}, found '{}'", c, self.pos, found),
+            None => bail!("Expected '{}' at {}, found end of input", c, self.pos),
+        }
+    }
+
+    /// Consumes `c` if it is the next non-whitespace character.
+    fn accept(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn word(&mut self) -> String {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '+'))
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn parse_value(&mut self, expected: Option<&Signature>) -> Result<Value<'static>> {
+        self.skip_whitespace();
+
+        // `@sig value` gives an explicit type, `uint32 5` style keywords do the same for basic types
+        if self.accept('@') {
+            let start = self.pos;
+            while self.peek().is_some_and(|c| !c.is_whitespace()) {
+                self.pos += 1;
+            }
+            let sig_text: String = self.chars[start..self.pos].iter().collect();
+            let signature = Signature::try_from(sig_text.as_str())
+                .map_err(|e| anyhow!("Invalid type annotation '@{}': {}", sig_text, e))?;
+            let value = self.parse_value(Some(&signature))?;
+            return check_expected(value, expected);
+        }
+        if self.peek().is_some_and(char::is_alphabetic) {
+            let checkpoint = self.pos;
+            let keyword = self.word();
+            let annotated = match keyword.as_str() {
+                "byte" => Some(Signature::U8),
+                "int16" => Some(Signature::I16),
+                "uint16" => Some(Signature::U16),
+                "int32" => Some(Signature::I32),
+                "uint32" => Some(Signature::U32),
+                "int64" => Some(Signature::I64),
+                "uint64" => Some(Signature::U64),
+                "double" => Some(Signature::F64),
+                "objectpath" => Some(Signature::ObjectPath),
+                "signature" => Some(Signature::Signature),
+                _ => None,
+            };
+            match annotated {
+                Some(signature) => {
+                    let value = self.parse_value(Some(&signature))?;
+                    return check_expected(value, expected);
+                }
+                None => self.pos = checkpoint,
+            }
+        }
+
+        match expected {
+            Some(Signature::Variant) => {
+                let inner = if self.accept('<') {
+                    let inner = self.parse_value(None)?;
+                    self.expect('>')?;
+                    inner
+                } else {
+                    self.parse_value(None)?
+                };
+                Ok(Value::Value(Box::new(inner)))
+            }
+            Some(Signature::Array(child)) => {
+                self.expect('[')?;
+                let mut array = Array::new(child.signature());
+                if !self.accept(']') {
+                    loop {
+                        array.append(self.parse_value(Some(child.signature()))?)?;
+                        if self.accept(']') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                Ok(Value::Array(array))
+            }
+            Some(Signature::Dict { key, value }) => {
+                self.expect('{')?;
+                let mut dict = Dict::new(key.signature(), value.signature());
+                if !self.accept('}') {
+                    loop {
+                        let k = self.parse_value(Some(key.signature()))?;
+                        self.expect(':')?;
+                        let v = self.parse_value(Some(value.signature()))?;
+                        dict.append(k, v)?;
+                        if self.accept('}') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                Ok(Value::Dict(dict))
+            }
+            Some(Signature::Structure(fields)) => {
+                self.expect('(')?;
+                let mut builder = StructureBuilder::new();
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.expect(',')?;
+                    }
+                    builder = builder.append_field(self.parse_value(Some(field))?);
+                }
+                // GVariant writes single element tuples as `(x,)`
+                self.accept(',');
+                self.expect(')')?;
+                Ok(Value::Structure(builder.build()?))
+            }
+            Some(basic) => self.parse_basic(Some(basic)),
+            None => self.parse_inferred(),
+        }
+    }
+
+    /// Parses a value without a known type, inferring it from the literal.
+    fn parse_inferred(&mut self) -> Result<Value<'static>> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('<') => {
+                self.pos += 1;
+                let inner = self.parse_value(None)?;
+                self.expect('>')?;
+                Ok(Value::Value(Box::new(inner)))
+            }
+            Some('[') => {
+                self.pos += 1;
+                if self.accept(']') {
+                    bail!("Cannot infer the type of an empty array, annotate it like '@as []'");
+                }
+                let first = self.parse_value(None)?;
+                let element_signature = first.value_signature().clone();
+                let mut array = Array::new(&element_signature);
+                array.append(first)?;
+                while !self.accept(']') {
+                    self.expect(',')?;
+                    array.append(self.parse_value(Some(&element_signature))?)?;
+                }
+                Ok(Value::Array(array))
+            }
+            Some('{') => {
+                self.pos += 1;
+                if self.accept('}') {
+                    bail!(
+                        "Cannot infer the type of an empty dict, annotate it like '@a{{sv}} {{}}'"
+                    );
+                }
+                let first_key = self.parse_value(None)?;
+                self.expect(':')?;
+                let first_value = self.parse_value(None)?;
+                let key_signature = first_key.value_signature().clone();
+                let value_signature = first_value.value_signature().clone();
+                let mut dict = Dict::new(&key_signature, &value_signature);
+                dict.append(first_key, first_value)?;
+                while !self.accept('}') {
+                    self.expect(',')?;
+                    let k = self.parse_value(Some(&key_signature))?;
+                    self.expect(':')?;
+                    let v = self.parse_value(Some(&value_signature))?;
+                    dict.append(k, v)?;
+                }
+                Ok(Value::Dict(dict))
+            }
+            Some('(') => {
+                self.pos += 1;
+                let mut builder = StructureBuilder::new();
+                let mut count = 0;
+                while !self.accept(')') {
+                    if count > 0 {
+                        self.expect(',')?;
+                        if self.accept(')') {
+                            break;
+                        }
+                    }
+                    builder = builder.append_field(self.parse_value(None)?);
+                    count += 1;
+                }
+                if count == 0 {
+                    bail!("Empty tuples are not valid D-Bus values");
+                }
+                Ok(Value::Structure(builder.build()?))
+            }
+            _ => self.parse_basic(None),
+        }
+    }
+
+    /// Parses a string, number or boolean literal, converting it to `expected` when given.
+    fn parse_basic(&mut self, expected: Option<&Signature>) -> Result<Value<'static>> {
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some(quote @ ('\'' | '"')) => {
+                self.pos += 1;
+                let text = self.parse_string(quote)?;
+                match expected {
+                    None | Some(Signature::Str) => Value::from(text),
+                    Some(Signature::ObjectPath) => Value::ObjectPath(
+                        ObjectPath::try_from(text.clone())
+                            .map_err(|e| anyhow!("Invalid object path '{}': {}", text, e))?,
+                    ),
+                    Some(Signature::Signature) => Value::Signature(
+                        Signature::try_from(text.as_str())
+                            .map_err(|e| anyhow!("Invalid signature '{}': {}", text, e))?,
+                    ),
+                    Some(other) => bail!("Expected a value of type '{}', found a string", other),
+                }
+            }
+            Some(_) => {
+                let word = self.word();
+                if word.is_empty() {
+                    bail!("Unexpected character at {}", self.pos);
+                }
+                parse_word(&word, expected)?
+            }
+            None => bail!("Unexpected end of input"),
+        };
+        Ok(value)
+    }
+
+    fn parse_string(&mut self, quote: char) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                bail!("Unterminated string");
+            };
+            self.pos += 1;
+            match c {
+                c if c == quote => return Ok(text),
+                '\\' => {
+                    let Some(escaped) = self.peek() else {
+                        bail!("Unterminated escape sequence");
+                    };
+                    self.pos += 1;
+                    text.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '0' => '\0',
+                        other => other,
+                    });
+                }
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+/// Converts a bare word (number or boolean) to a value of the expected type.
+fn parse_word(word: &str, expected: Option<&Signature>) -> Result<Value<'static>> {
+    fn int(word: &str) -> Result<i128> {
+        let (negative, digits) = match word.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, word.strip_prefix('+').unwrap_or(word)),
+        };
+        let value = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+            Some(hex) => i128::from_str_radix(hex, 16)?,
+            None => digits.parse::<i128>()?,
+        };
+        Ok(if negative { -value } else { value })
+    }
+    fn ranged<T: TryFrom<i128>>(word: &str, type_name: &str) -> Result<T> {
+        T::try_from(int(word).map_err(|_| anyhow!("'{}' is not an integer", word))?)
+            .map_err(|_| anyhow!("{} is out of range for {}", word, type_name))
+    }
+
+    let value = match (word, expected) {
+        ("true", None | Some(Signature::Bool)) => Value::Bool(true),
+        ("false", None | Some(Signature::Bool)) => Value::Bool(false),
+        (_, Some(Signature::U8)) => Value::U8(ranged(word, "byte")?),
+        (_, Some(Signature::I16)) => Value::I16(ranged(word, "int16")?),
+        (_, Some(Signature::U16)) => Value::U16(ranged(word, "uint16")?),
+        (_, Some(Signature::I32)) => Value::I32(ranged(word, "int32")?),
+        (_, Some(Signature::U32)) => Value::U32(ranged(word, "uint32")?),
+        (_, Some(Signature::I64)) => Value::I64(ranged(word, "int64")?),
+        (_, Some(Signature::U64)) => Value::U64(ranged(word, "uint64")?),
+        (_, Some(Signature::F64)) => Value::F64(
+            word.parse()
+                .map_err(|_| anyhow!("'{}' is not a number", word))?,
+        ),
+        (_, None) if word.contains(['.', 'e', 'E']) && !word.starts_with("0x") => Value::F64(
+            word.parse()
+                .map_err(|_| anyhow!("Unknown value '{}'", word))?,
+        ),
+        (_, None) => Value::I32(ranged(word, "int32")?),
+        (_, Some(other)) => bail!("Expected a value of type '{}', found '{}'", other, word),
+    };
+    Ok(value)
+}
+
+/// Verifies that an explicitly annotated value matches the type the caller expects.
+fn check_expected(value: Value<'static>, expected: Option<&Signature>) -> Result<Value<'static>> {
+    match expected {
+        Some(Signature::Variant) => Ok(Value::Value(Box::new(value))),
+        Some(expected) if value.value_signature() != expected => bail!(
+            "Expected a value of type '{}', found '{}'",
+            expected,
+            value.value_signature()
+        ),
+        _ => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sig(text: &str) -> Signature {
+        Signature::try_from(text).unwrap()
+    }
+
+    #[test]
+    fn untyped_arguments_are_inferred() {
+        let args = parse_args("'a', 5, -1.5, true", None).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                Value::from("a"),
+                Value::I32(5),
+                Value::F64(-1.5),
+                Value::Bool(true)
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_text_has_no_arguments() {
+        assert!(parse_args("  ", None).unwrap().is_empty());
+        assert!(parse_args("", Some(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn quoting() {
+        let args = parse_args(r#"'it\'s', "say \"hi\"", 'a, b'"#, None).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                Value::from("it's"),
+                Value::from("say \"hi\""),
+                Value::from("a, b")
+            ]
+        );
+        assert!(parse_args("'unterminated", None).is_err());
+    }
+
+    #[test]
+    fn typed_arguments_follow_the_signature() {
+        let args = parse_args("5, 'x', '/org/a'", Some(&[sig("u"), sig("s"), sig("o")])).unwrap();
+        assert_eq!(args[0], Value::U32(5));
+        assert_eq!(args[1], Value::from("x"));
+        assert_eq!(
+            args[2],
+            Value::ObjectPath(ObjectPath::try_from("/org/a").unwrap())
+        );
+    }
+
+    #[test]
+    fn a_single_typed_argument() {
+        let args = parse_args("'only'", Some(&[sig("s")])).unwrap();
+        assert_eq!(args, vec![Value::from("only")]);
+    }
+
+    #[test]
+    fn typed_arguments_are_checked() {
+        assert!(parse_args("'x'", Some(&[sig("u")])).is_err());
+        assert!(parse_args("1, 2", Some(&[sig("u")])).is_err());
+        assert!(parse_args("1", Some(&[sig("u"), sig("u")])).is_err());
+    }
+
+    #[test]
+    fn type_annotations() {
+        let args = parse_args("uint32 7, @x 8, <byte 9>", None).unwrap();
+        assert_eq!(args[0], Value::U32(7));
+        assert_eq!(args[1], Value::I64(8));
+        assert_eq!(args[2], Value::Value(Box::new(Value::U8(9))));
+    }
+
+    #[test]
+    fn containers() {
+        let args = parse_args("[1, 2], {'a': <1>}, (1, 'b')", None).unwrap();
+        assert_eq!(args[0].value_signature().to_string(), "ai");
+        assert_eq!(args[1].value_signature().to_string(), "a{sv}");
+        assert_eq!(args[2].value_signature().to_string(), "(is)");
+    }
+
+    #[test]
+    fn formatted_arguments_parse_back() {
+        let text = "uint32 7, @x 8, <byte 9>, [1, 2], {'a': <@u 1>}, ('it\\'s',)";
+        let args = parse_args(text, None).unwrap();
+        // Only values inside variants carry their type, the arguments take it from the signature
+        let signatures: Vec<Signature> = args
+            .iter()
+            .map(|arg| arg.value_signature().clone())
+            .collect();
+        assert_eq!(
+            parse_args(&format_args(&args), Some(&signatures)).unwrap(),
+            args
+        );
+    }
+}
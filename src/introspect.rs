@@ -0,0 +1,425 @@
+use crate::bus::BusType;
+use anyhow::Result;
+use ratatui::widgets::ListState;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zbus::{fdo::DBusProxy, Connection};
+use zbus_xml::{Arg, Node};
+
+const INTROSPECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Calls `org.freedesktop.DBus.Introspectable.Introspect` on `name` at `path` and parses the XML.
+pub async fn introspect(conn: &Connection, name: &str, path: &str) -> Result<Node<'static>> {
+    let reply = tokio::time::timeout(
+        INTROSPECT_TIMEOUT,
+        conn.call_method(
+            Some(name),
+            path,
+            Some("org.freedesktop.DBus.Introspectable"),
+            "Introspect",
+            &(),
+        ),
+    )
+    .await??;
+    let xml: String = reply.body().deserialize()?;
+    Ok(Node::from_reader(xml.as_bytes())?)
+}
+
+type CacheKey = (BusType, String, String);
+/// Introspection data of an object, `None` if it could not be introspected.
+pub type CacheEntry = Option<Arc<Node<'static>>>;
+
+/// Introspection data cached per bus, name and object path.
+/// Failed lookups are cached as well, so a service that does not answer is only asked once.
+#[derive(Default)]
+pub struct IntrospectionCache {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Lookups started by `fetch` that have not been received yet.
+    fetching: HashSet<CacheKey>,
+    fetched: Arc<Mutex<Vec<(CacheKey, CacheEntry)>>>,
+}
+
+impl IntrospectionCache {
+    pub async fn get(
+        &mut self,
+        conn: &Connection,
+        bus: BusType,
+        name: &str,
+        path: &str,
+    ) -> Option<Arc<Node<'static>>> {
+        let key = (bus, name.to_string(), path.to_string());
+        if let Some(entry) = self.entries.get(&key) {
+            return entry.clone();
+        }
+
+        let node = lookup(conn, name, path).await;
+        self.entries.insert(key, node.clone());
+        node
+    }
+
+    /// The cached data without waiting for it: `None` if the object was not looked up yet,
+    /// `Some(None)` if the lookup failed.
+    pub fn cached(
+        &self,
+        bus: BusType,
+        name: &str,
+        path: &str,
+    ) -> Option<Option<Arc<Node<'static>>>> {
+        self.entries
+            .get(&(bus, name.to_string(), path.to_string()))
+            .cloned()
+    }
+
+    /// Looks the object up in the background, the result is cached by a later `receive`.
+    pub fn fetch(&mut self, conn: &Connection, bus: BusType, name: &str, path: &str) {
+        let key = (bus, name.to_string(), path.to_string());
+        if self.entries.contains_key(&key) || !self.fetching.insert(key.clone()) {
+            return;
+        }
+        let conn = conn.clone();
+        let fetched = Arc::clone(&self.fetched);
+        tokio::spawn(async move {
+            let node = lookup(&conn, &key.1, &key.2).await;
+            fetched
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push((key, node));
+        });
+    }
+
+    /// Caches the lookups finished in the background, returns whether there were any.
+    pub fn receive(&mut self) -> bool {
+        let fetched = std::mem::take(&mut *self.fetched.lock().unwrap_or_else(|e| e.into_inner()));
+        let received = !fetched.is_empty();
+        for (key, node) in fetched {
+            self.fetching.remove(&key);
+            self.entries.insert(key, node);
+        }
+        received
+    }
+}
+
+/// Introspects an object, logging instead of failing.
+pub async fn lookup(conn: &Connection, name: &str, path: &str) -> CacheEntry {
+    match introspect(conn, name, path).await {
+        Ok(node) => Some(Arc::new(node)),
+        Err(e) => {
+            tracing::debug!("Introspection of {} {} failed: {}", name, path, e);
+            None
+        }
+    }
+}
+
+/// Formats introspected arguments as `name: sig, ...`, leaving out names that are not declared.
+pub fn format_args(args: &[Arg]) -> String {
+    args.iter()
+        .map(|arg| match arg.name() {
+            Some(name) => format!("{}: {}", name, arg.ty().inner()),
+            None => arg.ty().inner().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Which declared arguments of a member a message carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberArgs {
+    MethodIn,
+    MethodOut,
+    Signal,
+}
+
+/// Labels for the arguments of a member of an introspected object, such as
+/// `interface_name (s)`, or `None` if the object has no such member. An empty `interface`
+/// matches any interface of the object. Arguments without a declared name are labelled
+/// `i_N (sig)`.
+pub fn argument_labels(
+    node: &Node,
+    interface: &str,
+    member: &str,
+    kind: MemberArgs,
+) -> Option<Vec<String>> {
+    let interfaces = node
+        .interfaces()
+        .iter()
+        .filter(|iface| interface.is_empty() || iface.name().as_str() == interface);
+
+    let args: Vec<Arg> = match kind {
+        MemberArgs::Signal => interfaces
+            .flat_map(|iface| iface.signals())
+            .find(|signal| signal.name().as_str() == member)?
+            .args()
+            .to_vec(),
+        MemberArgs::MethodIn | MemberArgs::MethodOut => interfaces
+            .flat_map(|iface| iface.methods())
+            .find(|method| method.name().as_str() == member)?
+            .args()
+            .iter()
+            // Method arguments without a direction are inputs
+            .filter(|arg| {
+                (arg.direction() == Some(zbus_xml::ArgDirection::Out))
+                    == (kind == MemberArgs::MethodOut)
+            })
+            .cloned()
+            .collect(),
+    };
+
+    Some(
+        args.iter()
+            .enumerate()
+            .map(|(i, arg)| match arg.name() {
+                Some(name) => format!("{} ({})", name, arg.ty().inner()),
+                None => format!("i_{} ({})", i + 1, arg.ty().inner()),
+            })
+            .collect(),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserNodeKind {
+    Name,
+    Object,
+    Interface,
+    Method,
+    Signal,
+    Property,
+}
+
+impl BrowserNodeKind {
+    pub fn is_expandable(&self) -> bool {
+        matches!(
+            self,
+            BrowserNodeKind::Name | BrowserNodeKind::Object | BrowserNodeKind::Interface
+        )
+    }
+}
+
+/// A single row in the introspection browser tree.
+#[derive(Debug, Clone)]
+pub struct BrowserNode {
+    pub kind: BrowserNodeKind,
+    pub depth: usize,
+    pub label: String,
+    pub name: String,
+    pub path: String,
+    pub interface: String,
+    pub member: String,
+    pub expanded: bool,
+}
+
+/// State of the introspection browser: a flattened tree where the children of an expanded node
+/// directly follow it with a larger depth.
+#[derive(Default)]
+pub struct Browser {
+    pub bus: Option<BusType>,
+    pub nodes: Vec<BrowserNode>,
+    pub state: ListState,
+}
+
+impl Browser {
+    /// Lists the names on `bus` as the top level of the tree, well-known names first.
+    pub async fn open(&mut self, conn: &Connection, bus: BusType) -> Result<()> {
+        let proxy = DBusProxy::new(conn).await?;
+        let mut names: Vec<String> = proxy
+            .list_names()
+            .await?
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect();
+        names.sort_by(|a, b| a.starts_with(':').cmp(&b.starts_with(':')).then(a.cmp(b)));
+
+        self.bus = Some(bus);
+        self.nodes = names
+            .into_iter()
+            .map(|name| BrowserNode {
+                kind: BrowserNodeKind::Name,
+                depth: 0,
+                label: name.clone(),
+                name,
+                path: String::new(),
+                interface: String::new(),
+                member: String::new(),
+                expanded: false,
+            })
+            .collect();
+        self.state
+            .select(if self.nodes.is_empty() { None } else { Some(0) });
+        Ok(())
+    }
+
+    pub fn selected(&self) -> Option<&BrowserNode> {
+        self.state.selected().and_then(|i| self.nodes.get(i))
+    }
+
+    /// Expands the selected node, introspecting its object when needed.
+    pub async fn expand(
+        &mut self,
+        conn: &Connection,
+        cache: &mut IntrospectionCache,
+    ) -> Result<()> {
+        let (Some(index), Some(bus)) = (self.state.selected(), self.bus) else {
+            return Ok(());
+        };
+        let Some(node) = self.nodes.get(index).cloned() else {
+            return Ok(());
+        };
+        if node.expanded || !node.kind.is_expandable() {
+            return Ok(());
+        }
+
+        let child = |kind, label: String, path: &str, interface: &str, member: &str| BrowserNode {
+            kind,
+            depth: node.depth + 1,
+            label,
+            name: node.name.clone(),
+            path: path.to_string(),
+            interface: interface.to_string(),
+            member: member.to_string(),
+            expanded: false,
+        };
+
+        let children: Vec<BrowserNode> = match node.kind {
+            BrowserNodeKind::Name => {
+                vec![child(BrowserNodeKind::Object, "/".to_string(), "/", "", "")]
+            }
+            BrowserNodeKind::Object => {
+                let introspected = cache
+                    .get(conn, bus, &node.name, &node.path)
+                    .await
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Could not introspect {} {}", node.name, node.path)
+                    })?;
+                let mut children: Vec<BrowserNode> = introspected
+                    .interfaces()
+                    .iter()
+                    .map(|iface| {
+                        let iface_name = iface.name().to_string();
+                        child(
+                            BrowserNodeKind::Interface,
+                            iface_name.clone(),
+                            &node.path,
+                            &iface_name,
+                            "",
+                        )
+                    })
+                    .collect();
+                children.extend(introspected.nodes().iter().filter_map(|sub| {
+                    let sub_name = sub.name()?;
+                    let sub_path = if node.path == "/" {
+                        format!("/{}", sub_name)
+                    } else {
+                        format!("{}/{}", node.path, sub_name)
+                    };
+                    Some(child(
+                        BrowserNodeKind::Object,
+                        sub_path.clone(),
+                        &sub_path,
+                        "",
+                        "",
+                    ))
+                }));
+                children
+            }
+            BrowserNodeKind::Interface => {
+                let introspected = cache
+                    .get(conn, bus, &node.name, &node.path)
+                    .await
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Could not introspect {} {}", node.name, node.path)
+                    })?;
+                let Some(iface) = introspected
+                    .interfaces()
+                    .iter()
+                    .find(|iface| iface.name().as_str() == node.interface)
+                else {
+                    return Ok(());
+                };
+
+                let mut children = Vec::new();
+                for method in iface.methods() {
+                    let (inputs, outputs): (Vec<Arg>, Vec<Arg>) = method
+                        .args()
+                        .iter()
+                        .cloned()
+                        .partition(|arg| arg.direction() != Some(zbus_xml::ArgDirection::Out));
+                    let label = format!(
+                        "method {}({}) → ({})",
+                        method.name(),
+                        format_args(&inputs),
+                        format_args(&outputs)
+                    );
+                    children.push(child(
+                        BrowserNodeKind::Method,
+                        label,
+                        &node.path,
+                        &node.interface,
+                        method.name().as_str(),
+                    ));
+                }
+                for signal in iface.signals() {
+                    let label = format!("signal {}({})", signal.name(), format_args(signal.args()));
+                    children.push(child(
+                        BrowserNodeKind::Signal,
+                        label,
+                        &node.path,
+                        &node.interface,
+                        signal.name().as_str(),
+                    ));
+                }
+                for property in iface.properties() {
+                    let access = match (property.access().read(), property.access().write()) {
+                        (true, true) => "readwrite",
+                        (true, false) => "read",
+                        (false, true) => "write",
+                        (false, false) => "",
+                    };
+                    let label = format!(
+                        "property {}: {} [{}]",
+                        property.name(),
+                        property.ty().inner(),
+                        access
+                    );
+                    children.push(child(
+                        BrowserNodeKind::Property,
+                        label,
+                        &node.path,
+                        &node.interface,
+                        property.name().as_str(),
+                    ));
+                }
+                children
+            }
+            _ => Vec::new(),
+        };
+
+        self.nodes[index].expanded = true;
+        self.nodes.splice(index + 1..index + 1, children);
+        Ok(())
+    }
+
+    /// Collapses the selected node, or moves to its parent if it is not expanded.
+    pub fn collapse(&mut self) {
+        let Some(index) = self.state.selected() else {
+            return;
+        };
+        let Some(node) = self.nodes.get(index) else {
+            return;
+        };
+
+        if node.expanded {
+            let depth = node.depth;
+            let end = self.nodes[index + 1..]
+                .iter()
+                .position(|n| n.depth <= depth)
+                .map_or(self.nodes.len(), |p| index + 1 + p);
+            self.nodes.drain(index + 1..end);
+            self.nodes[index].expanded = false;
+        } else if let Some(parent) = self.nodes[..index]
+            .iter()
+            .rposition(|n| n.depth < node.depth)
+        {
+            self.state.select(Some(parent));
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::{bail, Context, Result};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+
+/// A private session bus, like the one `dbus-run-session` starts.
+pub struct IsolatedBus {
+    pub address: String,
+    daemon: Child,
+}
+
+impl IsolatedBus {
+    /// Starts `dbus-daemon --session` and waits for it to print its address.
+    /// The daemon is killed when this is dropped.
+    pub async fn start() -> Result<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--nopidfile", "--print-address"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to start dbus-daemon, is it installed?")?;
+        let stdout = daemon.stdout.take().context("No output from dbus-daemon")?;
+        let mut address = String::new();
+        BufReader::new(stdout).read_line(&mut address).await?;
+        let address = address.trim().to_string();
+        if address.is_empty() {
+            bail!("dbus-daemon exited without printing its address");
+        }
+        Ok(IsolatedBus { address, daemon })
+    }
+
+    /// Shuts the bus down, disconnecting everything still on it.
+    pub async fn stop(mut self) -> Result<()> {
+        self.daemon.kill().await?;
+        Ok(())
+    }
+}
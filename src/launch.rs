@@ -0,0 +1,226 @@
+use crate::body_tree::body_args;
+use crate::bus::{self, Item};
+use anyhow::{Context, Result};
+use ratatui::widgets::ListState;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zbus::message::Type;
+use zbus::zvariant::Value;
+
+/// File the output of the launched command goes to, the terminal belongs to the TUI.
+pub const OUTPUT_FILE: &str = "d-buddy-launch.log";
+
+/// How often the process tree is scanned for new descendants. Processes that live shorter
+/// are still recognized by their ancestors.
+const SCAN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A command started by d-buddy, traced together with every process it starts.
+pub struct Launch {
+    pub command: Vec<String>,
+    pub pid: u32,
+    /// Whether only the messages of the command are listed.
+    pub filter: bool,
+    /// The command and its descendants. Pids stay after their process exits, so messages
+    /// that arrive late still match.
+    pids: Arc<Mutex<HashSet<u32>>>,
+    exit: Arc<Mutex<Option<ExitStatus>>>,
+    /// Lines of the summary shown once the command has exited.
+    pub summary: Option<Vec<String>>,
+    pub summary_state: ListState,
+}
+
+impl Launch {
+    /// Starts the command with its output going to `OUTPUT_FILE`, and keeps collecting the pids
+    /// of its process tree until it exits. The command is killed when d-buddy quits.
+    /// `session_bus` replaces the session bus address the command connects to.
+    pub fn spawn(command: &[String], session_bus: Option<String>) -> Result<Self> {
+        let output = std::fs::File::create(OUTPUT_FILE)
+            .with_context(|| format!("Failed to create {}", OUTPUT_FILE))?;
+        let mut process = tokio::process::Command::new(&command[0]);
+        process
+            .args(&command[1..])
+            .stdin(Stdio::null())
+            .stdout(output.try_clone()?)
+            .stderr(output)
+            .kill_on_drop(true);
+        if let Some(address) = session_bus {
+            process.env("DBUS_SESSION_BUS_ADDRESS", address);
+        }
+        let mut child = process
+            .spawn()
+            .with_context(|| format!("Failed to start {}", command[0]))?;
+        let pid = child.id().context("The command exited immediately")?;
+
+        let pids = Arc::new(Mutex::new(HashSet::from([pid])));
+        let exit = Arc::new(Mutex::new(None));
+        let pids_clone = Arc::clone(&pids);
+        let exit_clone = Arc::clone(&exit);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SCAN_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    status = child.wait() => {
+                        scan(&pids_clone).await;
+                        *exit_clone.lock().unwrap_or_else(|e| e.into_inner()) = status.ok();
+                        break;
+                    }
+                    _ = interval.tick() => scan(&pids_clone).await,
+                }
+            }
+        });
+
+        Ok(Launch {
+            command: command.to_vec(),
+            pid,
+            filter: true,
+            pids,
+            exit,
+            summary: None,
+            summary_state: ListState::default(),
+        })
+    }
+
+    /// A snapshot of the pids traced so far.
+    pub fn pids(&self) -> HashSet<u32> {
+        self.pids.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        *self.exit.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the message was sent or received by the traced processes. Processes too short
+    /// lived to be seen in the process tree are recognized by their ancestors.
+    pub fn involves(pids: &HashSet<u32>, item: &Item) -> bool {
+        Self::sent_by(pids, item)
+            || item.receiver_pid.is_some_and(|pid| pids.contains(&pid))
+            || item
+                .receiver_ancestors
+                .iter()
+                .any(|ancestor| pids.contains(&ancestor.pid))
+    }
+
+    fn sent_by(pids: &HashSet<u32>, item: &Item) -> bool {
+        item.pid.is_some_and(|pid| pids.contains(&pid))
+            || item
+                .ancestors
+                .iter()
+                .any(|ancestor| pids.contains(&ancestor.pid))
+    }
+
+    /// Summarizes what the command did on the buses: its connections, the well-known names it
+    /// owned and the method calls it made, most frequent first.
+    pub fn build_summary(&self, messages: &[Item]) -> Vec<String> {
+        let pids = self.pids();
+        let mut traced = pids.clone();
+        let mut unique_names = BTreeSet::new();
+        for item in messages {
+            if Self::sent_by(&pids, item) {
+                traced.extend(item.pid);
+                if item.sender.starts_with(':') {
+                    unique_names.insert(item.sender.clone());
+                }
+            }
+        }
+
+        // Well-known names handed to one of its connections, told to the connection itself with
+        // NameAcquired and to everyone listening with NameOwnerChanged
+        let mut owned_names = BTreeSet::new();
+        for item in messages {
+            let Some(message) = item
+                .message
+                .as_ref()
+                .filter(|_| item.msg_type == Type::Signal)
+            else {
+                continue;
+            };
+            let (name, owner) = match (item.member.as_str(), body_args(message).as_slice()) {
+                ("NameAcquired", [Value::Str(name)]) => (name.to_string(), item.receiver.clone()),
+                ("NameOwnerChanged", [Value::Str(name), _, Value::Str(new_owner)]) => {
+                    (name.to_string(), new_owner.to_string())
+                }
+                _ => continue,
+            };
+            if !name.starts_with(':') && unique_names.contains(&owner) {
+                owned_names.insert(name);
+            }
+        }
+
+        let mut calls: HashMap<String, usize> = HashMap::new();
+        for item in messages {
+            if item.msg_type == Type::MethodCall && Self::sent_by(&pids, item) {
+                let call = format!("{} {}.{}", item.receiver, item.interface, item.member);
+                *calls.entry(call).or_default() += 1;
+            }
+        }
+        let mut calls: Vec<(String, usize)> = calls.into_iter().collect();
+        calls.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let status = match self.exit_status() {
+            Some(status) => format!("exited with {}", status),
+            None => "is still running".to_string(),
+        };
+        let mut lines = vec![
+            format!("{} (pid {}) {}", self.command.join(" "), self.pid, status),
+            format!("Processes traced: {}", traced.len()),
+            format!("Output written to {}", OUTPUT_FILE),
+            String::new(),
+            format!("Connections ({})", unique_names.len()),
+        ];
+        lines.extend(unique_names.iter().map(|name| format!("  {}", name)));
+        lines.push(String::new());
+        lines.push(format!("Names owned ({})", owned_names.len()));
+        lines.extend(owned_names.iter().map(|name| format!("  {}", name)));
+        lines.push(String::new());
+        lines.push(format!(
+            "Calls made ({})",
+            calls.iter().map(|(_, count)| count).sum::<usize>()
+        ));
+        lines.extend(
+            calls
+                .iter()
+                .map(|(call, count)| format!("  {:>5}  {}", count, call)),
+        );
+        lines
+    }
+}
+
+/// Reads the process tree off the async threads, the scan goes through all of `/proc`.
+async fn scan(pids: &Arc<Mutex<HashSet<u32>>>) {
+    let pids = Arc::clone(pids);
+    let _ = tokio::task::spawn_blocking(move || add_descendants(&pids)).await;
+}
+
+/// Adds every process whose parent is already in the set, until no more are found.
+fn add_descendants(pids: &Mutex<HashSet<u32>>) {
+    let parents = parent_pids();
+    let mut pids = pids.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        let found: Vec<u32> = parents
+            .iter()
+            .filter(|(pid, ppid)| pids.contains(ppid) && !pids.contains(pid))
+            .map(|(pid, _)| *pid)
+            .collect();
+        if found.is_empty() {
+            break;
+        }
+        pids.extend(found);
+    }
+}
+
+/// The parent of every running process, read from `/proc/<pid>/stat`.
+fn parent_pids() -> HashMap<u32, u32> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            Some((pid, bus::parent_pid(pid)?))
+        })
+        .collect()
+}
@@ -1,8 +1,29 @@
+mod body_tree;
 mod bus;
+mod capture;
+mod codegen;
+mod commands;
+mod composer;
 mod config;
+mod decoders;
 mod event;
+mod expect;
+mod faults;
+mod gvariant;
+mod introspect;
+mod isolated;
+mod launch;
+mod mock;
+mod names;
+mod objects;
+mod properties;
+mod proxy;
+mod repeater;
+mod replay;
+mod sandbox;
 mod state;
 mod ui;
+mod wire;
 
 use config::Config;
 use state::{App, Mode};
@@ -12,7 +33,7 @@ use anyhow::Result; // For simplified error handling
 use arboard::Clipboard; // For clipboard access
 use std::sync::{Arc, Mutex};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::event::EventStream;
 use futures::stream::StreamExt; // For extending stream functionality, used with zbus MessageStream
 use ratatui::prelude::*;
@@ -45,16 +66,120 @@ struct Args {
     /// Set the maximum number of messages to keep in memory (rolling window)
     #[arg(long)]
     max_messages: Option<usize>,
+    /// Config file to load instead of ~/.config/d-buddy/config.toml
+    #[arg(long)]
+    config: Option<std::path::PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Command to start and trace, only its messages are shown: d-buddy -- <command> [args]
+    #[arg(last = true)]
+    launch: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Capture by proxying clients to a bus or peer, for connections that cannot be monitored
+    Proxy {
+        /// Socket to accept clients on, point them at it with unix:path=<socket>
+        #[arg(long)]
+        listen: std::path::PathBuf,
+        /// Address of the real bus or peer, ie unix:path=/run/user/1000/bus
+        #[arg(long)]
+        upstream: String,
+        /// File of fault rules to start with, one per line, ie `delay 500ms member=GetId`
+        #[arg(long)]
+        faults: Option<std::path::PathBuf>,
+        /// Command to start behind the proxy and trace: d-buddy proxy ... -- <command> [args]
+        #[arg(last = true)]
+        launch: Vec<String>,
+    },
+    /// Stand in for a service, answering calls with the replies recorded in a capture
+    Mock {
+        /// Capture file saved from the TUI with `S`
+        #[arg(long)]
+        from: std::path::PathBuf,
+        /// Name of the service to mock, claimed on the bus
+        #[arg(long)]
+        name: String,
+        /// Unique name the service had in the capture, ie ":1.42", if the capture does not
+        /// show it owning the name (repeatable)
+        #[arg(long)]
+        owner: Vec<String>,
+        /// Bus to claim the name on: session, system or a D-Bus address
+        #[arg(long, default_value = "session")]
+        bus: String,
+        /// Emit the recorded signals at the times they were captured
+        #[arg(long)]
+        timeline: bool,
+    },
+    /// Send the signals of a capture, and optionally its method calls, onto a bus again
+    Replay {
+        /// Capture file saved from the TUI with `S`
+        #[arg(long)]
+        from: std::path::PathBuf,
+        /// Bus to send to: session, system or a D-Bus address
+        #[arg(long, default_value = "session")]
+        bus: String,
+        /// Only replay messages matching this expression, as taken by expect, ie
+        /// 'interface == "org.freedesktop.UPower" and not member=Changed'
+        #[arg(long, default_value = "")]
+        filter: String,
+        /// Send the method calls too
+        #[arg(long)]
+        calls: bool,
+        /// original, fast, or a factor for the gaps between messages, ie 0.5 for twice as fast
+        #[arg(long, default_value = "original")]
+        timing: replay::Timing,
+        /// Well-known name to claim before replaying, so clients matching on it receive the signals
+        #[arg(long)]
+        name: Vec<String>,
+    },
+    /// Run a command on a private session bus of its own, shown in the Isolated tab
+    RunIsolated {
+        /// Save the messages of the private bus to this capture file on exit
+        #[arg(long)]
+        save: Option<std::path::PathBuf>,
+        /// Command to run: d-buddy run-isolated -- <command> [args]
+        #[arg(last = true, required = true)]
+        launch: Vec<String>,
+    },
+    /// Wait for messages matching a condition, exiting non-zero if they do not arrive in time
+    Expect {
+        /// Bus to watch: session, system or a D-Bus address
+        #[arg(long, visible_alias = "address", default_value = "session")]
+        bus: String,
+        /// Condition to wait for, ie 'member == "JobRemoved" and arg3 == "done"'. Process
+        /// fields such as exe or unit are looked up when a message arrives, so they miss
+        /// senders that have already exited
+        #[arg(long, required_unless_present = "file", conflicts_with = "file")]
+        filter: Option<String>,
+        /// TOML file with an ordered sequence of `[[expect]]` steps, or a YAML file (.yaml,
+        /// .yml) with an `expect` list
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+        /// How long to wait, for each step of a file unless it has its own, ie 10s or 500ms
+        #[arg(long, default_value = "30s", value_parser = faults::parse_duration)]
+        timeout: Duration,
+        /// Number of matching messages to wait for
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+    },
 }
 
 // Main asynchronous entry point of the application
 #[tokio::main]
 async fn main() -> Result<()> {
-    #[cfg(target_os = "linux")]
-    check_clipboard_utilities();
-
     let args = Args::parse();
 
+    // Only the TUI uses the clipboard
+    #[cfg(target_os = "linux")]
+    if !matches!(
+        args.command,
+        Some(Command::Mock { .. } | Command::Replay { .. } | Command::Expect { .. })
+    ) {
+        check_clipboard_utilities();
+    }
+
     let _log_guard = if args.log {
         tracing_log::LogTracer::init().expect("Failed to set logger");
         let file_appender = tracing_appender::rolling::daily(".", "d-buddy.log");
@@ -88,13 +213,140 @@ async fn main() -> Result<()> {
         config.max_messages = max_msgs;
     }
     config.enable_debug_ui = args.debug_ui;
+    // An explicitly given config file has to exist, the default one is optional
+    match &args.config {
+        Some(path) => config.load_file(path)?,
+        None => {
+            if let Some(path) = Config::default_path().filter(|path| path.exists()) {
+                config.load_file(&path)?;
+            }
+        }
+    }
 
     let mut app = App::default();
     app.initialize_static_ui_elements(&config);
-    let session_messages = bus::dbus_listener(BusType::Session).await?;
-    let system_messages = bus::dbus_listener(BusType::System).await?;
-    app.messages.insert(BusType::Session, session_messages);
-    app.messages.insert(BusType::System, system_messages);
+    let mut isolated_bus = None;
+    match &args.command {
+        Some(Command::Mock {
+            from,
+            name,
+            owner,
+            bus,
+            timeline,
+        }) => return mock::run(from, name, owner, bus, *timeline).await,
+        Some(Command::Replay {
+            from,
+            bus,
+            filter,
+            calls,
+            timing,
+            name,
+        }) => return replay::run(from, bus, filter, *calls, *timing, name).await,
+        Some(Command::Expect {
+            bus,
+            filter,
+            file,
+            timeout,
+            count,
+        }) => {
+            let expectations = match (filter, file) {
+                (Some(filter), _) => vec![expect::Expectation::new(filter, *count, *timeout)?],
+                (None, Some(file)) => expect::load(file, *timeout)?,
+                (None, None) => anyhow::bail!("Either --filter or --file is needed"),
+            };
+            return expect::run(bus, &expectations).await;
+        }
+        Some(Command::Proxy {
+            listen,
+            upstream,
+            faults,
+            ..
+        }) => {
+            if let Some(path) = faults {
+                *app.faults.rules.lock().unwrap_or_else(|e| e.into_inner()) =
+                    faults::load_rules(path)?;
+            }
+            app.faults.proxying = true;
+            // Proxied traffic is shown as the session bus. A peer never answers Hello,
+            // so a connection for calls is only kept if upstream turns out to be a bus.
+            let conn = tokio::time::timeout(Duration::from_secs(2), bus::connect_address(upstream))
+                .await
+                .ok()
+                .and_then(|conn| conn.ok());
+            let proxy_messages = proxy::proxy_listener(
+                listen,
+                upstream,
+                conn.clone(),
+                Arc::clone(&app.faults.rules),
+            )
+            .await?;
+            app.messages.insert(BusType::Session, proxy_messages);
+            app.messages.insert(
+                BusType::System,
+                Arc::new(tokio::sync::Mutex::new(Vec::new())),
+            );
+            if let Some(conn) = conn {
+                app.connections.insert(BusType::Session, conn);
+            }
+            app.status_message = format!("Proxying unix:path={} to {}", listen.display(), upstream);
+        }
+        Some(Command::RunIsolated { .. }) => {
+            let bus = isolated::IsolatedBus::start().await?;
+            let conn = bus::connect_address(&bus.address).await?;
+            app.messages.insert(
+                BusType::Isolated,
+                bus::listen(conn, BusType::Isolated).await?,
+            );
+            app.connections
+                .insert(BusType::Isolated, bus::connect_address(&bus.address).await?);
+            // The real buses are shown too when they are there
+            for t in [BusType::Session, BusType::System] {
+                let messages = match bus::dbus_listener(t).await {
+                    Ok(messages) => messages,
+                    Err(_) => Arc::new(tokio::sync::Mutex::new(Vec::new())),
+                };
+                app.messages.insert(t, messages);
+                if let Ok(conn) = bus::connect(t).await {
+                    app.connections.insert(t, conn);
+                }
+            }
+            app.stream = BusType::Isolated;
+            app.isolated_address = Some(bus.address.clone());
+            isolated_bus = Some(bus);
+        }
+        None => {
+            let session_messages = bus::dbus_listener(BusType::Session).await?;
+            let system_messages = bus::dbus_listener(BusType::System).await?;
+            app.messages.insert(BusType::Session, session_messages);
+            app.messages.insert(BusType::System, system_messages);
+            app.connections
+                .insert(BusType::Session, bus::connect(BusType::Session).await?);
+            app.connections
+                .insert(BusType::System, bus::connect(BusType::System).await?);
+        }
+    }
+
+    // Behind the proxy, the command is pointed at it so all of its traffic is seen
+    let (command, session_bus) = match &args.command {
+        Some(Command::Proxy { listen, launch, .. }) => {
+            (launch, Some(format!("unix:path={}", listen.display())))
+        }
+        Some(Command::RunIsolated { launch, .. }) => (launch, app.isolated_address.clone()),
+        _ => (&args.launch, None),
+    };
+    if !command.is_empty() {
+        let mut launch = launch::Launch::spawn(command, session_bus)?;
+        // Everything on the private bus is there because of the command, services it
+        // activated included, so nothing is hidden
+        launch.filter = isolated_bus.is_none();
+        app.status_message = format!(
+            "Tracing {} (pid {}), output goes to {}",
+            command[0],
+            launch.pid,
+            launch::OUTPUT_FILE
+        );
+        app.launch = Some(launch);
+    }
 
     if args.check {
         println!("Check mode: Setup successful. App initialized and listeners started.");
@@ -105,6 +357,28 @@ async fn main() -> Result<()> {
         let mut terminal = setup_terminal()?;
         run(&mut terminal, &mut app, &config).await?;
         restore_terminal()?;
+        if let Some(lines) = app
+            .launch
+            .as_ref()
+            .and_then(|launch| launch.summary.as_ref())
+        {
+            println!("{}", lines.join("\n"));
+        }
+        if let Some(Command::RunIsolated {
+            save: Some(path), ..
+        }) = &args.command
+        {
+            if let Some(messages) = app.messages.get(&BusType::Isolated) {
+                let count = capture::save(path, &messages.lock().await)?;
+                println!("Saved {} messages to {}", count, path.display());
+            }
+        }
+        if let Some(bus) = isolated_bus {
+            bus.stop().await?;
+        }
+        if let Some(Command::Proxy { listen, .. }) = &args.command {
+            let _ = std::fs::remove_file(listen);
+        }
         Ok(())
     }
 }
@@ -210,14 +484,17 @@ async fn run(
             0
         };
 
-        let both_count = session_count + system_count;
+        let isolated_count = match app.messages.get(&BusType::Isolated) {
+            Some(arc) => Some(arc.lock().await.len()),
+            None => None,
+        };
 
         // Create a scope to ensure the lock is released before drawing
 
         {
             let _processing_span = tracing::info_span!("message_processing").entered();
             let all_messages = match app.stream {
-                BusType::Session | BusType::System => {
+                BusType::Session | BusType::System | BusType::Isolated => {
                     let _message_collection_span =
                         tracing::info_span!("message_collection_single_bus").entered();
                     let mut messages = app.messages.get(&app.stream).unwrap().lock().await.clone();
@@ -246,7 +523,7 @@ async fn run(
                     {
                         let _combined_sort_span =
                             tracing::info_span!("message_collection_combined_sort").entered();
-                        combined_messages.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+                        combined_messages.sort_by_key(|a| a.timestamp);
                     }
                     if combined_messages.len() > config.max_messages {
                         let start = combined_messages.len() - config.max_messages;
@@ -257,6 +534,72 @@ async fn run(
             };
             tracing::debug!("Filtering: all_messages.len() = {}", all_messages.len());
 
+            if app.mode == Mode::PendingCalls {
+                let _pending_span = tracing::info_span!("pending_calls").entered();
+                app.pending_calls = bus::pending_calls(&all_messages);
+            }
+
+            if app.mode == Mode::Errors {
+                let _errors_span = tracing::info_span!("error_summaries").entered();
+                app.error_summaries = bus::error_summaries(&all_messages);
+            }
+
+            // Argument labels looked up in the background since the details were shown
+            event::receive_composer_reply(app, config);
+            app.repeater.receive();
+            if app.introspection_cache.receive() && app.detail_labels_pending && app.show_details {
+                event::update_detail_text(app, config).await;
+            }
+
+            if app.mode == Mode::Properties {
+                if let Some(bus) = app.properties.bus {
+                    let _properties_span = tracing::info_span!("properties_changes").entered();
+                    if let Some(conn) = app.connections.get(&bus) {
+                        let messages = app.messages.get(&bus).unwrap().lock().await;
+                        app.properties.apply_changes(conn, &messages);
+                    }
+                }
+            }
+
+            if app.mode == Mode::Names {
+                if let Some(bus) = app.names.bus {
+                    if let Some(conn) = app.connections.get(&bus) {
+                        let messages = app.messages.get(&bus).unwrap().lock().await;
+                        if let Some(status) = app.names.receive(conn, &messages) {
+                            app.status_message = status;
+                        }
+                    }
+                }
+            }
+
+            if app.mode == Mode::Objects {
+                if let Some(bus) = app.objects.bus {
+                    let _objects_span = tracing::info_span!("object_model_events").entered();
+                    let messages = app.messages.get(&bus).unwrap().lock().await;
+                    app.objects.update_events(&messages);
+                }
+            }
+
+            if let Some(launch) = &mut app.launch {
+                if launch.summary.is_none() && launch.exit_status().is_some() {
+                    let _launch_span = tracing::info_span!("launch_summary").entered();
+                    let mut messages = Vec::new();
+                    for arc in app.messages.values() {
+                        messages.extend(arc.lock().await.iter().cloned());
+                    }
+                    launch.summary = Some(launch.build_summary(&messages));
+                    launch.summary_state.select(Some(0));
+                    if app.mode == Mode::Normal {
+                        app.mode = Mode::LaunchSummary;
+                    }
+                }
+            }
+            let launch_pids = app
+                .launch
+                .as_ref()
+                .filter(|launch| launch.filter)
+                .map(|launch| launch.pids());
+
             let filter_text = app.input.value();
 
             {
@@ -282,32 +625,32 @@ async fn run(
 
                             if !app.filter_criteria.is_empty() {
                                 for (field, value) in &app.filter_criteria {
-                                    let item_field_value: std::borrow::Cow<'_, str> =
-                                        match field.as_str() {
-                                            "sender" => item.sender_display(),
-                                            "member" => item.member.as_str().into(),
-                                            "path" => item.path.as_str().into(),
-                                            "serial" => item.serial.as_str().into(),
-                                            "reply_serial" => item.reply_serial.as_str().into(),
-                                            _ => {
-                                                passes_field_filters = false;
-                                                "".into() // Return an empty Cow
-                                            }
-                                        };
-                                    if passes_field_filters && !item_field_value.contains(value) {
+                                    if !item.matches_field(field, value) {
                                         passes_field_filters = false;
-
                                         break;
                                     }
                                 }
                             }
 
-                            let passes_general_filter = filter_text.is_empty()
-                                || item.sender.contains(filter_text)
-                                || item.member.contains(filter_text)
-                                || item.path.contains(filter_text);
+                            let passes_type_toggles = match item.msg_type {
+                                zbus::message::Type::Error => true,
+                                zbus::message::Type::MethodReturn => {
+                                    !app.hide_returns && !app.only_errors
+                                }
+                                _ => !app.only_errors,
+                            };
+
+                            let passes_general_filter =
+                                filter_text.is_empty() || item.matches_text(filter_text);
 
-                            passes_field_filters && passes_general_filter
+                            let passes_launch = launch_pids
+                                .as_ref()
+                                .is_none_or(|pids| launch::Launch::involves(pids, item));
+
+                            passes_field_filters
+                                && passes_type_toggles
+                                && passes_general_filter
+                                && passes_launch
                         }
                     })
                     .cloned()
@@ -332,6 +675,20 @@ async fn run(
 
                             bus::GroupingType::Serial => a.serial.cmp(&b.serial),
 
+                            bus::GroupingType::Executable => a.exe.cmp(&b.exe),
+
+                            bus::GroupingType::Unit => a.unit.cmp(&b.unit),
+
+                            bus::GroupingType::Slice => a.slice.cmp(&b.slice),
+
+                            bus::GroupingType::Parent => a.parent().cmp(&b.parent()),
+
+                            bus::GroupingType::Sandbox => {
+                                a.sandbox.to_string().cmp(&b.sandbox.to_string())
+                            }
+
+                            bus::GroupingType::AppId => a.app_id.cmp(&b.app_id),
+
                             bus::GroupingType::None => std::cmp::Ordering::Equal,
                         };
 
@@ -365,7 +722,7 @@ async fn run(
                     config,
                     session_count,
                     system_count,
-                    both_count,
+                    isolated_count,
                     &temp_filtered_items[..],
                 );
                 app.filtered_and_sorted_items = temp_filtered_items;
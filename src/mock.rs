@@ -0,0 +1,391 @@
+use crate::body_tree::body_args;
+use crate::bus;
+use crate::capture::{self, CapturedMessage};
+use crate::gvariant;
+use anyhow::{bail, Result};
+use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use zbus::message::Type;
+use zbus::zvariant::Value;
+use zbus::{Connection, MessageStream};
+
+/// Object path and interface of the methods that control the mock itself.
+const CONTROL_PATH: &str = "/dev/dbuddy/Mock";
+const CONTROL_INTERFACE: &str = "dev.dbuddy.Mock";
+
+/// A recorded call to the mocked service and the reply it got.
+struct Exchange {
+    interface: String,
+    member: String,
+    args: String,
+    reply: CapturedMessage,
+}
+
+/// Replies and signals of one service, taken from a capture.
+pub struct Mock {
+    exchanges: Vec<Exchange>,
+    signals: Vec<CapturedMessage>,
+    /// How often each call has been answered, so repeated calls walk through the recorded
+    /// replies in order and then keep repeating the last one.
+    answered: HashMap<(String, String, Option<String>), usize>,
+    /// Time of the first captured message, the start of the signal timeline.
+    start: f64,
+}
+
+impl Mock {
+    /// Picks the calls to `name` with their replies, and the signals it sent, out of a capture.
+    /// Replies and signals come from the unique name the service had while it was captured.
+    /// It is learned from the replies to calls addressed to `name`, from `NameOwnerChanged`
+    /// and `GetNameOwner` in the capture, or given in `owners`.
+    pub fn from_capture(messages: &[CapturedMessage], name: &str, owners: &[String]) -> Self {
+        let is_reply =
+            |m: &&CapturedMessage| m.msg_type == "method_return" || m.msg_type == "error";
+        let calls: HashMap<(&str, u32), &CapturedMessage> = messages
+            .iter()
+            .filter(|m| m.msg_type == "method_call")
+            .map(|m| ((m.sender.as_str(), m.serial), m))
+            .collect();
+
+        let mut names: HashSet<String> = HashSet::from([name.to_string()]);
+        names.extend(owners.iter().cloned());
+        names.extend(recorded_owners(messages, &calls, name));
+        for reply in messages.iter().filter(is_reply) {
+            let call = reply
+                .reply_serial
+                .and_then(|serial| calls.get(&(reply.destination.as_str(), serial)));
+            if call.is_some_and(|call| call.destination == name) {
+                names.insert(reply.sender.clone());
+            }
+        }
+
+        let exchanges = messages
+            .iter()
+            .filter(is_reply)
+            .filter(|reply| names.contains(reply.sender.as_str()))
+            .filter_map(|reply| {
+                let call = calls.get(&(reply.destination.as_str(), reply.reply_serial?))?;
+                Some(Exchange {
+                    interface: call.interface.clone(),
+                    member: call.member.clone(),
+                    args: call.args.clone(),
+                    reply: reply.clone(),
+                })
+            })
+            .collect();
+        let signals = messages
+            .iter()
+            .filter(|m| m.msg_type == "signal" && names.contains(m.sender.as_str()))
+            .cloned()
+            .collect();
+
+        Mock {
+            exchanges,
+            signals,
+            answered: HashMap::new(),
+            start: messages.first().map_or(0.0, |m| m.timestamp),
+        }
+    }
+
+    /// The recorded reply for a call. Calls with the same arguments are preferred, otherwise
+    /// any recorded call of the same method is used. The interface is ignored if the call has none.
+    fn reply_for(&mut self, interface: &str, member: &str, args: &str) -> Option<CapturedMessage> {
+        let method = |exchange: &&Exchange| {
+            exchange.member == member && (interface.is_empty() || exchange.interface == interface)
+        };
+        let exact: Vec<&Exchange> = self
+            .exchanges
+            .iter()
+            .filter(method)
+            .filter(|exchange| exchange.args == args)
+            .collect();
+        let (key, candidates) = if exact.is_empty() {
+            let any: Vec<&Exchange> = self.exchanges.iter().filter(method).collect();
+            ((interface.to_string(), member.to_string(), None), any)
+        } else {
+            let key = (
+                interface.to_string(),
+                member.to_string(),
+                Some(args.to_string()),
+            );
+            (key, exact)
+        };
+        let last = candidates.len().checked_sub(1)?;
+        let count = self.answered.entry(key).or_default();
+        let reply = candidates[(*count).min(last)].reply.clone();
+        *count += 1;
+        Some(reply)
+    }
+}
+
+/// Claims `name` on the bus and answers method calls with the replies recorded in the capture.
+/// `bus` is `session`, `system` or a D-Bus address. Runs until interrupted.
+pub async fn run(
+    capture_path: &Path,
+    name: &str,
+    owners: &[String],
+    bus: &str,
+    timeline: bool,
+) -> Result<()> {
+    let messages = capture::load(capture_path)?;
+    let mut mock = Mock::from_capture(&messages, name, owners);
+    if mock.exchanges.is_empty() && mock.signals.is_empty() {
+        bail!(
+            "{} has no replies or signals from {}",
+            capture_path.display(),
+            name
+        );
+    }
+
+    let conn = bus::connection_builder(bus)?.name(name)?.build().await?;
+    println!(
+        "Mocking {} with {} recorded replies and {} signals, emit signals with:",
+        name,
+        mock.exchanges.len(),
+        mock.signals.len()
+    );
+    let bus_flag = match bus {
+        "session" => "--user".to_string(),
+        "system" => "--system".to_string(),
+        address => format!("--address={}", address),
+    };
+    println!(
+        "  busctl {} call {} {} {} EmitSignals s <member, or empty for all>",
+        bus_flag, name, CONTROL_PATH, CONTROL_INTERFACE
+    );
+
+    if timeline {
+        let conn = conn.clone();
+        let signals = mock.signals.clone();
+        let start = mock.start;
+        tokio::spawn(async move {
+            let started = tokio::time::Instant::now();
+            for signal in signals {
+                let offset = Duration::from_secs_f64((signal.timestamp - start).max(0.0));
+                tokio::time::sleep_until(started + offset).await;
+                if let Err(e) = emit(&conn, &signal).await {
+                    eprintln!("Failed to emit {}: {:#}", signal.member, e);
+                }
+            }
+        });
+    }
+
+    // The recorded paths, interfaces and signatures are only known at runtime, so calls are
+    // answered from the raw stream rather than by interfaces served with the object server
+    let mut stream = MessageStream::from(&conn);
+    while let Some(msg) = stream.next().await {
+        let msg = msg?;
+        if msg.message_type() != Type::MethodCall {
+            continue;
+        }
+        let header = msg.header();
+        let interface = header
+            .interface()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        let member = header.member().map(|m| m.to_string()).unwrap_or_default();
+        let sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+        let args = gvariant::format_args(&body_args(&msg));
+
+        let reply = if interface == CONTROL_INTERFACE {
+            let filter = match body_args(&msg).first() {
+                Some(Value::Str(member)) => member.to_string(),
+                _ => String::new(),
+            };
+            let mut count = 0u32;
+            for signal in mock
+                .signals
+                .iter()
+                .filter(|signal| filter.is_empty() || signal.member == filter)
+            {
+                emit(&conn, signal).await?;
+                count += 1;
+            }
+            println!("Emitted {} recorded signal(s) for {}", count, sender);
+            zbus::Message::method_return(&header)?.build(&(count,))?
+        } else {
+            match mock.reply_for(&interface, &member, &args) {
+                Some(recorded) => {
+                    let builder = match recorded.message_type() {
+                        Some(Type::Error) => {
+                            zbus::Message::error(&header, recorded.error_name.as_str())?
+                        }
+                        _ => zbus::Message::method_return(&header)?,
+                    };
+                    println!(
+                        "{}.{}({}) from {}: {} {}",
+                        interface,
+                        member,
+                        args,
+                        sender,
+                        recorded.msg_type,
+                        if recorded.error_name.is_empty() {
+                            &recorded.args
+                        } else {
+                            &recorded.error_name
+                        }
+                    );
+                    capture::build_message(builder, recorded.values()?)?
+                }
+                None => {
+                    println!(
+                        "{}.{}({}) from {}: no recorded reply",
+                        interface, member, args, sender
+                    );
+                    zbus::Message::error(&header, "org.freedesktop.DBus.Error.UnknownMethod")?
+                        .build(&(format!("No recorded reply for {}.{}", interface, member),))?
+                }
+            }
+        };
+        if !header
+            .primary()
+            .flags()
+            .contains(zbus::message::Flags::NoReplyExpected)
+        {
+            conn.send(&reply).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The unique names the capture records for `name`, from `NameOwnerChanged` signals and the
+/// replies to `GetNameOwner`.
+fn recorded_owners(
+    messages: &[CapturedMessage],
+    calls: &HashMap<(&str, u32), &CapturedMessage>,
+    name: &str,
+) -> HashSet<String> {
+    let string = |value: &Value| match value {
+        Value::Str(s) => Some(s.to_string()),
+        _ => None,
+    };
+    let mut owners = HashSet::new();
+    for message in messages {
+        if message.interface == "org.freedesktop.DBus"
+            && message.member == "NameOwnerChanged"
+            && message.msg_type == "signal"
+        {
+            let values = message.values().unwrap_or_default();
+            if values.first().and_then(string).as_deref() == Some(name) {
+                owners.extend(values.iter().skip(1).filter_map(string));
+            }
+        } else if message.msg_type == "method_return" {
+            let Some(call) = message
+                .reply_serial
+                .and_then(|serial| calls.get(&(message.destination.as_str(), serial)))
+            else {
+                continue;
+            };
+            let asked = call.values().unwrap_or_default();
+            if call.member == "GetNameOwner"
+                && call.destination == "org.freedesktop.DBus"
+                && asked.first().and_then(string).as_deref() == Some(name)
+            {
+                owners.extend(
+                    message
+                        .values()
+                        .unwrap_or_default()
+                        .first()
+                        .and_then(string),
+                );
+            }
+        }
+    }
+    owners.retain(|owner| !owner.is_empty());
+    owners
+}
+
+/// Sends a recorded signal again.
+async fn emit(conn: &Connection, signal: &CapturedMessage) -> Result<()> {
+    conn.send(&signal.to_message()?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(json: &str) -> CapturedMessage {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn capture() -> Vec<CapturedMessage> {
+        [
+            r#"{"timestamp":1.0,"bus":"session","type":"method_call","serial":3,"sender":":1.2","destination":"org.example","path":"/","interface":"org.example.Counter","member":"Add","signature":"u","args":"1"}"#,
+            r#"{"timestamp":1.1,"bus":"session","type":"method_return","serial":10,"reply_serial":3,"sender":":1.7","destination":":1.2","signature":"u","args":"11"}"#,
+            r#"{"timestamp":1.2,"bus":"session","type":"method_call","serial":3,"sender":":1.4","destination":"org.other","path":"/","interface":"org.example.Counter","member":"Add","signature":"u","args":"1"}"#,
+            r#"{"timestamp":1.3,"bus":"session","type":"method_return","serial":5,"reply_serial":3,"sender":":1.8","destination":":1.4","signature":"u","args":"99"}"#,
+            r#"{"timestamp":1.4,"bus":"session","type":"method_call","serial":4,"sender":":1.2","destination":"org.example","path":"/","interface":"org.example.Counter","member":"Add","signature":"u","args":"2"}"#,
+            r#"{"timestamp":1.5,"bus":"session","type":"error","serial":11,"reply_serial":4,"sender":":1.7","destination":":1.2","error_name":"org.example.Error.Full","signature":"s","args":"'full'"}"#,
+            r#"{"timestamp":1.6,"bus":"session","type":"method_call","serial":5,"sender":":1.2","destination":"org.example","path":"/","interface":"org.example.Counter","member":"Add","signature":"u","args":"1"}"#,
+            r#"{"timestamp":1.7,"bus":"session","type":"method_return","serial":12,"reply_serial":5,"sender":":1.7","destination":":1.2","signature":"u","args":"12"}"#,
+            r#"{"timestamp":1.8,"bus":"session","type":"signal","serial":13,"sender":":1.7","path":"/","interface":"org.example.Counter","member":"Changed","signature":"u","args":"12"}"#,
+            r#"{"timestamp":1.9,"bus":"session","type":"signal","serial":6,"sender":":1.8","path":"/","interface":"org.example.Counter","member":"Changed","signature":"u","args":"99"}"#,
+        ]
+        .iter()
+        .map(|json| message(json))
+        .collect()
+    }
+
+    #[test]
+    fn replies_are_matched_to_calls_to_the_name() {
+        let mock = Mock::from_capture(&capture(), "org.example", &[]);
+        let exchanges: Vec<(&str, &str)> = mock
+            .exchanges
+            .iter()
+            .map(|e| (e.args.as_str(), e.reply.args.as_str()))
+            .collect();
+        assert_eq!(exchanges, vec![("1", "11"), ("2", "'full'"), ("1", "12")]);
+        // Signals from the owner learned from the replies, not from other services
+        assert_eq!(mock.signals.len(), 1);
+        assert_eq!(mock.signals[0].args, "12");
+        assert_eq!(mock.start, 1.0);
+    }
+
+    #[test]
+    fn calls_walk_through_the_recorded_replies() {
+        let mut mock = Mock::from_capture(&capture(), "org.example", &[]);
+        let mut reply = |interface: &str, args: &str| {
+            mock.reply_for(interface, "Add", args)
+                .map(|reply| reply.args)
+        };
+        assert_eq!(reply("org.example.Counter", "1").as_deref(), Some("11"));
+        assert_eq!(reply("org.example.Counter", "1").as_deref(), Some("12"));
+        assert_eq!(reply("org.example.Counter", "1").as_deref(), Some("12"));
+        assert_eq!(reply("", "2").as_deref(), Some("'full'"));
+        // Unknown arguments fall back to any recorded call of the method
+        assert_eq!(reply("org.example.Counter", "5").as_deref(), Some("11"));
+        assert_eq!(reply("org.example.Other", "1"), None);
+        assert!(mock.reply_for("", "Missing", "").is_none());
+    }
+
+    #[test]
+    fn signal_senders_from_the_recorded_owner() {
+        let signal = message(
+            r#"{"timestamp":3.0,"bus":"session","type":"signal","serial":2,"sender":":1.30","path":"/","interface":"org.example.Counter","member":"Changed","signature":"u","args":"1"}"#,
+        );
+        let owner_changed = message(
+            r#"{"timestamp":2.0,"bus":"session","type":"signal","serial":9,"sender":"org.freedesktop.DBus","path":"/org/freedesktop/DBus","interface":"org.freedesktop.DBus","member":"NameOwnerChanged","signature":"sss","args":"'org.example', '', ':1.30'"}"#,
+        );
+        assert!(
+            Mock::from_capture(std::slice::from_ref(&signal), "org.example", &[])
+                .signals
+                .is_empty()
+        );
+        let mock = Mock::from_capture(&[owner_changed, signal.clone()], "org.example", &[]);
+        assert_eq!(mock.signals.len(), 1);
+
+        let get_owner = message(
+            r#"{"timestamp":1.0,"bus":"session","type":"method_call","serial":4,"sender":":1.2","destination":"org.freedesktop.DBus","path":"/org/freedesktop/DBus","interface":"org.freedesktop.DBus","member":"GetNameOwner","signature":"s","args":"'org.example'"}"#,
+        );
+        let owner = message(
+            r#"{"timestamp":1.1,"bus":"session","type":"method_return","serial":7,"reply_serial":4,"sender":"org.freedesktop.DBus","destination":":1.2","signature":"s","args":"':1.30'"}"#,
+        );
+        let mock = Mock::from_capture(&[get_owner, owner, signal.clone()], "org.example", &[]);
+        assert_eq!(mock.signals.len(), 1);
+
+        let mock = Mock::from_capture(&[signal], "org.example", &[":1.30".to_string()]);
+        assert_eq!(mock.signals.len(), 1);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::bus::{self, BusType, Item, ProcessInfo};
+use anyhow::{bail, Result};
+use ratatui::widgets::ListState;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use zbus::fdo::{DBusProxy, StartServiceReply};
+use zbus::names::{BusName, WellKnownName};
+use zbus::Connection;
+
+/// A name on the bus, running or activatable.
+#[derive(Debug, Clone)]
+pub struct BusNameRow {
+    pub name: String,
+    /// Unique name of the connection owning the name, empty when it is not running.
+    pub owner: String,
+    /// Connections waiting to own the name after the owner, in order.
+    pub queued: Vec<String>,
+    /// Whether the bus can start a service for the name.
+    pub activatable: bool,
+    pub process: Option<ProcessInfo>,
+    /// Captured messages from or to the connection owning the name.
+    pub messages: usize,
+}
+
+/// The result of work done in the background, picked up by `receive`.
+type Slot<T> = Arc<Mutex<Option<std::result::Result<T, String>>>>;
+
+/// State of the bus names panel: every name from `ListNames` and `ListActivatableNames`,
+/// well-known names first.
+#[derive(Default)]
+pub struct NamesPanel {
+    pub bus: Option<BusType>,
+    pub names: Vec<BusNameRow>,
+    pub state: ListState,
+    /// Whether the names are still being listed after `open`.
+    pub loading: bool,
+    loaded: Slot<Vec<BusNameRow>>,
+    /// Whether the bus is starting the service of a name.
+    pub starting: bool,
+    /// What became of the start, for the status line.
+    started: Slot<String>,
+}
+
+impl NamesPanel {
+    /// Starts listing the names on `bus` with their owners, queues and processes. The names show
+    /// up on a later `receive`.
+    pub fn open(&mut self, conn: &Connection, bus: BusType) {
+        if self.bus != Some(bus) {
+            self.names.clear();
+            self.state.select(None);
+        }
+        self.bus = Some(bus);
+        self.loading = true;
+        // A listing still running for the previous bus lands in the old slot
+        self.loaded = Arc::default();
+
+        let conn = conn.clone();
+        let loaded = Arc::clone(&self.loaded);
+        tokio::spawn(async move {
+            let result = list(&conn).await.map_err(|e| e.to_string());
+            *loaded.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        });
+    }
+
+    /// Takes over the names once they are listed, with the captured messages of each counted,
+    /// and lists them again after a service was started. Returns what to show in the status
+    /// line.
+    pub fn receive(&mut self, conn: &Connection, messages: &[Item]) -> Option<String> {
+        let mut status = None;
+        let started = self
+            .started
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(result) = started {
+            self.starting = false;
+            status = Some(result.unwrap_or_else(|e| format!("Failed to start: {}", e)));
+            if let Some(bus) = self.bus {
+                self.open(conn, bus);
+            }
+        }
+
+        let loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match loaded {
+            Some(Ok(names)) => {
+                self.loading = false;
+                self.names = names;
+                self.count_messages(messages);
+                let selected = self.state.selected().unwrap_or(0);
+                self.state.select(match self.names.len() {
+                    0 => None,
+                    len => Some(selected.min(len - 1)),
+                });
+            }
+            Some(Err(e)) => {
+                self.loading = false;
+                status = Some(format!("Failed to list names: {}", e));
+            }
+            None => {}
+        }
+        status
+    }
+
+    /// Counts the captured messages from or to the connection behind each name.
+    pub fn count_messages(&mut self, messages: &[Item]) {
+        for i in 0..self.names.len() {
+            let peer = self.peer_names(i);
+            self.names[i].messages = messages
+                .iter()
+                .filter(|item| peer.contains(&item.sender) || peer.contains(&item.receiver))
+                .count();
+        }
+    }
+
+    pub fn selected(&self) -> Option<&BusNameRow> {
+        self.state.selected().and_then(|i| self.names.get(i))
+    }
+
+    /// Every name of the connection behind row `i`: its unique name and the well-known names
+    /// it owns. Names that are not running only stand for themselves.
+    pub fn peer_names(&self, i: usize) -> Vec<String> {
+        let Some(row) = self.names.get(i) else {
+            return Vec::new();
+        };
+        if row.owner.is_empty() {
+            return vec![row.name.clone()];
+        }
+        let mut peer = vec![row.owner.clone()];
+        peer.extend(
+            self.names
+                .iter()
+                .filter(|other| other.owner == row.owner && other.name != row.owner)
+                .map(|other| other.name.clone()),
+        );
+        peer
+    }
+
+    /// Asks the bus to start the service of the selected name, in the background since the bus
+    /// waits for the service to come up.
+    pub fn start_selected(&mut self, conn: &Connection) -> Result<()> {
+        let Some(row) = self.selected() else {
+            bail!("No name selected");
+        };
+        if !row.activatable {
+            bail!("{} is not activatable", row.name);
+        }
+        if self.starting {
+            bail!("Already starting a service");
+        }
+        let name = WellKnownName::try_from(row.name.clone())?;
+        self.starting = true;
+        self.started = Arc::default();
+
+        let conn = conn.clone();
+        let started = Arc::clone(&self.started);
+        tokio::spawn(async move {
+            let result = start(&conn, name).await.map_err(|e| e.to_string());
+            *started.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        });
+        Ok(())
+    }
+}
+
+/// Every running and activatable name on the bus of `conn`, with the owners, queues and
+/// processes of all names looked up at once.
+async fn list(conn: &Connection) -> Result<Vec<BusNameRow>> {
+    let proxy = DBusProxy::new(conn).await?;
+    let running: BTreeSet<String> = proxy
+        .list_names()
+        .await?
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect();
+    let activatable: BTreeSet<String> = proxy
+        .list_activatable_names()
+        .await?
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect();
+
+    let cache = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+    let rows = running.union(&activatable).map(|name| {
+        let (proxy, cache) = (&proxy, &cache);
+        let (running, activatable) = (running.contains(name), activatable.contains(name));
+        async move {
+            let (owner, queued) = if name.starts_with(':') {
+                (name.clone(), Vec::new())
+            } else if running {
+                owners(proxy, name).await
+            } else {
+                (String::new(), Vec::new())
+            };
+            let process = if owner.is_empty() {
+                None
+            } else {
+                bus::get_process_info(conn, &owner, cache).await
+            };
+            BusNameRow {
+                name: name.clone(),
+                owner,
+                queued,
+                activatable,
+                process,
+                messages: 0,
+            }
+        }
+    });
+    let mut names = futures::future::join_all(rows).await;
+    names.sort_by(|a, b| {
+        a.name
+            .starts_with(':')
+            .cmp(&b.name.starts_with(':'))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(names)
+}
+
+/// Starts the service of `name` and describes how it went.
+async fn start(conn: &Connection, name: WellKnownName<'static>) -> Result<String> {
+    let reply = DBusProxy::new(conn)
+        .await?
+        .start_service_by_name(name.clone(), 0)
+        .await?;
+    Ok(match StartServiceReply::try_from(reply) {
+        Ok(StartServiceReply::AlreadyRunning) => format!("{} is already running", name),
+        _ => format!("Started {}", name),
+    })
+}
+
+/// The owner of a well-known name and the connections queued behind it. A name that went
+/// away since it was listed has no owner.
+async fn owners(proxy: &DBusProxy<'_>, name: &str) -> (String, Vec<String>) {
+    let Ok(well_known) = WellKnownName::try_from(name) else {
+        return (String::new(), Vec::new());
+    };
+    let owner = match proxy
+        .get_name_owner(BusName::from(well_known.clone()))
+        .await
+    {
+        Ok(owner) => owner.to_string(),
+        Err(_) => return (String::new(), Vec::new()),
+    };
+    let queued = proxy
+        .list_queued_owners(well_known)
+        .await
+        .map(|owners| {
+            owners
+                .into_iter()
+                .map(|owner| owner.to_string())
+                .filter(|queued| *queued != owner)
+                .collect()
+        })
+        .unwrap_or_default();
+    (owner, queued)
+}
@@ -0,0 +1,361 @@
+use crate::bus::{BusType, Item};
+use crate::introspect::{CacheEntry, IntrospectionCache};
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use zbus::message::Type;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+use zbus::{fdo::DBusProxy, Connection};
+
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const GET_MANAGED_OBJECTS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Properties by name, per interface, per object path.
+pub type ObjectTree = BTreeMap<String, BTreeMap<String, BTreeMap<String, Value<'static>>>>;
+
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+/// Owner, manager path and objects of a service, found in the background by `open`.
+type Loaded = (String, String, ObjectTree);
+
+/// Object model of a service implementing `org.freedesktop.DBus.ObjectManager`.
+///
+/// The model is a snapshot from `GetManagedObjects` plus the captured signals that changed it,
+/// so the state at any captured point in time can be rebuilt: forward from the snapshot for
+/// later signals, and by undoing signals for earlier ones.
+#[derive(Default)]
+pub struct ObjectModel {
+    pub bus: Option<BusType>,
+    pub destination: String,
+    /// Unique name owning `destination`, which the signals are sent from.
+    pub owner: String,
+    pub manager_path: String,
+    snapshot: ObjectTree,
+    snapshot_time: Option<SystemTime>,
+    /// Captured `InterfacesAdded`, `InterfacesRemoved` and `PropertiesChanged` signals, oldest first.
+    pub events: Vec<Item>,
+    /// Point in time the tree is shown at, `None` for the live state.
+    pub at: Option<SystemTime>,
+    pub scroll: u16,
+    /// Whether the object manager is still being looked for after `open`.
+    pub loading: bool,
+    pub error: String,
+    loaded: Arc<Mutex<Option<std::result::Result<Loaded, String>>>>,
+    /// Number of captured messages already looked through for `events`.
+    scanned: usize,
+}
+
+impl ObjectModel {
+    /// Starts looking for the object manager of the peer of `item` and loading its objects in
+    /// the background. They show up on a later `update_events`.
+    pub fn open(
+        &mut self,
+        conn: &Connection,
+        cache: &IntrospectionCache,
+        item: &Item,
+    ) -> Result<()> {
+        let destination = if item.msg_type == Type::MethodCall {
+            &item.receiver
+        } else {
+            &item.sender
+        };
+        if destination.is_empty() || item.path.is_empty() {
+            bail!("The message has no peer and object path to look for an object manager");
+        }
+
+        self.bus = Some(item.stream_type);
+        self.destination = destination.clone();
+        self.owner.clear();
+        self.manager_path.clear();
+        self.snapshot.clear();
+        self.snapshot_time = None;
+        self.events.clear();
+        self.scanned = 0;
+        self.at = None;
+        self.scroll = 0;
+        self.error.clear();
+        self.loading = true;
+        // A search still running for the previous service lands in the old slot
+        self.loaded = Arc::default();
+
+        let known: Vec<(String, Option<CacheEntry>)> = ancestors(&item.path)
+            .into_iter()
+            .map(|path| {
+                let node = cache.cached(item.stream_type, destination, &path);
+                (path, node)
+            })
+            .collect();
+        let conn = conn.clone();
+        let (destination, path) = (destination.clone(), item.path.clone());
+        let loaded = Arc::clone(&self.loaded);
+        tokio::spawn(async move {
+            let result = load(&conn, known, &destination, &path)
+                .await
+                .map_err(|e| e.to_string());
+            *loaded.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        });
+        Ok(())
+    }
+
+    /// Takes over the objects once they are loaded, then collects the signals of the service
+    /// captured since the last update.
+    pub fn update_events(&mut self, items: &[Item]) {
+        let loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match loaded {
+            Some(Ok((owner, manager_path, snapshot))) => {
+                self.loading = false;
+                self.owner = owner;
+                self.manager_path = manager_path;
+                self.snapshot = snapshot;
+                self.snapshot_time = Some(SystemTime::now());
+            }
+            Some(Err(e)) => {
+                self.loading = false;
+                self.error = e;
+            }
+            None => {}
+        }
+        if self.loading || !self.error.is_empty() {
+            return;
+        }
+
+        if items.len() < self.scanned {
+            self.events.clear();
+            self.scanned = 0;
+        }
+        let new = items[self.scanned..]
+            .iter()
+            .filter(|item| item.msg_type == Type::Signal && item.sender == self.owner)
+            .filter(|item| {
+                (item.interface == OBJECT_MANAGER_INTERFACE && item.path == self.manager_path)
+                    || !item.property_changes.is_empty()
+            })
+            .cloned();
+        self.events.extend(new);
+        self.scanned = items.len();
+    }
+
+    /// The objects as they were at `at`, or as they are now.
+    pub fn state(&self) -> ObjectTree {
+        let mut tree = self.snapshot.clone();
+        let Some(snapshot_time) = self.snapshot_time else {
+            return tree;
+        };
+
+        match self.at {
+            Some(at) if at < snapshot_time => {
+                for event in self
+                    .events
+                    .iter()
+                    .rev()
+                    .filter(|e| e.timestamp > at && e.timestamp <= snapshot_time)
+                {
+                    undo(&mut tree, event);
+                }
+            }
+            at => {
+                for event in self.events.iter().filter(|e| {
+                    e.timestamp > snapshot_time && at.is_none_or(|at| e.timestamp <= at)
+                }) {
+                    apply(&mut tree, event);
+                }
+            }
+        }
+        tree
+    }
+}
+
+/// A path followed by each of its ancestors, ie `/a/b`, `/a` and `/`.
+fn ancestors(path: &str) -> Vec<String> {
+    let mut paths = vec![path.to_string()];
+    let mut path = path;
+    loop {
+        match path.rfind('/') {
+            Some(0) if path != "/" => path = "/",
+            Some(i) if i > 0 => path = &path[..i],
+            _ => break,
+        }
+        paths.push(path.to_string());
+    }
+    paths
+}
+
+/// Finds the object manager on `path` or above and loads its objects. `known` holds the
+/// cached introspection data of each of those paths, if there is any.
+async fn load(
+    conn: &Connection,
+    known: Vec<(String, Option<CacheEntry>)>,
+    destination: &str,
+    path: &str,
+) -> Result<Loaded> {
+    let mut manager_path = None;
+    for (candidate, node) in known {
+        let node = match node {
+            Some(node) => node,
+            None => crate::introspect::lookup(conn, destination, &candidate).await,
+        };
+        if node.is_some_and(|node| {
+            node.interfaces()
+                .iter()
+                .any(|iface| iface.name().as_str() == OBJECT_MANAGER_INTERFACE)
+        }) {
+            manager_path = Some(candidate);
+            break;
+        }
+    }
+    let Some(manager_path) = manager_path else {
+        bail!("{} has no object manager on {} or above", destination, path);
+    };
+
+    let owner = if destination.starts_with(':') {
+        destination.to_string()
+    } else {
+        let proxy = DBusProxy::new(conn).await?;
+        proxy
+            .get_name_owner(destination.try_into()?)
+            .await?
+            .to_string()
+    };
+
+    let reply = tokio::time::timeout(
+        GET_MANAGED_OBJECTS_TIMEOUT,
+        conn.call_method(
+            Some(destination),
+            manager_path.as_str(),
+            Some(OBJECT_MANAGER_INTERFACE),
+            "GetManagedObjects",
+            &(),
+        ),
+    )
+    .await??;
+    let objects: ManagedObjects = reply.body().deserialize()?;
+    let snapshot = objects
+        .into_iter()
+        .map(|(path, interfaces)| (path.to_string(), to_tree_interfaces(interfaces)))
+        .collect();
+    Ok((owner, manager_path, snapshot))
+}
+
+fn to_tree_interfaces(
+    interfaces: HashMap<String, HashMap<String, OwnedValue>>,
+) -> BTreeMap<String, BTreeMap<String, Value<'static>>> {
+    interfaces
+        .into_iter()
+        .map(|(interface, properties)| {
+            let properties = properties
+                .into_iter()
+                .map(|(name, value)| (name, Value::from(value)))
+                .collect();
+            (interface, properties)
+        })
+        .collect()
+}
+
+fn apply(tree: &mut ObjectTree, event: &Item) {
+    match event.member.as_str() {
+        "InterfacesAdded" => {
+            if let Some((path, interfaces)) = event.message.as_ref().and_then(|m| {
+                m.body()
+                    .deserialize::<(
+                        OwnedObjectPath,
+                        HashMap<String, HashMap<String, OwnedValue>>,
+                    )>()
+                    .ok()
+            }) {
+                tree.entry(path.to_string())
+                    .or_default()
+                    .extend(to_tree_interfaces(interfaces));
+            }
+        }
+        "InterfacesRemoved" => {
+            if let Some((path, interfaces)) = event.message.as_ref().and_then(|m| {
+                m.body()
+                    .deserialize::<(OwnedObjectPath, Vec<String>)>()
+                    .ok()
+            }) {
+                remove_interfaces(tree, path.as_str(), &interfaces);
+            }
+        }
+        _ => {
+            // Objects that are not managed are not part of the model
+            let Some(object) = tree.get_mut(&event.path) else {
+                return;
+            };
+            for change in &event.property_changes {
+                let Some(properties) = object.get_mut(&change.interface) else {
+                    continue;
+                };
+                match &change.new {
+                    Some(value) => {
+                        properties.insert(change.name.clone(), value.clone());
+                    }
+                    None => {
+                        properties.remove(&change.name);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reverts a signal. Values that were not known before the signal are left out, and
+/// interfaces brought back by undoing `InterfacesRemoved` come back without properties.
+fn undo(tree: &mut ObjectTree, event: &Item) {
+    match event.member.as_str() {
+        "InterfacesAdded" => {
+            if let Some((path, interfaces)) = event.message.as_ref().and_then(|m| {
+                m.body()
+                    .deserialize::<(
+                        OwnedObjectPath,
+                        HashMap<String, HashMap<String, OwnedValue>>,
+                    )>()
+                    .ok()
+            }) {
+                let names: Vec<String> = interfaces.into_keys().collect();
+                remove_interfaces(tree, path.as_str(), &names);
+            }
+        }
+        "InterfacesRemoved" => {
+            if let Some((path, interfaces)) = event.message.as_ref().and_then(|m| {
+                m.body()
+                    .deserialize::<(OwnedObjectPath, Vec<String>)>()
+                    .ok()
+            }) {
+                let object = tree.entry(path.to_string()).or_default();
+                for interface in interfaces {
+                    object.entry(interface).or_default();
+                }
+            }
+        }
+        _ => {
+            let Some(object) = tree.get_mut(&event.path) else {
+                return;
+            };
+            for change in &event.property_changes {
+                let Some(properties) = object.get_mut(&change.interface) else {
+                    continue;
+                };
+                match &change.old {
+                    Some(value) => {
+                        properties.insert(change.name.clone(), value.clone());
+                    }
+                    None => {
+                        properties.remove(&change.name);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn remove_interfaces(tree: &mut ObjectTree, path: &str, interfaces: &[String]) {
+    if let Some(object) = tree.get_mut(path) {
+        for interface in interfaces {
+            object.remove(interface);
+        }
+        if object.is_empty() {
+            tree.remove(path);
+        }
+    }
+}
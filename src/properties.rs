@@ -0,0 +1,431 @@
+use crate::bus::{BusType, Item};
+use crate::gvariant;
+use crate::introspect::IntrospectionCache;
+use anyhow::{anyhow, bail, Result};
+use ratatui::widgets::ListState;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tui_input::Input;
+use zbus::message::Type;
+use zbus::zvariant::{OwnedValue, Signature, Value};
+use zbus::{fdo::DBusProxy, Connection};
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const PROPERTIES_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A property of the viewed object.
+pub struct PropertyEntry {
+    pub interface: String,
+    pub name: String,
+    /// Declared type, or the type of the last value when the object could not be introspected.
+    pub signature: Option<Signature>,
+    /// `None` when the access is not known because the object could not be introspected.
+    pub writable: Option<bool>,
+    /// The current value, `None` if it could not be read or was invalidated.
+    pub value: Option<Value<'static>>,
+}
+
+/// Interface, name and new value of a property that was read again after it was invalidated.
+type Refresh = (String, String, Option<Value<'static>>);
+/// The result of work done in the background, picked up by `apply_changes`.
+type Slot<T> = Arc<Mutex<Option<std::result::Result<T, String>>>>;
+
+/// State of the properties pane for one destination and object path.
+#[derive(Default)]
+pub struct PropertiesView {
+    pub bus: Option<BusType>,
+    pub destination: String,
+    /// Unique name owning `destination`, which is what `PropertiesChanged` signals are sent from.
+    pub owner: String,
+    pub path: String,
+    pub entries: Vec<PropertyEntry>,
+    pub state: ListState,
+    /// Input for a new value while `Set` is being composed.
+    pub editing: Option<Input>,
+    pub error: String,
+    last_change: Option<SystemTime>,
+    /// Values of invalidated properties read in the background.
+    refreshed: Arc<Mutex<Vec<Refresh>>>,
+    /// Whether the properties are still being read after `open`.
+    pub loading: bool,
+    /// The owner and properties of the object, once they are read.
+    loaded: Slot<(String, Vec<PropertyEntry>)>,
+    /// Whether a `Set` is on its way.
+    pub saving: bool,
+    /// The value read back after a `Set`.
+    saved: Slot<Refresh>,
+}
+
+impl PropertiesView {
+    /// Starts reading every property of the object the message was sent to or from. The
+    /// properties show up on a later `apply_changes`.
+    pub fn open(
+        &mut self,
+        conn: &Connection,
+        cache: &IntrospectionCache,
+        item: &Item,
+    ) -> Result<()> {
+        let destination = if item.msg_type == Type::MethodCall {
+            &item.receiver
+        } else {
+            &item.sender
+        };
+        if destination.is_empty() || item.path.is_empty() {
+            bail!("The message has no peer and object path to read properties from");
+        }
+
+        self.bus = Some(item.stream_type);
+        self.destination = destination.clone();
+        self.path = item.path.clone();
+        self.owner.clear();
+        self.entries.clear();
+        self.state.select(None);
+        self.editing = None;
+        self.error.clear();
+        self.last_change = Some(SystemTime::now());
+        // Reads still running for the previous object land in the old slots
+        self.refreshed = Arc::default();
+        self.loaded = Arc::default();
+        self.saved = Arc::default();
+        self.loading = true;
+        self.saving = false;
+
+        let conn = conn.clone();
+        let known = cache.cached(item.stream_type, destination, &item.path);
+        let (destination, path, interface) = (
+            destination.clone(),
+            item.path.clone(),
+            item.interface.clone(),
+        );
+        let loaded = Arc::clone(&self.loaded);
+        tokio::spawn(async move {
+            let result = load(&conn, known, &destination, &path, &interface)
+                .await
+                .map_err(|e| e.to_string());
+            *loaded.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        });
+        Ok(())
+    }
+
+    pub fn selected(&self) -> Option<&PropertyEntry> {
+        self.state.selected().and_then(|i| self.entries.get(i))
+    }
+
+    /// Applies `PropertiesChanged` signals of the viewed object captured since the last update.
+    /// Invalidated properties are read again in the background, since the signal does not carry
+    /// their value, and show up on a later update.
+    pub fn apply_changes(&mut self, conn: &Connection, items: &[Item]) {
+        let loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match loaded {
+            Some(Ok((owner, entries))) => {
+                self.loading = false;
+                self.owner = owner;
+                self.entries = entries;
+                self.state.select((!self.entries.is_empty()).then_some(0));
+            }
+            Some(Err(e)) => {
+                self.loading = false;
+                self.error = e;
+            }
+            None => {}
+        }
+        // Signals wait until the owner they come from is known
+        if self.loading {
+            return;
+        }
+        let saved = self.saved.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match saved {
+            Some(Ok((interface, name, value))) => {
+                self.saving = false;
+                self.update(&interface, &name, value);
+                self.editing = None;
+            }
+            Some(Err(e)) => {
+                self.saving = false;
+                self.error = e;
+            }
+            None => {}
+        }
+
+        let refreshed =
+            std::mem::take(&mut *self.refreshed.lock().unwrap_or_else(|e| e.into_inner()));
+        for (interface, name, value) in refreshed {
+            self.update(&interface, &name, value);
+        }
+
+        // Only the messages captured since the last update, which are at the end
+        let since = self.last_change;
+        let new = items
+            .iter()
+            .rev()
+            .take_while(|item| since.is_none_or(|since| item.timestamp > since))
+            .count();
+        let signals: Vec<&Item> = items[items.len() - new..]
+            .iter()
+            .filter(|item| {
+                item.msg_type == Type::Signal
+                    && item.member == "PropertiesChanged"
+                    && item.interface == PROPERTIES_INTERFACE
+                    && item.path == self.path
+                    && item.sender == self.owner
+            })
+            .collect();
+        if let Some(last) = items.last().filter(|_| new > 0) {
+            self.last_change = Some(last.timestamp);
+        }
+
+        for signal in signals {
+            let Some(message) = &signal.message else {
+                continue;
+            };
+            let Ok((interface, changed, invalidated)) =
+                message
+                    .body()
+                    .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+            else {
+                continue;
+            };
+
+            for (name, value) in changed {
+                self.update(&interface, &name, Some(Value::from(value)));
+            }
+            if invalidated.is_empty() {
+                continue;
+            }
+            let conn = conn.clone();
+            let (destination, path) = (self.destination.clone(), self.path.clone());
+            let refreshed = Arc::clone(&self.refreshed);
+            tokio::spawn(async move {
+                for name in invalidated {
+                    let value = get(&conn, &destination, &path, &interface, &name)
+                        .await
+                        .ok();
+                    refreshed.lock().unwrap_or_else(|e| e.into_inner()).push((
+                        interface.clone(),
+                        name,
+                        value,
+                    ));
+                }
+            });
+        }
+    }
+
+    fn update(&mut self, interface: &str, name: &str, value: Option<Value<'static>>) {
+        match self
+            .entries
+            .iter_mut()
+            .find(|p| p.interface == interface && p.name == name)
+        {
+            Some(property) => property.value = value,
+            None => self.entries.push(PropertyEntry {
+                interface: interface.to_string(),
+                name: name.to_string(),
+                signature: value.as_ref().map(|v| v.value_signature().clone()),
+                writable: None,
+                value,
+            }),
+        }
+    }
+
+    /// Starts editing the selected property, prefilled with its current value.
+    pub fn start_edit(&mut self) -> Result<()> {
+        let Some(property) = self.selected() else {
+            return Ok(());
+        };
+        if property.writable == Some(false) {
+            bail!("{} is read-only", property.name);
+        }
+        let text = property
+            .value
+            .as_ref()
+            .map(gvariant::format)
+            .unwrap_or_default();
+        self.editing = Some(Input::from(text));
+        self.error.clear();
+        Ok(())
+    }
+
+    /// Parses the edited value against the property type and sets it in the background.
+    /// A value that does not parse is reported right away.
+    pub fn set(&mut self, conn: &Connection) -> Result<()> {
+        let (Some(input), Some(index)) = (&self.editing, self.state.selected()) else {
+            return Ok(());
+        };
+        let Some(property) = self.entries.get(index) else {
+            return Ok(());
+        };
+        let signature = property
+            .signature
+            .clone()
+            .ok_or_else(|| anyhow!("The type of {} is not known", property.name))?;
+        let value = gvariant::parse_args(input.value(), Some(&[signature]))?
+            .pop()
+            .ok_or_else(|| anyhow!("A value is required"))?;
+
+        let conn = conn.clone();
+        let (destination, path) = (self.destination.clone(), self.path.clone());
+        let (interface, name) = (property.interface.clone(), property.name.clone());
+        let saved = Arc::clone(&self.saved);
+        self.saving = true;
+        tokio::spawn(async move {
+            let result = set(&conn, &destination, &path, &interface, &name, value)
+                .await
+                .map(|value| (interface, name, value))
+                .map_err(|e| e.to_string());
+            *saved.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        });
+        Ok(())
+    }
+}
+
+/// Resolves the owner of `destination` and reads every property of the object, declared ones
+/// with their type and access. `known` is the cached introspection data, if there is any.
+async fn load(
+    conn: &Connection,
+    known: Option<Option<Arc<zbus_xml::Node<'static>>>>,
+    destination: &str,
+    path: &str,
+    interface: &str,
+) -> Result<(String, Vec<PropertyEntry>)> {
+    let owner = if destination.starts_with(':') {
+        destination.to_string()
+    } else {
+        let proxy = DBusProxy::new(conn).await?;
+        proxy
+            .get_name_owner(destination.try_into()?)
+            .await?
+            .to_string()
+    };
+
+    let node = match known {
+        Some(node) => node,
+        None => crate::introspect::lookup(conn, destination, path).await,
+    };
+    let declared: Vec<(String, Vec<PropertyEntry>)> = match &node {
+        Some(node) => node
+            .interfaces()
+            .iter()
+            .filter(|iface| !iface.properties().is_empty())
+            .map(|iface| {
+                let properties = iface
+                    .properties()
+                    .iter()
+                    .map(|property| PropertyEntry {
+                        interface: iface.name().to_string(),
+                        name: property.name().to_string(),
+                        signature: Some(property.ty().inner().clone()),
+                        writable: Some(property.access().write()),
+                        value: None,
+                    })
+                    .collect();
+                (iface.name().to_string(), properties)
+            })
+            .collect(),
+        // Without introspection the interface of the message is the best guess
+        None if !interface.is_empty() => vec![(interface.to_string(), Vec::new())],
+        None => bail!("Could not introspect {} {}", destination, path),
+    };
+
+    let mut entries = Vec::new();
+    for (interface, mut properties) in declared {
+        match get_all(conn, destination, path, &interface).await {
+            Ok(values) => {
+                for (name, value) in values {
+                    match properties.iter_mut().find(|p| p.name == name) {
+                        Some(property) => property.value = Some(value),
+                        None => properties.push(PropertyEntry {
+                            interface: interface.clone(),
+                            signature: Some(value.value_signature().clone()),
+                            writable: None,
+                            name,
+                            value: Some(value),
+                        }),
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::debug!("GetAll {} on {} failed: {}", interface, path, e);
+            }
+        }
+        properties.sort_by(|a, b| a.name.cmp(&b.name));
+        entries.extend(properties);
+    }
+    Ok((owner, entries))
+}
+
+/// Sets a property and reads it back, since not every service emits `PropertiesChanged`.
+async fn set(
+    conn: &Connection,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    name: &str,
+    value: Value<'static>,
+) -> Result<Option<Value<'static>>> {
+    let reply = crate::bus::call_method(
+        conn,
+        destination,
+        path,
+        Some(PROPERTIES_INTERFACE),
+        "Set",
+        vec![
+            Value::from(interface.to_string()),
+            Value::from(name.to_string()),
+            Value::Value(Box::new(value)),
+        ],
+        PROPERTIES_TIMEOUT,
+    )
+    .await?;
+    if let Some(error_name) = reply.header().error_name() {
+        let text: String = reply.body().deserialize().unwrap_or_default();
+        bail!("{}: {}", error_name, text);
+    }
+    Ok(get(conn, destination, path, interface, name).await.ok())
+}
+
+async fn get_all(
+    conn: &Connection,
+    destination: &str,
+    path: &str,
+    interface: &str,
+) -> Result<Vec<(String, Value<'static>)>> {
+    let reply = tokio::time::timeout(
+        PROPERTIES_TIMEOUT,
+        conn.call_method(
+            Some(destination),
+            path,
+            Some(PROPERTIES_INTERFACE),
+            "GetAll",
+            &(interface,),
+        ),
+    )
+    .await??;
+    let values: HashMap<String, OwnedValue> = reply.body().deserialize()?;
+    Ok(values
+        .into_iter()
+        .map(|(name, value)| (name, Value::from(value)))
+        .collect())
+}
+
+async fn get(
+    conn: &Connection,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    name: &str,
+) -> Result<Value<'static>> {
+    let reply = tokio::time::timeout(
+        PROPERTIES_TIMEOUT,
+        conn.call_method(
+            Some(destination),
+            path,
+            Some(PROPERTIES_INTERFACE),
+            "Get",
+            &(interface, name),
+        ),
+    )
+    .await??;
+    let value: OwnedValue = reply.body().deserialize()?;
+    Ok(Value::from(value))
+}
@@ -0,0 +1,563 @@
+use crate::bus::{self, BusType, Item, ProcessInfo, PropertyStore};
+use crate::faults::{self, FaultAction, FaultRules};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, Mutex};
+use zbus::message::{Flags, Type};
+use zbus::zvariant::serialized::{Context as SerializedContext, Data};
+use zbus::zvariant::Endian;
+use zbus::Connection;
+
+/// The largest message the specification allows.
+const MAX_MESSAGE_LEN: usize = 128 * 1024 * 1024;
+
+/// Where the proxy forwards its clients to.
+#[derive(Clone)]
+enum Upstream {
+    Path(PathBuf),
+    Abstract(Vec<u8>),
+}
+
+impl Upstream {
+    /// Parses a D-Bus address such as `unix:path=/run/user/1000/bus`, or a plain socket path.
+    /// The first supported entry of a `;` separated address is used.
+    fn parse(address: &str) -> Result<Self> {
+        if address.starts_with('/') {
+            return Ok(Upstream::Path(PathBuf::from(address)));
+        }
+        for entry in address.split(';') {
+            let Some(options) = entry.strip_prefix("unix:") else {
+                continue;
+            };
+            for option in options.split(',') {
+                match option.split_once('=') {
+                    Some(("path", value)) => {
+                        let path = String::from_utf8(unescape(value)?)?;
+                        return Ok(Upstream::Path(PathBuf::from(path)));
+                    }
+                    Some(("abstract", value)) => return Ok(Upstream::Abstract(unescape(value)?)),
+                    _ => {}
+                }
+            }
+        }
+        bail!(
+            "Unsupported upstream address {}, expected unix:path=... or unix:abstract=...",
+            address
+        )
+    }
+
+    async fn connect(&self) -> Result<UnixStream> {
+        match self {
+            Upstream::Path(path) => UnixStream::connect(path)
+                .await
+                .with_context(|| format!("Failed to connect to {}", path.display())),
+            #[cfg(target_os = "linux")]
+            Upstream::Abstract(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+                let stream = std::os::unix::net::UnixStream::connect_addr(&addr)
+                    .context("Failed to connect to the abstract socket")?;
+                stream.set_nonblocking(true)?;
+                Ok(UnixStream::from_std(stream)?)
+            }
+            #[cfg(not(target_os = "linux"))]
+            Upstream::Abstract(_) => bail!("Abstract sockets are only supported on Linux"),
+        }
+    }
+}
+
+/// Undoes the `%xx` escaping of D-Bus address values.
+fn unescape(value: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .with_context(|| format!("Invalid escape in address value {}", value))?;
+            bytes.push(hex);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    Ok(bytes)
+}
+
+/// State shared by all proxied clients.
+struct Shared {
+    messages: Arc<Mutex<Vec<Item>>>,
+    property_store: Mutex<PropertyStore>,
+    cache: Arc<Mutex<HashMap<String, ProcessInfo>>>,
+    /// Connection to the upstream bus to look up the processes behind other names,
+    /// `None` when upstream is a peer rather than a bus.
+    conn: Option<Connection>,
+    rules: FaultRules,
+}
+
+/// One proxied client connection.
+struct Client {
+    info: Option<ProcessInfo>,
+    /// Unique name the bus assigned, learned from the first message addressed to the client.
+    name: std::sync::Mutex<String>,
+    /// Calls whose reply is to be replaced by an error, by the direction the reply
+    /// travels in and the serial of the call.
+    replacements: std::sync::Mutex<HashMap<(Direction, u32), (zbus::Message, String)>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    ToUpstream,
+    ToClient,
+}
+
+/// A forwarded message waiting to be recorded, with the marker of the fault injected into it.
+type Record = (zbus::Message, Direction, Option<String>);
+
+/// Accepts clients on the `listen` socket and forwards their traffic to `upstream`, the way
+/// xdg-dbus-proxy does. Every message passing in either direction is recorded as a Session item,
+/// after the first matching fault rule has been applied to it.
+pub async fn proxy_listener(
+    listen: &Path,
+    upstream: &str,
+    conn: Option<Connection>,
+    rules: FaultRules,
+) -> Result<Arc<Mutex<Vec<Item>>>> {
+    let upstream = Upstream::parse(upstream)?;
+    remove_stale_socket(listen)?;
+    let listener = UnixListener::bind(listen)
+        .with_context(|| format!("Failed to listen on {}", listen.display()))?;
+
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let shared = Arc::new(Shared {
+        messages: Arc::clone(&messages),
+        property_store: Mutex::new(PropertyStore::new()),
+        cache: Arc::new(Mutex::new(HashMap::new())),
+        conn,
+        rules,
+    });
+
+    tokio::spawn(async move {
+        loop {
+            let client = match listener.accept().await {
+                Ok((client, _)) => client,
+                Err(e) => {
+                    tracing::warn!("Failed to accept a proxy client: {}", e);
+                    continue;
+                }
+            };
+            let upstream = upstream.clone();
+            let shared = Arc::clone(&shared);
+            tokio::spawn(async move {
+                if let Err(e) = handle_client(client, &upstream, &shared).await {
+                    tracing::warn!("Proxy client disconnected: {:#}", e);
+                }
+            });
+        }
+    });
+
+    Ok(messages)
+}
+
+/// Removes a socket left behind by an earlier run, but nothing that is not a socket.
+fn remove_stale_socket(path: &Path) -> Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove the old socket {}", path.display())),
+        Ok(_) => bail!("{} exists and is not a socket", path.display()),
+        Err(_) => Ok(()),
+    }
+}
+
+async fn handle_client(client: UnixStream, upstream: &Upstream, shared: &Shared) -> Result<()> {
+    let info = match client.peer_cred().ok().and_then(|cred| cred.pid()) {
+        Some(pid) => bus::process_info_for_pid(pid as u32).await,
+        None => None,
+    };
+    let client_state = Client {
+        info,
+        name: std::sync::Mutex::new(String::new()),
+        replacements: std::sync::Mutex::new(HashMap::new()),
+    };
+
+    let server = upstream.connect().await?;
+    let (client_read, mut client_write) = client.into_split();
+    let (server_read, mut server_write) = server.into_split();
+    let mut client_read = BufReader::new(client_read);
+    let mut server_read = BufReader::new(server_read);
+
+    relay_auth(
+        &mut client_read,
+        &mut client_write,
+        &mut server_read,
+        &mut server_write,
+    )
+    .await?;
+
+    // Delayed messages are written from their own tasks, so the writers are shared
+    let (client_write, server_write) = (
+        Arc::new(Mutex::new(client_write)),
+        Arc::new(Mutex::new(server_write)),
+    );
+    let (records, recorded) = mpsc::unbounded_channel();
+    let to_upstream = pump(
+        client_read,
+        server_write,
+        Direction::ToUpstream,
+        &client_state,
+        shared,
+        records.clone(),
+    );
+    let to_client = pump(
+        server_read,
+        client_write,
+        Direction::ToClient,
+        &client_state,
+        shared,
+        records,
+    );
+    // Either side hanging up ends the session, dropping both sockets. The recorder finishes
+    // the messages that were already forwarded
+    let pumps = async {
+        tokio::select! {
+            result = to_upstream => result,
+            result = to_client => result,
+        }
+    };
+    let (result, ()) = tokio::join!(pumps, record(recorded, &client_state, shared));
+    result
+}
+
+/// Records forwarded messages in the order they passed, looking up the processes behind them
+/// off the path of the messages.
+async fn record(mut recorded: mpsc::UnboundedReceiver<Record>, client: &Client, shared: &Shared) {
+    while let Some((msg, direction, fault)) = recorded.recv().await {
+        let mut item = to_item(msg, direction, client, shared).await;
+        item.fault = fault;
+        shared.messages.lock().await.push(item);
+    }
+}
+
+/// Passes the line based authentication handshake through until the client sends `BEGIN`.
+/// Every client command gets exactly one reply line, except `BEGIN` which ends the handshake.
+async fn relay_auth<CR, CW, SR, SW>(
+    client_read: &mut CR,
+    client_write: &mut CW,
+    server_read: &mut SR,
+    server_write: &mut SW,
+) -> Result<()>
+where
+    CR: AsyncBufReadExt + Unpin,
+    CW: AsyncWrite + Unpin,
+    SR: AsyncBufReadExt + Unpin,
+    SW: AsyncWrite + Unpin,
+{
+    // The handshake starts with a single nul byte from the client
+    let mut nul = [0u8; 1];
+    client_read.read_exact(&mut nul).await?;
+    server_write.write_all(&nul).await?;
+
+    loop {
+        let mut line = Vec::new();
+        if client_read.read_until(b'\n', &mut line).await? == 0 {
+            bail!("Client closed the connection during authentication");
+        }
+        match line.trim_ascii_end() {
+            b"BEGIN" => {
+                server_write.write_all(&line).await?;
+                return Ok(());
+            }
+            b"NEGOTIATE_UNIX_FD" => {
+                // File descriptors are not forwarded, so the client falls back to not using them
+                client_write
+                    .write_all(b"ERROR \"The proxy does not forward file descriptors\"\r\n")
+                    .await?;
+                continue;
+            }
+            _ => server_write.write_all(&line).await?,
+        }
+
+        let mut reply = Vec::new();
+        if server_read.read_until(b'\n', &mut reply).await? == 0 {
+            bail!("Upstream closed the connection during authentication");
+        }
+        client_write.write_all(&reply).await?;
+    }
+}
+
+/// Forwards whole messages from one side to the other, injecting faults, and hands each of them
+/// to the recorder.
+async fn pump<R, W>(
+    mut from: R,
+    to: Arc<Mutex<W>>,
+    direction: Direction,
+    client: &Client,
+    shared: &Shared,
+    records: mpsc::UnboundedSender<Record>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let reverse = match direction {
+        Direction::ToUpstream => Direction::ToClient,
+        Direction::ToClient => Direction::ToUpstream,
+    };
+    loop {
+        let mut frame = vec![0u8; 16];
+        match from.read_exact(&mut frame).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        let endian = match frame[0] {
+            b'l' => Endian::Little,
+            b'B' => Endian::Big,
+            marker => bail!("Invalid endianness marker {:#04x}", marker),
+        };
+        let u32_at = |offset: usize| {
+            let bytes: [u8; 4] = frame[offset..offset + 4].try_into().unwrap_or_default();
+            match endian {
+                Endian::Little => u32::from_le_bytes(bytes),
+                Endian::Big => u32::from_be_bytes(bytes),
+            }
+        };
+        let body_len = u32_at(4) as usize;
+        let fields_len = u32_at(12) as usize;
+        let total = (16 + fields_len).next_multiple_of(8) + body_len;
+        if total > MAX_MESSAGE_LEN {
+            bail!("Message of {} bytes exceeds the maximum size", total);
+        }
+        frame.resize(total, 0);
+        from.read_exact(&mut frame[16..]).await?;
+
+        let data = Data::new(frame.clone(), SerializedContext::new_dbus(endian, 0));
+        // SAFETY: the bytes are a complete message as read off the wire, just like the messages
+        // zbus receives itself. Malformed headers and bodies are reported as errors.
+        let msg = match unsafe { zbus::Message::from_bytes(data) } {
+            Ok(msg) => msg,
+            Err(e) => {
+                tracing::warn!("Failed to decode a proxied message: {}", e);
+                to.lock().await.write_all(&frame).await?;
+                continue;
+            }
+        };
+        // Faults are decided on what the message and the client tell, so the message is not
+        // held up by lookups
+        let item = header_item(&msg, direction, client, shared);
+
+        let replacement = match item.msg_type {
+            Type::MethodReturn | Type::Error => msg.header().reply_serial().and_then(|serial| {
+                client
+                    .replacements
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&(direction, serial.get()))
+            }),
+            _ => None,
+        };
+        // An error reply stays as it is, only a method return is swapped
+        if let (Some((call, name)), Type::MethodReturn) = (replacement, item.msg_type) {
+            let error = error_reply(&call, &msg, &name)?;
+            to.lock().await.write_all(&error.data()[..]).await?;
+            let _ = records.send((msg, direction, Some(FaultAction::Error(name).marker())));
+            let _ = records.send((error, direction, Some("injected".to_string())));
+            continue;
+        }
+
+        let fault = match faults::find(&shared.rules, &item) {
+            Some(FaultAction::Drop) => Some(FaultAction::Drop.marker()),
+            Some(FaultAction::Delay(delay)) => {
+                // Only this message waits, the ones behind it go ahead
+                let to = Arc::clone(&to);
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    if let Err(e) = to.lock().await.write_all(&frame).await {
+                        tracing::warn!("Failed to forward a delayed message: {}", e);
+                    }
+                });
+                Some(FaultAction::Delay(delay).marker())
+            }
+            Some(FaultAction::Duplicate) => {
+                to.lock().await.write_all(&frame).await?;
+                to.lock().await.write_all(&frame).await?;
+                Some(FaultAction::Duplicate.marker())
+            }
+            Some(FaultAction::Error(name))
+                if item.msg_type == Type::MethodCall && !item.no_reply_expected =>
+            {
+                // The call goes through, its reply is swapped when it comes back
+                let serial = msg.header().primary().serial_num().get();
+                client
+                    .replacements
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert((reverse, serial), (msg.clone(), name));
+                to.lock().await.write_all(&frame).await?;
+                None
+            }
+            _ => {
+                to.lock().await.write_all(&frame).await?;
+                None
+            }
+        };
+        let _ = records.send((msg, direction, fault));
+    }
+}
+
+/// An error reply to `call` that takes the place of `reply`, keeping its serial and addressing.
+fn error_reply(call: &zbus::Message, reply: &zbus::Message, name: &str) -> Result<zbus::Message> {
+    let call_header = call.header();
+    let reply_header = reply.header();
+    let mut builder =
+        zbus::Message::error(&call_header, name)?.serial(reply_header.primary().serial_num());
+    if let Some(sender) = reply_header.sender() {
+        builder = builder.sender(sender)?;
+    }
+    if let Some(destination) = reply_header.destination() {
+        builder = builder.destination(destination)?;
+    }
+    Ok(builder.build(&("Injected by d-buddy",))?)
+}
+
+/// The message as a list entry with the fields its header carries and the processes known
+/// without asking the bus, which fault rules are matched against.
+fn header_item(
+    msg: &zbus::Message,
+    direction: Direction,
+    client: &Client,
+    shared: &Shared,
+) -> Item {
+    let header = msg.header();
+    let mut sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+    let receiver = header
+        .destination()
+        .map(|d| d.to_string())
+        .unwrap_or_default();
+    {
+        let mut name = client.name.lock().unwrap_or_else(|e| e.into_inner());
+        match direction {
+            Direction::ToClient if name.is_empty() && receiver.starts_with(':') => {
+                *name = receiver.clone();
+            }
+            // Only the bus fills in the sender, so calls on their way up carry none yet
+            Direction::ToUpstream if sender.is_empty() => sender = name.clone(),
+            _ => {}
+        }
+    }
+    let mut item = Item {
+        sender,
+        receiver,
+        member: header.member().map(|m| m.to_string()).unwrap_or_default(),
+        path: header.path().map(|p| p.to_string()).unwrap_or_default(),
+        interface: header
+            .interface()
+            .map(|i| i.to_string())
+            .unwrap_or_default(),
+        msg_type: msg.message_type(),
+        no_reply_expected: header.primary().flags().contains(Flags::NoReplyExpected),
+        error_name: header
+            .error_name()
+            .map(|e| e.to_string())
+            .unwrap_or_default(),
+        serial: header.primary().serial_num().to_string(),
+        reply_serial: header
+            .reply_serial()
+            .map(|s| s.to_string())
+            .unwrap_or_default(),
+        is_reply: header.reply_serial().is_some(),
+        message: Some(msg.clone()),
+        ..Default::default()
+    };
+    // The client is known from its socket, the other side only if it was looked up before
+    let cached = |name: &str| {
+        shared
+            .cache
+            .try_lock()
+            .ok()
+            .and_then(|cache| cache.get(name).cloned())
+    };
+    match direction {
+        Direction::ToUpstream => {
+            item.set_sender_process(client.info.clone());
+            item.set_receiver_process(cached(&item.receiver));
+        }
+        Direction::ToClient => {
+            item.set_sender_process(cached(&item.sender));
+            item.set_receiver_process(client.info.clone());
+        }
+    }
+    item
+}
+
+async fn to_item(
+    msg: zbus::Message,
+    direction: Direction,
+    client: &Client,
+    shared: &Shared,
+) -> Item {
+    let header = msg.header();
+    let sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+    let destination = header
+        .destination()
+        .map(|d| d.to_string())
+        .unwrap_or_default();
+
+    let (sender_info, receiver_info) = match direction {
+        Direction::ToUpstream => (client.info.clone(), lookup(shared, &destination).await),
+        Direction::ToClient => {
+            let ours = destination.starts_with(':')
+                && *client.name.lock().unwrap_or_else(|e| e.into_inner()) == destination;
+            if let (true, Some(info)) = (ours, &client.info) {
+                shared
+                    .cache
+                    .lock()
+                    .await
+                    .entry(destination.clone())
+                    .or_insert_with(|| info.clone());
+            }
+            (lookup(shared, &sender).await, client.info.clone())
+        }
+    };
+
+    let mut item = {
+        let mut property_store = shared.property_store.lock().await;
+        bus::new_item(
+            msg.clone(),
+            BusType::Session,
+            sender_info,
+            receiver_info,
+            &mut property_store,
+        )
+    };
+    // Only the bus fills in the sender, so calls on their way up carry none yet
+    if item.sender.is_empty() {
+        if let Direction::ToUpstream = direction {
+            item.sender = client
+                .name
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone();
+        }
+    }
+    item
+}
+
+/// The process behind a unique name, if upstream is a bus that can tell.
+async fn lookup(shared: &Shared, name: &str) -> Option<ProcessInfo> {
+    if !name.starts_with(':') {
+        return None;
+    }
+    match &shared.conn {
+        Some(conn) => bus::get_process_info(conn, name, &shared.cache).await,
+        None => shared.cache.lock().await.get(name).cloned(),
+    }
+}
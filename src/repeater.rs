@@ -0,0 +1,136 @@
+use crate::body_tree::body_args;
+use crate::bus::Item;
+use crate::gvariant;
+use anyhow::{bail, Result};
+use ratatui::widgets::ListState;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tui_input::Input;
+use zbus::message::Type;
+use zbus::zvariant::Signature;
+use zbus::Connection;
+
+/// A single resend of the call and what came back.
+pub struct Attempt {
+    pub sent_at: SystemTime,
+    pub args: String,
+    /// The method return or error reply, or why no reply arrived.
+    pub reply: std::result::Result<zbus::Message, String>,
+}
+
+/// State of the edit-and-resend popup for a captured method call.
+#[derive(Default)]
+pub struct Repeater {
+    pub call: Option<Item>,
+    /// Types of the original body, so edited arguments keep them.
+    pub signatures: Vec<Signature>,
+    pub input: Input,
+    pub history: Vec<Attempt>,
+    pub history_state: ListState,
+    pub error: String,
+    /// Attempts whose reply has not arrived yet.
+    pub sending: usize,
+    /// Attempts finished in the background, moved into `history` by `receive`.
+    finished: Arc<Mutex<Vec<Attempt>>>,
+}
+
+impl Repeater {
+    /// Loads a captured method call into the editor. Reopening the same call keeps its history.
+    pub fn open(&mut self, item: &Item) -> Result<()> {
+        if item.msg_type != Type::MethodCall {
+            bail!("Only method calls can be resent");
+        }
+        let Some(message) = &item.message else {
+            bail!("The message body was not captured");
+        };
+
+        let body = message.body();
+        let signatures = match body.signature() {
+            Signature::Unit => Vec::new(),
+            Signature::Structure(fields) => fields.iter().cloned().collect(),
+            signature => vec![signature.clone()],
+        };
+        let args = body_args(message);
+
+        let same_call = self
+            .call
+            .as_ref()
+            .is_some_and(|call| call.stream_type == item.stream_type && call.serial == item.serial);
+        if !same_call {
+            self.history.clear();
+            self.history_state.select(None);
+            // Replies still on their way belong to the previous call
+            self.finished = Arc::default();
+            self.sending = 0;
+        }
+        self.call = Some(item.clone());
+        self.signatures = signatures;
+        self.input = Input::from(gvariant::format_args(&args));
+        self.error.clear();
+        Ok(())
+    }
+
+    pub fn selected_attempt(&self) -> Option<&Attempt> {
+        self.history_state
+            .selected()
+            .and_then(|i| self.history.get(i))
+    }
+
+    /// Sends the call again with the edited arguments in the background, the attempt is recorded
+    /// when its reply arrives. Arguments that do not parse are reported through `error` without
+    /// sending anything.
+    pub fn send(&mut self, conn: &Connection, timeout: Duration) {
+        let Some(call) = &self.call else {
+            return;
+        };
+        let text = self.input.value().trim().to_string();
+        let args = match gvariant::parse_args(&text, Some(&self.signatures)) {
+            Ok(args) => args,
+            Err(e) => {
+                self.error = e.to_string();
+                return;
+            }
+        };
+        self.error.clear();
+
+        let sent_at = SystemTime::now();
+        let conn = conn.clone();
+        let call = call.clone();
+        let finished = Arc::clone(&self.finished);
+        self.sending += 1;
+        tokio::spawn(async move {
+            let reply = crate::bus::call_method(
+                &conn,
+                &call.receiver,
+                &call.path,
+                (!call.interface.is_empty()).then_some(call.interface.as_str()),
+                &call.member,
+                args,
+                timeout,
+            )
+            .await
+            .map_err(|e| e.to_string());
+            finished
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(Attempt {
+                    sent_at,
+                    args: text,
+                    reply,
+                });
+        });
+    }
+
+    /// Records the attempts whose reply arrived, selecting the latest.
+    pub fn receive(&mut self) {
+        let finished =
+            std::mem::take(&mut *self.finished.lock().unwrap_or_else(|e| e.into_inner()));
+        if finished.is_empty() {
+            return;
+        }
+        self.sending -= finished.len();
+        self.history.extend(finished);
+        self.history.sort_by_key(|attempt| attempt.sent_at);
+        self.history_state.select(Some(self.history.len() - 1));
+    }
+}
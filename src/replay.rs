@@ -0,0 +1,120 @@
+use crate::bus;
+use crate::capture;
+use crate::expect::{Condition, PROCESS_FIELDS};
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::time::Duration;
+use zbus::message::Type;
+
+/// How the gaps between replayed messages are timed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// The gaps as they were captured.
+    Original,
+    /// The captured gaps multiplied by a factor, ie 0.5 plays twice as fast.
+    Scaled(f64),
+    /// No gaps at all.
+    Fast,
+}
+
+impl std::str::FromStr for Timing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "original" => Ok(Timing::Original),
+            "fast" => Ok(Timing::Fast),
+            factor => match factor.parse::<f64>() {
+                Ok(factor) if factor >= 0.0 && factor.is_finite() => Ok(Timing::Scaled(factor)),
+                _ => Err(anyhow::anyhow!(
+                    "Unknown timing '{}', expected original, fast or a scale factor such as 0.5",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Sends the signals of a capture, and with `calls` its method calls, onto a bus again.
+/// Only messages matching `filter`, an expression as taken by `expect`, are sent. `names` are claimed first, so clients that match
+/// on the original sender receive the signals.
+pub async fn run(
+    capture_path: &Path,
+    bus: &str,
+    filter: &str,
+    calls: bool,
+    timing: Timing,
+    names: &[String],
+) -> Result<()> {
+    let condition = match filter.trim() {
+        "" => None,
+        filter => {
+            Some(Condition::parse(filter).with_context(|| format!("Invalid filter '{}'", filter))?)
+        }
+    };
+    if condition
+        .as_ref()
+        .is_some_and(|condition| condition.uses_field(&PROCESS_FIELDS))
+    {
+        bail!(
+            "Captures do not record the sending process, the filter cannot use {}",
+            PROCESS_FIELDS.join(", ")
+        );
+    }
+    let messages: Vec<_> = capture::load(capture_path)?
+        .into_iter()
+        .filter(|message| match message.message_type() {
+            Some(Type::Signal) => true,
+            Some(Type::MethodCall) => calls,
+            _ => false,
+        })
+        .filter(|message| {
+            condition
+                .as_ref()
+                .is_none_or(|condition| condition.matches(&message.to_item()))
+        })
+        .collect();
+    if messages.is_empty() {
+        bail!("Nothing in {} to replay", capture_path.display());
+    }
+
+    let mut builder = bus::connection_builder(bus)?;
+    for name in names {
+        builder = builder.name(name.as_str())?;
+    }
+    let conn = builder.build().await?;
+
+    let first = messages[0].timestamp;
+    let started = tokio::time::Instant::now();
+    let mut previous = first;
+    let mut elapsed = Duration::ZERO;
+    for message in &messages {
+        let gap = Duration::from_secs_f64((message.timestamp - previous).max(0.0));
+        previous = message.timestamp;
+        elapsed += match timing {
+            Timing::Original => gap,
+            Timing::Scaled(factor) => gap.mul_f64(factor),
+            Timing::Fast => Duration::ZERO,
+        };
+        tokio::time::sleep_until(started + elapsed).await;
+
+        let outgoing = message.to_message()?;
+        conn.send(&outgoing).await?;
+        println!(
+            "+{:.3}s {} {}.{}({}) {}",
+            started.elapsed().as_secs_f64(),
+            message.msg_type,
+            message.interface,
+            message.member,
+            message.args,
+            message.path
+        );
+    }
+    println!(
+        "Replayed {} messages in {:.3}s (captured over {:.3}s)",
+        messages.len(),
+        started.elapsed().as_secs_f64(),
+        previous - first
+    );
+    Ok(())
+}
@@ -0,0 +1,236 @@
+/// The kind of sandbox a process runs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sandbox {
+    #[default]
+    None,
+    Flatpak,
+    Snap,
+    Container,
+}
+
+impl std::fmt::Display for Sandbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sandbox::None => write!(f, ""),
+            Sandbox::Flatpak => write!(f, "flatpak"),
+            Sandbox::Snap => write!(f, "snap"),
+            Sandbox::Container => write!(f, "container"),
+        }
+    }
+}
+
+/// Finds out whether a process is sandboxed and the ID of the app inside, ie
+/// `org.gnome.Maps` for a Flatpak, `firefox` for a snap or the short ID of a container.
+/// `cgroup` is the content of `/proc/<pid>/cgroup` and `exe` the executable of the process.
+/// Reads files in the root of the process, so async code calls it with `spawn_blocking`.
+pub fn detect(pid: u32, cgroup: &str, exe: &str) -> Option<(Sandbox, String)> {
+    let components = cgroup_components(cgroup);
+    flatpak(pid, &components)
+        .map(|id| (Sandbox::Flatpak, id))
+        .or_else(|| snap(&components, exe).map(|id| (Sandbox::Snap, id)))
+        .or_else(|| container(pid, &components).map(|id| (Sandbox::Container, id)))
+}
+
+/// Every component of every hierarchy, ie `app.slice` and `app-flatpak-org.gnome.Maps-2.scope`.
+fn cgroup_components(cgroup: &str) -> Vec<&str> {
+    cgroup
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.split('/'))
+        .filter(|component| !component.is_empty())
+        .collect()
+}
+
+/// The app of a Flatpak from `.flatpak-info` in its root, which is also there for the
+/// `bwrap` and `xdg-dbus-proxy` processes of the app. Falls back to the `app-flatpak-*` scope
+/// when the root of the process can't be read.
+fn flatpak(pid: u32, components: &[&str]) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/root/.flatpak-info", pid))
+        .ok()
+        .and_then(|info| flatpak_info_name(&info))
+        .or_else(|| flatpak_scope(components))
+}
+
+/// The `name` of the `[Application]` or `[Runtime]` section of a `.flatpak-info`.
+fn flatpak_info_name(info: &str) -> Option<String> {
+    let mut section = "";
+    for line in info.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name;
+        } else if let Some(name) = line.strip_prefix("name=") {
+            if section == "Application" || section == "Runtime" {
+                return Some(name.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// The app of an `app-flatpak-<id>-<instance>.scope`.
+fn flatpak_scope(components: &[&str]) -> Option<String> {
+    components.iter().find_map(|component| {
+        let id = component
+            .strip_prefix("app-flatpak-")?
+            .strip_suffix(".scope")?;
+        // The scope ends with the instance number, ie `-1234`
+        Some(id.rsplit_once('-').map_or(id, |(id, _)| id).to_string())
+    })
+}
+
+/// The name of a snap from its `snap.<name>.<app>` scope, or the `/snap/<name>/` its
+/// executable lives in.
+fn snap(components: &[&str], exe: &str) -> Option<String> {
+    components
+        .iter()
+        .find_map(|component| component.strip_prefix("snap.")?.split('.').next())
+        .or_else(|| exe.strip_prefix("/snap/")?.split('/').next())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+/// The ID of a Docker, Podman, CRI or LXC container from its cgroup, or the name Podman
+/// writes to `/run/.containerenv`.
+fn container(pid: u32, components: &[&str]) -> Option<String> {
+    if let Some(id) = container_id(components) {
+        return Some(id);
+    }
+    let root = format!("/proc/{}/root", pid);
+    // Inside a container d-buddy shares its root with everything it sees
+    if same_root(&root) {
+        return None;
+    }
+    if let Ok(env) = std::fs::read_to_string(format!("{}/run/.containerenv", root)) {
+        return Some(containerenv_name(&env));
+    }
+    std::path::Path::new(&format!("{}/.dockerenv", root))
+        .exists()
+        .then(|| "docker".to_string())
+}
+
+/// The ID of a container in the cgroup components, shortened to 12 characters if it is a hash.
+fn container_id(components: &[&str]) -> Option<String> {
+    let id = components.iter().enumerate().find_map(|(i, component)| {
+        ["docker-", "libpod-", "crio-", "cri-containerd-"]
+            .iter()
+            .find_map(|prefix| component.strip_prefix(prefix)?.strip_suffix(".scope"))
+            .filter(|id| !id.starts_with("conmon-"))
+            .or_else(|| component.strip_prefix("lxc.payload."))
+            .or_else(|| match *component {
+                "docker" | "lxc" => components.get(i + 1).copied(),
+                _ => None,
+            })
+    })?;
+    let short = if id.len() > 12 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        &id[..12]
+    } else {
+        id
+    };
+    Some(short.to_string())
+}
+
+/// The container name in the `/run/.containerenv` Podman writes, or `podman` if it has none.
+fn containerenv_name(env: &str) -> String {
+    env.lines()
+        .find_map(|line| line.strip_prefix("name="))
+        .map(|name| name.trim_matches('"').to_string())
+        .unwrap_or_else(|| "podman".to_string())
+}
+
+/// Whether the root directory of a process is the root of d-buddy.
+fn same_root(root: &str) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(root), std::fs::metadata("/")) {
+        (Ok(theirs), Ok(ours)) => theirs.dev() == ours.dev() && theirs.ino() == ours.ino(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_of_all_hierarchies() {
+        assert_eq!(
+            cgroup_components("0::/user.slice/app.slice/a.scope\n1:name=systemd:/b.scope\n"),
+            vec!["user.slice", "app.slice", "a.scope", "b.scope"]
+        );
+        assert!(cgroup_components("0::/\n").is_empty());
+    }
+
+    #[test]
+    fn flatpak_info() {
+        let app = "[Application]\nname=org.gnome.Maps\nruntime=runtime/org.gnome.Platform/x86_64/46\n\n[Instance]\ninstance-id=1234\n";
+        assert_eq!(flatpak_info_name(app).as_deref(), Some("org.gnome.Maps"));
+        // Names of other sections are not the app
+        let runtime = "[Instance]\nname=other\n[Runtime]\n name=org.gnome.Sdk \n";
+        assert_eq!(flatpak_info_name(runtime).as_deref(), Some("org.gnome.Sdk"));
+        assert_eq!(flatpak_info_name("[Instance]\nname=other\n"), None);
+        assert_eq!(flatpak_info_name(""), None);
+    }
+
+    #[test]
+    fn flatpak_scopes() {
+        assert_eq!(
+            flatpak_scope(&["app.slice", "app-flatpak-org.gnome.Maps-1234.scope"]).as_deref(),
+            Some("org.gnome.Maps")
+        );
+        assert_eq!(flatpak_scope(&["app-gnome-firefox-1234.scope"]), None);
+    }
+
+    #[test]
+    fn snaps() {
+        let components = cgroup_components(
+            "0::/user.slice/user-1000.slice/user@1000.service/app.slice/snap.firefox.firefox-5d1a.scope\n",
+        );
+        assert_eq!(snap(&components, "").as_deref(), Some("firefox"));
+        assert_eq!(
+            snap(&[], "/snap/spotify/80/usr/share/spotify/spotify").as_deref(),
+            Some("spotify")
+        );
+        assert_eq!(snap(&[], "/usr/bin/firefox"), None);
+        assert_eq!(snap(&[], "/snap/"), None);
+    }
+
+    #[test]
+    fn containers_from_cgroups() {
+        let hash = "4f3a9c2e1b7d8a6f5e4d3c2b1a0f9e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b3a2f";
+        let id = |cgroup: &str| container_id(&cgroup_components(cgroup));
+        assert_eq!(
+            id(&format!("0::/system.slice/docker-{}.scope\n", hash)).as_deref(),
+            Some("4f3a9c2e1b7d")
+        );
+        assert_eq!(
+            id(&format!(
+                "0::/machine.slice/libpod-{}.scope/container\n",
+                hash
+            ))
+            .as_deref(),
+            Some("4f3a9c2e1b7d")
+        );
+        // The monitor process of Podman is not in the container
+        assert_eq!(
+            id(&format!("0::/machine.slice/libpod-conmon-{}.scope\n", hash)),
+            None
+        );
+        assert_eq!(
+            id(&format!("12:memory:/docker/{}\n", hash)).as_deref(),
+            Some("4f3a9c2e1b7d")
+        );
+        assert_eq!(
+            id("0::/lxc.payload.web/system.slice\n").as_deref(),
+            Some("web")
+        );
+        assert_eq!(id("0::/lxc/web\n").as_deref(), Some("web"));
+        assert_eq!(id("0::/user.slice/user-1000.slice/session-2.scope\n"), None);
+    }
+
+    #[test]
+    fn containerenv() {
+        assert_eq!(
+            containerenv_name("engine=\"podman-4.9.3\"\nname=\"toolbox\"\nid=\"abc\"\n"),
+            "toolbox"
+        );
+        assert_eq!(containerenv_name(""), "podman");
+    }
+}
@@ -1,4 +1,15 @@
+use crate::body_tree::BodyTree;
 use crate::bus::{BusType, Item};
+use crate::codegen::ProxyExport;
+use crate::commands::CommandFormat;
+use crate::composer::Composer;
+use crate::faults::FaultPanel;
+use crate::introspect::{Browser, IntrospectionCache};
+use crate::launch::Launch;
+use crate::names::NamesPanel;
+use crate::objects::ObjectModel;
+use crate::properties::PropertiesView;
+use crate::repeater::Repeater;
 use ratatui::{
     style::Stylize,
     text::{Line, Text},
@@ -17,12 +28,32 @@ pub enum Mode {
     AutoFilterSelection, // Mode for selecting autofilter field
     ThreadView,          // Mode for viewing a specific message thread
     GroupingSelection,   // Mode for selecting a grouping option
+    PendingCalls,        // Mode for viewing method calls still waiting for a reply
+    Errors,              // Mode for viewing error messages aggregated by error name
+    Browser,             // Mode for browsing names and objects through introspection
+    Composer,            // Mode for composing and sending a method call
+    Repeater,            // Mode for editing and resending a captured method call
+    Properties,          // Mode for viewing and setting properties of an object
+    Objects,             // Mode for viewing the object model of an ObjectManager
+    CommandFormat,       // Mode for choosing the tool to copy a method call as a command for
+    ProxyExport,         // Mode for writing a generated zbus proxy to a file
+    Faults,              // Mode for editing the fault rules of the proxy
+    LaunchSummary,       // Mode for viewing what a launched command did on the buses
+    Names,               // Mode for viewing the names on the bus, their owners and queues
+}
+
+// Tabs of the message details popup
+#[derive(PartialEq, Clone, Copy)]
+pub enum DetailTab {
+    Body, // Header summary and the body as a tree
+    Wire, // Annotated hexdump of the wire bytes
 }
 
 // Main application struct holding all the state
 pub struct App {
     pub stream: BusType,
     pub messages: HashMap<BusType, Arc<Mutex<Vec<Item>>>>,
+    pub connections: HashMap<BusType, zbus::Connection>, // Connections for calls made by d-buddy itself
     pub filtered_and_sorted_items: Vec<Item>,
     pub list_state: ListState, // State of the message list widget (e.g., selected item)
     pub show_details: bool,    // Flag to indicate if message details popup should be shown
@@ -30,7 +61,11 @@ pub struct App {
     pub input: Input,          // Input buffer for the filtering text
     pub detail_text: Text<'static>, // The formatted string for the currently viewed detail
     pub detail_scroll: u16,    // The vertical scroll offset for the detail view
-    pub status_message: String, // A temporary message to show in the status bar
+    pub body_tree: BodyTree,   // Navigable tree of the arguments of the viewed message
+    pub detail_tab: DetailTab, // Tab shown in the details popup
+    pub detail_labels_pending: bool, // Argument labels of the viewed message are still being looked up
+    pub wire_text: Text<'static>,    // Annotated hexdump of the viewed message
+    pub status_message: String,      // A temporary message to show in the status bar
     pub thread_serial: Option<String>,
     pub detail_scroll_request: Option<i32>,
     pub filter_criteria: HashMap<String, String>,
@@ -40,7 +75,26 @@ pub struct App {
     pub min_width: u16,
     pub min_height: u16,
     pub use_relative_time: bool,
+    pub hide_returns: bool, // Hide method returns from the message list
+    pub only_errors: bool,  // Show only error replies in the message list
     pub enable_lighting_strike: bool,
+    pub pending_calls: Vec<Item>,
+    pub pending_calls_state: ListState,
+    pub error_summaries: Vec<crate::bus::ErrorSummary>,
+    pub error_summaries_state: ListState,
+    pub browser: Browser,
+    pub names: NamesPanel,
+    pub introspection_cache: IntrospectionCache,
+    pub composer: Composer,
+    pub repeater: Repeater,
+    pub properties: PropertiesView,
+    pub objects: ObjectModel,
+    pub command_choices: Vec<(CommandFormat, std::result::Result<String, String>)>,
+    pub command_choice_state: ListState,
+    pub proxy_export: ProxyExport,
+    pub faults: FaultPanel,
+    pub launch: Option<Launch>, // Command started with `d-buddy -- <command>`, whose messages are shown
+    pub isolated_address: Option<String>, // Address of the private bus of `d-buddy run-isolated`
 
     // Cached static UI elements
     pub cached_filtering_key_hints: Option<Line<'static>>,
@@ -49,6 +103,19 @@ pub struct App {
     pub cached_autofilter_selection_key_hints: Option<Line<'static>>,
     pub cached_thread_view_key_hints: Option<Line<'static>>,
     pub cached_grouping_selection_key_hints: Option<Line<'static>>,
+    pub cached_pending_calls_key_hints: Option<Line<'static>>,
+    pub cached_errors_key_hints: Option<Line<'static>>,
+    pub cached_browser_key_hints: Option<Line<'static>>,
+    pub cached_names_key_hints: Option<Line<'static>>,
+    pub cached_composer_key_hints: Option<Line<'static>>,
+    pub cached_repeater_key_hints: Option<Line<'static>>,
+    pub cached_properties_key_hints: Option<Line<'static>>,
+    pub cached_property_edit_key_hints: Option<Line<'static>>,
+    pub cached_objects_key_hints: Option<Line<'static>>,
+    pub cached_command_format_key_hints: Option<Line<'static>>,
+    pub cached_proxy_export_key_hints: Option<Line<'static>>,
+    pub cached_faults_key_hints: Option<Line<'static>>,
+    pub cached_launch_summary_key_hints: Option<Line<'static>>,
     pub cached_console_too_small_message: Option<Line<'static>>,
 
     // Cached title elements
@@ -62,6 +129,7 @@ impl Default for App {
         App {
             stream: BusType::Session,
             messages: HashMap::new(), // Initialize with an empty list of messages
+            connections: HashMap::new(),
             filtered_and_sorted_items: Vec::new(),
             list_state: ListState::default(), // Default list state (no item selected)
             show_details: false,              // Details popup is hidden by default
@@ -69,7 +137,11 @@ impl Default for App {
             input: Input::default(),          // Empty input buffer
             detail_text: Text::default(),     // No detail text initially
             detail_scroll: 0,                 // Start with no scroll
-            status_message: String::new(),    // No status message initially
+            body_tree: BodyTree::default(),
+            detail_tab: DetailTab::Body,
+            detail_labels_pending: false,
+            wire_text: Text::default(),
+            status_message: String::new(), // No status message initially
             thread_serial: None,
             detail_scroll_request: None,
             filter_criteria: HashMap::new(),
@@ -79,7 +151,26 @@ impl Default for App {
             min_width: 20,
             min_height: 20,
             use_relative_time: false,
+            hide_returns: false,
+            only_errors: false,
             enable_lighting_strike: false,
+            pending_calls: Vec::new(),
+            pending_calls_state: ListState::default(),
+            error_summaries: Vec::new(),
+            error_summaries_state: ListState::default(),
+            browser: Browser::default(),
+            names: NamesPanel::default(),
+            introspection_cache: IntrospectionCache::default(),
+            composer: Composer::default(),
+            repeater: Repeater::default(),
+            properties: PropertiesView::default(),
+            objects: ObjectModel::default(),
+            command_choices: Vec::new(),
+            command_choice_state: ListState::default(),
+            proxy_export: ProxyExport::default(),
+            faults: FaultPanel::default(),
+            launch: None,
+            isolated_address: None,
 
             // Initialize cached elements as None
             cached_filtering_key_hints: None,
@@ -88,6 +179,19 @@ impl Default for App {
             cached_autofilter_selection_key_hints: None,
             cached_thread_view_key_hints: None,
             cached_grouping_selection_key_hints: None,
+            cached_pending_calls_key_hints: None,
+            cached_errors_key_hints: None,
+            cached_browser_key_hints: None,
+            cached_names_key_hints: None,
+            cached_composer_key_hints: None,
+            cached_repeater_key_hints: None,
+            cached_properties_key_hints: None,
+            cached_property_edit_key_hints: None,
+            cached_objects_key_hints: None,
+            cached_command_format_key_hints: None,
+            cached_proxy_export_key_hints: None,
+            cached_faults_key_hints: None,
+            cached_launch_summary_key_hints: None,
             cached_console_too_small_message: None,
             cached_title_prefix: None,
             cached_title_suffix: None,
@@ -96,6 +200,14 @@ impl Default for App {
 }
 
 impl App {
+    /// The bus that calls made by d-buddy go to. The combined view falls back to the session bus.
+    pub fn active_bus(&self) -> BusType {
+        match self.stream {
+            BusType::Both => BusType::Session,
+            bus => bus,
+        }
+    }
+
     pub fn initialize_static_ui_elements(&mut self, config: &crate::config::Config) {
         // "Console too small" message
         self.cached_console_too_small_message = Some(Line::from(
@@ -127,7 +239,21 @@ impl App {
             "PgUp".bold().fg(config.color_keybind_key),
             "/".dim(),
             "PgDn".bold().fg(config.color_keybind_key),
-            ": scroll".into(),
+            ": move | ".into(),
+            "←".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "→".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": collapse/expand | ".into(),
+            "y".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "Y".bold().fg(config.color_keybind_key),
+            ": copy value/json | ".into(),
+            "v".bold().fg(config.color_keybind_key),
+            ": filter on value | ".into(),
+            "w".bold().fg(config.color_keybind_key),
+            ": body/wire".into(),
         ]));
 
         // Normal mode (main view) key hints
@@ -140,10 +266,38 @@ impl App {
             ": time | ".into(),
             "f".bold().fg(config.color_keybind_key),
             ": filter | ".into(),
+            "H".bold().fg(config.color_keybind_key),
+            ": hide returns | ".into(),
+            "E".bold().fg(config.color_keybind_key),
+            ": only errors | ".into(),
             "g".bold().fg(config.color_keybind_key),
             ": group | ".into(),
+            "p".bold().fg(config.color_keybind_key),
+            ": pending | ".into(),
+            "e".bold().fg(config.color_keybind_key),
+            ": errors | ".into(),
+            "b".bold().fg(config.color_keybind_key),
+            ": browse | ".into(),
+            "n".bold().fg(config.color_keybind_key),
+            ": names | ".into(),
+            "i".bold().fg(config.color_keybind_key),
+            ": invoke | ".into(),
+            "R".bold().fg(config.color_keybind_key),
+            ": resend | ".into(),
+            "o".bold().fg(config.color_keybind_key),
+            ": properties | ".into(),
+            "m".bold().fg(config.color_keybind_key),
+            ": objects | ".into(),
             "r".bold().fg(config.color_keybind_key),
-            ": reply | ".into(),
+            ": copy as command | ".into(),
+            "G".bold().fg(config.color_keybind_key),
+            ": generate proxy | ".into(),
+            "F".bold().fg(config.color_keybind_key),
+            ": faults | ".into(),
+            "L".bold().fg(config.color_keybind_key),
+            ": launch summary | ".into(),
+            "S".bold().fg(config.color_keybind_key),
+            ": save | ".into(),
             "x".bold().fg(config.color_keybind_key),
             ": clear | ".into(),
             "s".bold().fg(config.color_keybind_key),
@@ -186,6 +340,184 @@ impl App {
             ": navigate".into(),
         ]));
 
+        // PendingCalls key hints
+        self.cached_pending_calls_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": show thread | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": navigate".into(),
+        ]));
+
+        // Errors key hints
+        self.cached_errors_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": jump to call | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": navigate".into(),
+        ]));
+
+        // Bus names key hints
+        self.cached_names_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": filter live list | ".into(),
+            "a".bold().fg(config.color_keybind_key),
+            ": start activatable | ".into(),
+            "r".bold().fg(config.color_keybind_key),
+            ": refresh | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": navigate".into(),
+        ]));
+
+        // Browser key hints
+        self.cached_browser_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "→".bold().fg(config.color_keybind_key),
+            ": expand | ".into(),
+            "←".bold().fg(config.color_keybind_key),
+            ": collapse | ".into(),
+            "f".bold().fg(config.color_keybind_key),
+            ": filter live list | ".into(),
+            "G".bold().fg(config.color_keybind_key),
+            ": generate proxy | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": navigate".into(),
+        ]));
+
+        // Composer key hints
+        self.cached_composer_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": cancel | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": send | ".into(),
+            "Tab".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": next field | ".into(),
+            "S-Tab".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↑".bold().fg(config.color_keybind_key),
+            ": previous field".into(),
+        ]));
+
+        // Repeater key hints
+        self.cached_repeater_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": send | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": browse attempts".into(),
+        ]));
+
+        // Properties key hints
+        self.cached_properties_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": set | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": navigate".into(),
+        ]));
+
+        // Property editing key hints
+        self.cached_property_edit_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": cancel | ".into(),
+            "Enter".bold().fg(config.color_keybind_key),
+            ": set value".into(),
+        ]));
+
+        // Objects key hints
+        self.cached_objects_key_hints = Some(Line::from(vec![
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close | ".into(),
+            "[".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "]".bold().fg(config.color_keybind_key),
+            ": previous/next message | ".into(),
+            "l".bold().fg(config.color_keybind_key),
+            ": live | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": scroll".into(),
+        ]));
+
+        // Command format key hints
+        self.cached_command_format_key_hints = Some(Line::from(vec![
+            "Enter".bold().fg(config.color_keybind_key),
+            ": copy | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": select | ".into(),
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close".into(),
+        ]));
+
+        // Proxy export key hints
+        self.cached_proxy_export_key_hints = Some(Line::from(vec![
+            "Enter".bold().fg(config.color_keybind_key),
+            ": write file | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "PgUp".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "PgDn".bold().fg(config.color_keybind_key),
+            ": scroll | ".into(),
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close".into(),
+        ]));
+
+        // Faults key hints
+        self.cached_faults_key_hints = Some(Line::from(vec![
+            "Enter".bold().fg(config.color_keybind_key),
+            ": add rule | ".into(),
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": select | ".into(),
+            "Tab".bold().fg(config.color_keybind_key),
+            ": enable/disable | ".into(),
+            "Del".bold().fg(config.color_keybind_key),
+            ": remove | ".into(),
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close".into(),
+        ]));
+
+        // Launch summary key hints
+        self.cached_launch_summary_key_hints = Some(Line::from(vec![
+            "↑".bold().fg(config.color_keybind_key),
+            "/".dim(),
+            "↓".bold().fg(config.color_keybind_key),
+            ": scroll | ".into(),
+            "Esc".bold().fg(config.color_keybind_key),
+            ": close".into(),
+        ]));
+
         // Title elements
         self.cached_title_prefix = Some(Line::from("D-Bus Signals ["));
         self.cached_title_suffix = Some(Line::from("]"));
@@ -1,4 +1,6 @@
 use super::{App, Config, Mode};
+use crate::body_tree::{hexdump, HEXDUMP_WIDTH};
+use crate::state::DetailTab;
 use ratatui::{
     prelude::*,
     text::{Line, Text},
@@ -6,7 +8,7 @@ use ratatui::{
 };
 
 use std::borrow::Cow;
-use zbus::zvariant::Value;
+use zbus::zvariant::{Structure, Value};
 
 /// Represents an item in the main list, which can either be a message or a group header.
 enum ViewItem<'a> {
@@ -45,6 +47,15 @@ fn build_view_items<'a>(app: &App, filtered_items: &'a [crate::bus::Item]) -> Ve
                 crate::bus::GroupingType::Member => item.member.as_str().into(),
                 crate::bus::GroupingType::Path => item.path.as_str().into(),
                 crate::bus::GroupingType::Serial => item.serial.as_str().into(),
+                crate::bus::GroupingType::Executable => item.exe.as_str().into(),
+                crate::bus::GroupingType::Unit => item.unit.as_str().into(),
+                crate::bus::GroupingType::Slice => item.slice.as_str().into(),
+                crate::bus::GroupingType::Parent => match item.parent() {
+                    Some((name, pid)) => format!("{}:{}", name, pid).into(),
+                    None => "".into(),
+                },
+                crate::bus::GroupingType::Sandbox => item.sandbox.to_string().into(),
+                crate::bus::GroupingType::AppId => item.app_id.as_str().into(),
                 crate::bus::GroupingType::None => unreachable!(),
             };
             current_group_keys_vec.push(group_component);
@@ -137,11 +148,15 @@ fn create_message_list_item<'a>(
     ));
     spans.push(Span::raw("]"));
     spans.push(Span::raw(" "));
-    spans.push(if item.is_reply {
-        Span::raw(" ↩ ")
-    } else {
-        Span::raw("   ")
-    });
+    let (type_glyph, type_color) = match item.msg_type {
+        zbus::message::Type::Signal => ("◆", config.color_type_signal),
+        zbus::message::Type::MethodCall => ("→", config.color_type_call),
+        zbus::message::Type::MethodReturn => ("↩", config.color_type_return),
+        zbus::message::Type::Error => ("✗", config.color_error_name),
+    };
+    spans.push(Span::raw(" "));
+    spans.push(Span::styled(type_glyph, Style::default().fg(type_color)));
+    spans.push(Span::raw(" "));
     spans.push(Span::styled(
         sender_info.into_owned(),
         if app.show_details {
@@ -179,6 +194,20 @@ fn create_message_list_item<'a>(
             Style::default().fg(config.color_path_normal)
         },
     ));
+    if let Some(fault) = &item.fault {
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(
+            format!("[{}]", fault),
+            Style::default().fg(config.color_fault).bold(),
+        ));
+    }
+    if !item.property_changes.is_empty() {
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(
+            item.property_changes_summary(&config.decoders),
+            Style::default().fg(config.color_property_new),
+        ));
+    }
 
     ListItem::new(Line::from(spans))
 }
@@ -239,7 +268,7 @@ pub fn ui(
     config: &Config,
     session_count: usize,
     system_count: usize,
-    both_count: usize,
+    isolated_count: Option<usize>,
     filtered_items: &[crate::bus::Item],
 ) {
     //if console is too small
@@ -286,6 +315,11 @@ pub fn ui(
             Style::default().fg(config.color_keybind_text).italic(),
             Style::default().fg(config.color_stream_session).bold(), // Reusing session color for "Both" active
         ),
+        crate::bus::BusType::Isolated => (
+            Style::default().fg(config.color_keybind_text).italic(),
+            Style::default().fg(config.color_keybind_text).italic(),
+            Style::default().fg(config.color_keybind_text).italic(),
+        ),
     };
 
     let mut title_spans = app.cached_title_prefix.as_ref().unwrap().clone();
@@ -294,8 +328,22 @@ pub fn ui(
         Span::raw("|"),
         Span::styled(format!("System({})", system_count), system_style),
         Span::raw("|"),
-        Span::styled(format!("Both({})", both_count), both_style),
+        Span::styled(
+            format!("Both({})", session_count + system_count),
+            both_style,
+        ),
     ]));
+    if let Some(isolated_count) = isolated_count {
+        let isolated_style = if app.stream == crate::bus::BusType::Isolated {
+            Style::default().fg(config.color_stream_isolated).bold()
+        } else {
+            Style::default().fg(config.color_keybind_text).italic()
+        };
+        title_spans.extend(Line::from(vec![
+            Span::raw("|"),
+            Span::styled(format!("Isolated({})", isolated_count), isolated_style),
+        ]));
+    }
 
     // Add filter status if active
     let general_filter = app.input.value();
@@ -306,6 +354,14 @@ pub fn ui(
     for (k, v) in &app.filter_criteria {
         filter_parts.push(format!("{}={}", k, v));
     }
+    if let Some(launch) = app.launch.as_ref().filter(|launch| launch.filter) {
+        filter_parts.push(format!("launched: {}", launch.command[0]));
+    }
+    if app.only_errors {
+        filter_parts.push("errors only".to_string());
+    } else if app.hide_returns {
+        filter_parts.push("no returns".to_string());
+    }
 
     if !filter_parts.is_empty() {
         let filter_line = Line::from(vec![
@@ -387,7 +443,7 @@ fn render_overlays(
     app: &mut App,
     config: &Config,
     display_items: &[ViewItem],
-    _filtered_items: &[crate::bus::Item],
+    filtered_items: &[crate::bus::Item],
 ) {
     // Render Filtering popup
     if let Mode::Filtering = app.mode {
@@ -451,16 +507,8 @@ fn render_overlays(
         let mut list_items = Vec::new();
         if let Some(selected_index) = app.list_state.selected() {
             if let Some(ViewItem::Message(item, _)) = display_items.get(selected_index) {
-                let autofilter_options = ["sender", "member", "path", "serial", "reply_serial"];
-                for &option in autofilter_options.iter() {
-                    let example_value: Cow<'_, str> = match option {
-                        "sender" => item.sender_display(),
-                        "member" => item.member.as_str().into(),
-                        "path" => item.path.as_str().into(),
-                        "serial" => item.serial.as_str().into(),
-                        "reply_serial" => item.reply_serial.as_str().into(),
-                        _ => "".into(),
-                    };
+                for &option in crate::bus::FILTER_FIELDS.iter() {
+                    let example_value = item.field_value(option).unwrap_or_default();
                     list_items.push(ListItem::new(Line::from(vec![
                         Span::raw(format!("{}: ", option)),
                         Span::styled(
@@ -488,13 +536,7 @@ fn render_overlays(
     // Render GroupingSelection popup
     if let Mode::GroupingSelection = app.mode {
         let _span = tracing::info_span!("render_grouping_selection_popup").entered();
-        let all_grouping_options = [
-            crate::bus::GroupingType::Sender,
-            crate::bus::GroupingType::Member,
-            crate::bus::GroupingType::Path,
-            crate::bus::GroupingType::Serial,
-            crate::bus::GroupingType::None,
-        ];
+        let all_grouping_options = crate::bus::GroupingType::ALL;
         let list_display_options: Vec<String> = all_grouping_options
             .iter()
             .map(|gt| gt.to_string())
@@ -565,13 +607,1031 @@ fn render_overlays(
         frame.render_stateful_widget(list, inner_area, &mut app.grouping_selection_state);
     }
 
-    // Render message details popup
-    if app.show_details {
+    // Render PendingCalls popup
+    if let Mode::PendingCalls = app.mode {
+        let _span = tracing::info_span!("render_pending_calls_popup").entered();
+        let area = centered_rect(80, 60, frame.area());
+        let now = std::time::SystemTime::now();
+        let timed_out_count = app
+            .pending_calls
+            .iter()
+            .filter(|call| {
+                now.duration_since(call.timestamp).unwrap_or_default() > config.method_call_timeout
+            })
+            .count();
+
+        let mut title_spans = Line::from(format!("Pending Calls ({})", app.pending_calls.len()));
+        if timed_out_count > 0 {
+            title_spans.push_span(Span::styled(
+                format!(
+                    " {} past {}s timeout",
+                    timed_out_count,
+                    config.method_call_timeout.as_secs()
+                ),
+                Style::default().fg(config.color_call_timed_out).bold(),
+            ));
+        }
+        let block = Block::default().title(title_spans).borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+
+        let inner_area = block.inner(area);
+
+        let list_items: Vec<ListItem> = app
+            .pending_calls
+            .iter()
+            .map(|call| {
+                let waited = now.duration_since(call.timestamp).unwrap_or_default();
+                let waited_style = if waited > config.method_call_timeout {
+                    Style::default().fg(config.color_call_timed_out).bold()
+                } else {
+                    Style::default().fg(config.color_timestamp_normal)
+                };
+                let mut spans = vec![
+                    Span::styled(format!("[{:>7.1}s] ", waited.as_secs_f32()), waited_style),
+                    Span::styled(
+                        call.serial.clone(),
+                        match call.stream_type {
+                            crate::bus::BusType::Session => {
+                                Style::default().fg(config.color_stream_session)
+                            }
+                            crate::bus::BusType::System => {
+                                Style::default().fg(config.color_stream_system)
+                            }
+                            crate::bus::BusType::Isolated => {
+                                Style::default().fg(config.color_stream_isolated)
+                            }
+                            crate::bus::BusType::Both => {
+                                Style::default().fg(config.color_timestamp_normal)
+                            }
+                        },
+                    ),
+                    Span::raw(" "),
+                    Span::styled(
+                        call.sender_display().into_owned(),
+                        Style::default().fg(config.color_sender_normal),
+                    ),
+                ];
+                if !call.receiver.is_empty() {
+                    spans.push(Span::raw(" -> "));
+                    spans.push(Span::styled(
+                        call.receiver_display().into_owned(),
+                        Style::default().fg(config.color_sender_normal),
+                    ));
+                }
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(
+                    call.member.clone(),
+                    Style::default().fg(config.color_member_normal),
+                ));
+                spans.push(Span::raw("@"));
+                spans.push(Span::styled(
+                    call.path.clone(),
+                    Style::default().fg(config.color_path_normal),
+                ));
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        if app.pending_calls.is_empty() {
+            app.pending_calls_state.select(None);
+        } else if app.pending_calls_state.selected().unwrap_or(0) >= app.pending_calls.len() {
+            app.pending_calls_state
+                .select(Some(app.pending_calls.len() - 1));
+        }
+
+        let list = List::new(list_items)
+            .block(Block::default())
+            .highlight_symbol("> ")
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(config.color_selection_highlight_bg),
+            );
+
+        frame.render_stateful_widget(list, inner_area, &mut app.pending_calls_state);
+    }
+
+    // Render Errors popup
+    if let Mode::Errors = app.mode {
+        let _span = tracing::info_span!("render_errors_popup").entered();
+        let area = centered_rect(80, 60, frame.area());
+        let total: usize = app.error_summaries.iter().map(|s| s.count).sum();
+        let block = Block::default()
+            .title(format!(
+                "Errors ({} names, {} total)",
+                app.error_summaries.len(),
+                total
+            ))
+            .borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+
+        let inner_area = block.inner(area);
+
+        let list_items: Vec<ListItem> = app
+            .error_summaries
+            .iter()
+            .map(|summary| {
+                let first_seen: chrono::DateTime<chrono::Local> = summary.first_seen.into();
+                let last_seen: chrono::DateTime<chrono::Local> = summary.last_seen.into();
+                let latest = &summary.latest;
+                let error_text = latest.error_text();
+
+                // An error travels from the callee back to the caller
+                let mut detail_spans = vec![
+                    Span::raw("    "),
+                    Span::styled(
+                        latest.receiver_display().into_owned(),
+                        Style::default().fg(config.color_sender_normal),
+                    ),
+                    Span::raw(" -> "),
+                    Span::styled(
+                        latest.sender_display().into_owned(),
+                        Style::default().fg(config.color_sender_normal),
+                    ),
+                ];
+                if !error_text.is_empty() {
+                    detail_spans.push(Span::raw(format!(": {}", error_text)));
+                }
+
+                ListItem::new(Text::from(vec![
+                    Line::from(vec![
+                        Span::styled(
+                            summary.name.clone(),
+                            Style::default().fg(config.color_error_name).bold(),
+                        ),
+                        Span::raw(format!(" ×{} ", summary.count)),
+                        Span::styled(
+                            format!(
+                                "[{} .. {}]",
+                                first_seen.format("%H:%M:%S"),
+                                last_seen.format("%H:%M:%S")
+                            ),
+                            Style::default().fg(config.color_timestamp_normal),
+                        ),
+                    ]),
+                    Line::from(detail_spans),
+                ]))
+            })
+            .collect();
+
+        if app.error_summaries.is_empty() {
+            app.error_summaries_state.select(None);
+        } else if app.error_summaries_state.selected().unwrap_or(0) >= app.error_summaries.len() {
+            app.error_summaries_state
+                .select(Some(app.error_summaries.len() - 1));
+        }
+
+        let list = List::new(list_items)
+            .block(Block::default())
+            .highlight_symbol("> ")
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(config.color_selection_highlight_bg),
+            );
+
+        frame.render_stateful_widget(list, inner_area, &mut app.error_summaries_state);
+    }
+
+    // Render bus names popup
+    if let Mode::Names = app.mode {
+        let _span = tracing::info_span!("render_names_popup").entered();
+        let area = centered_rect(80, 80, frame.area());
+        let block = Block::default()
+            .title(format!(
+                "Names on the {:?} bus ({}){}",
+                app.names.bus.unwrap_or(crate::bus::BusType::Session),
+                app.names.names.len(),
+                if app.names.starting {
+                    " (starting)"
+                } else if app.names.loading {
+                    " (listing)"
+                } else {
+                    ""
+                }
+            ))
+            .borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+
+        let inner_area = block.inner(area);
+
+        let list_items: Vec<ListItem> = app
+            .names
+            .names
+            .iter()
+            .map(|row| {
+                let mut name_spans = vec![
+                    Span::styled(
+                        row.name.clone(),
+                        Style::default().fg(config.color_sender_normal).bold(),
+                    ),
+                    Span::raw(format!(" ×{}", row.messages)),
+                ];
+                if row.activatable {
+                    name_spans.push(Span::styled(
+                        " [activatable]",
+                        Style::default().fg(config.color_member_normal),
+                    ));
+                }
+
+                let mut detail_spans = vec![Span::raw("    ")];
+                if row.owner.is_empty() {
+                    detail_spans.push(Span::styled(
+                        "not running",
+                        Style::default().fg(config.color_timestamp_normal),
+                    ));
+                } else if row.owner != row.name {
+                    detail_spans.push(Span::raw(row.owner.clone()));
+                    detail_spans.push(Span::raw(" "));
+                }
+                if let Some(process) = &row.process {
+                    detail_spans.push(Span::styled(
+                        format!("{}:{}", process.name, process.pid),
+                        Style::default().fg(config.color_path_normal),
+                    ));
+                }
+                if !row.queued.is_empty() {
+                    detail_spans.push(Span::raw(format!(" queued: {}", row.queued.join(", "))));
+                }
+
+                ListItem::new(Text::from(vec![
+                    Line::from(name_spans),
+                    Line::from(detail_spans),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(list_items)
+            .block(Block::default())
+            .highlight_symbol("> ")
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(config.color_selection_highlight_bg),
+            );
+
+        frame.render_stateful_widget(list, inner_area, &mut app.names.state);
+    }
+
+    // Render introspection Browser popup
+    if let Mode::Browser = app.mode {
+        let _span = tracing::info_span!("render_browser_popup").entered();
+        let area = centered_rect(80, 80, frame.area());
+        let bus_label = match app.browser.bus {
+            Some(crate::bus::BusType::System) => "System",
+            Some(crate::bus::BusType::Isolated) => "Isolated",
+            _ => "Session",
+        };
+        let block = Block::default()
+            .title(format!("Browse {} Bus ({} names)", bus_label, {
+                app.browser
+                    .nodes
+                    .iter()
+                    .filter(|n| n.kind == crate::introspect::BrowserNodeKind::Name)
+                    .count()
+            }))
+            .borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+
+        let inner_area = block.inner(area);
+
+        let list_items: Vec<ListItem> = app
+            .browser
+            .nodes
+            .iter()
+            .map(|node| {
+                let glyph = if !node.kind.is_expandable() {
+                    "  "
+                } else if node.expanded {
+                    "▾ "
+                } else {
+                    "▸ "
+                };
+                let style = match node.kind {
+                    crate::introspect::BrowserNodeKind::Name => {
+                        Style::default().fg(config.color_sender_normal)
+                    }
+                    crate::introspect::BrowserNodeKind::Object => {
+                        Style::default().fg(config.color_path_normal)
+                    }
+                    crate::introspect::BrowserNodeKind::Interface => {
+                        Style::default().fg(config.color_member_normal)
+                    }
+                    _ => Style::default(),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw("  ".repeat(node.depth)),
+                    Span::raw(glyph),
+                    Span::styled(node.label.clone(), style),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(list_items)
+            .block(Block::default())
+            .highlight_symbol("> ")
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(config.color_selection_highlight_bg),
+            );
+
+        frame.render_stateful_widget(list, inner_area, &mut app.browser.state);
+    }
+
+    // Render method call Composer popup
+    if let Mode::Composer = app.mode {
+        let _span = tracing::info_span!("render_composer_popup").entered();
+        let fields = crate::composer::COMPOSER_FIELDS;
+        let r = frame.area();
+        let height = fields.len() as u16 + 5;
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(height),
+                Constraint::Min(0),
+            ])
+            .split(r);
+        let area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(80),
+                Constraint::Percentage(10),
+            ])
+            .split(vertical[1])[1];
+
+        let block = Block::default()
+            .title(format!(
+                "Call Method on {:?} Bus",
+                app.composer.bus.unwrap_or(app.active_bus())
+            ))
+            .borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+        let inner_area = block.inner(area);
+
+        let label_width = fields.iter().map(|f| f.len()).max().unwrap_or(0) + 2;
+        let value_width = (inner_area.width as usize)
+            .saturating_sub(label_width)
+            .max(1);
+        let mut lines: Vec<Line> = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            let input = &app.composer.inputs[i];
+            let focused = i == app.composer.focus;
+            let value: String = input
+                .value()
+                .chars()
+                .skip(input.visual_scroll(value_width))
+                .collect();
+            let label_style = if focused {
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(config.color_keybind_key)
+            } else {
+                Style::default()
+            };
+            let value_style = if focused {
+                Style::default().bg(config.color_selection_highlight_bg)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}", format!("{}:", field), width = label_width),
+                    label_style,
+                ),
+                Span::styled(value, value_style),
+            ]));
+        }
+        lines.push(Line::from(""));
+
+        let signature = match &app.composer.args {
+            Some(args) => format!(
+                "({})",
+                args.iter()
+                    .map(|arg| match &arg.name {
+                        Some(name) => format!("{}: {}", name, arg.signature),
+                        None => arg.signature.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            None => "unknown, argument types are inferred".to_string(),
+        };
+        lines.push(Line::from(vec![
+            Span::raw("Signature: "),
+            Span::styled(signature, Style::default().fg(config.color_member_normal)),
+        ]));
+        if app.composer.sending {
+            lines.push(Line::from(Span::styled(
+                "Sending, waiting for the reply",
+                Style::default().add_modifier(Modifier::DIM),
+            )));
+        } else if !app.composer.error.is_empty() {
+            lines.push(Line::from(Span::styled(
+                app.composer.error.clone(),
+                Style::default().fg(config.color_error_name),
+            )));
+        }
+
+        frame.render_widget(Paragraph::new(lines), inner_area);
+    }
+
+    // Render Repeater popup
+    if let Mode::Repeater = app.mode {
+        let _span = tracing::info_span!("render_repeater_popup").entered();
+        let area = centered_rect(80, 80, frame.area());
+        let title = match &app.repeater.call {
+            Some(call) => format!("Resend {} -> {} {}", call.member, call.receiver, call.path),
+            None => "Resend".to_string(),
+        };
+        let block = Block::default().title(title).borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+        let inner_area = block.inner(area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(inner_area);
+
+        let signature: String = app
+            .repeater
+            .signatures
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let input_block = Block::default()
+            .title(format!("Arguments ({})", signature))
+            .borders(Borders::ALL);
+        let width = input_block.inner(rows[0]).width.max(3);
+        let scroll = app.repeater.input.visual_scroll(width as usize);
+        frame.render_widget(
+            Paragraph::new(app.repeater.input.value())
+                .scroll((0, scroll as u16))
+                .block(input_block),
+            rows[0],
+        );
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                app.repeater.error.clone(),
+                Style::default().fg(config.color_error_name),
+            )),
+            rows[1],
+        );
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(rows[2]);
+
+        let list_items: Vec<ListItem> = app
+            .repeater
+            .history
+            .iter()
+            .enumerate()
+            .map(|(i, attempt)| {
+                let dt: chrono::DateTime<chrono::Local> = attempt.sent_at.into();
+                let outcome = match &attempt.reply {
+                    Ok(reply) => match reply.header().error_name() {
+                        Some(name) => Span::styled(
+                            name.to_string(),
+                            Style::default().fg(config.color_error_name),
+                        ),
+                        None => {
+                            Span::styled("return", Style::default().fg(config.color_member_normal))
+                        }
+                    },
+                    Err(e) => {
+                        Span::styled(e.clone(), Style::default().fg(config.color_call_timed_out))
+                    }
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("#{} ", i + 1)),
+                    Span::styled(
+                        dt.format("%H:%M:%S%.3f ").to_string(),
+                        Style::default().fg(config.color_timestamp_normal),
+                    ),
+                    outcome,
+                ]))
+            })
+            .collect();
+        let list = List::new(list_items)
+            .block(
+                Block::default()
+                    .title(match app.repeater.sending {
+                        0 => format!("Attempts ({})", app.repeater.history.len()),
+                        sending => format!(
+                            "Attempts ({}, {} sending)",
+                            app.repeater.history.len(),
+                            sending
+                        ),
+                    })
+                    .borders(Borders::ALL),
+            )
+            .highlight_symbol("> ")
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(config.color_selection_highlight_bg),
+            );
+        frame.render_stateful_widget(list, columns[0], &mut app.repeater.history_state);
+
+        let reply_text = match app.repeater.selected_attempt() {
+            Some(attempt) => {
+                let mut lines = vec![
+                    Line::from(format!("Arguments: {}", attempt.args)),
+                    Line::from(""),
+                ];
+                match &attempt.reply {
+                    Ok(reply) => lines.extend(format_reply(reply, config).lines),
+                    Err(e) => lines.push(Line::from(e.clone())),
+                }
+                Text::from(lines)
+            }
+            None => Text::from("Press Enter to send"),
+        };
+        frame.render_widget(
+            Paragraph::new(reply_text)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().title("Reply").borders(Borders::ALL)),
+            columns[1],
+        );
+    }
+
+    // Render Properties popup
+    if let Mode::Properties = app.mode {
+        let _span = tracing::info_span!("render_properties_popup").entered();
+        let area = centered_rect(80, 80, frame.area());
+        let block = Block::default()
+            .title(format!(
+                "Properties of {} {}{}",
+                app.properties.destination,
+                app.properties.path,
+                if app.properties.loading {
+                    " (reading)"
+                } else {
+                    ""
+                }
+            ))
+            .borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+        let inner_area = block.inner(area);
+
+        let footer_height = if app.properties.editing.is_some() {
+            4
+        } else if !app.properties.error.is_empty() {
+            1
+        } else {
+            0
+        };
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(footer_height)])
+            .split(inner_area);
+
+        let mut previous_interface: Option<&str> = None;
+        let list_items: Vec<ListItem> = app
+            .properties
+            .entries
+            .iter()
+            .map(|property| {
+                let mut lines: Vec<Line> = Vec::new();
+                if previous_interface != Some(property.interface.as_str()) {
+                    lines.push(Line::from(Span::styled(
+                        property.interface.clone(),
+                        Style::default()
+                            .add_modifier(Modifier::BOLD)
+                            .fg(config.color_member_normal),
+                    )));
+                    previous_interface = Some(property.interface.as_str());
+                }
+                let access = match property.writable {
+                    Some(true) => " [readwrite]",
+                    Some(false) => " [read]",
+                    None => "",
+                };
+                let signature = property
+                    .signature
+                    .as_ref()
+                    .map(|s| s.to_string())
+                    .unwrap_or_default();
+                lines.push(Line::from(vec![
+                    Span::raw("  "),
+                    Span::styled(
+                        property.name.clone(),
+                        Style::default().fg(config.color_sender_normal),
+                    ),
+                    Span::raw(format!(": {}{}", signature, access)),
+                ]));
+                match &property.value {
+                    Some(value) => {
+                        let decoded = [config.decoders.decode_property(
+                            &property.interface,
+                            &property.name,
+                            value,
+                        )];
+                        let text = format_value(value, &[], &decoded, config);
+                        lines.extend(text.lines.into_iter().map(|line| {
+                            let mut indented = Line::from("    ");
+                            indented.extend(line.spans);
+                            indented
+                        }))
+                    }
+                    None => lines.push(Line::from("    [not readable]".dim())),
+                }
+                ListItem::new(Text::from(lines))
+            })
+            .collect();
+        let list = List::new(list_items)
+            .highlight_symbol("> ")
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(config.color_selection_highlight_bg),
+            );
+        frame.render_stateful_widget(list, rows[0], &mut app.properties.state);
+
+        let error = if app.properties.saving {
+            Span::styled(
+                "Setting the value",
+                Style::default().add_modifier(Modifier::DIM),
+            )
+        } else {
+            Span::styled(
+                app.properties.error.clone(),
+                Style::default().fg(config.color_error_name),
+            )
+        };
+        if let Some(input) = &app.properties.editing {
+            let name = app
+                .properties
+                .selected()
+                .map(|p| p.name.clone())
+                .unwrap_or_default();
+            let input_block = Block::default()
+                .title(format!("Set {}", name))
+                .borders(Borders::ALL);
+            let edit_rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Length(1)])
+                .split(rows[1]);
+            let width = input_block.inner(edit_rows[0]).width.max(3);
+            let scroll = input.visual_scroll(width as usize);
+            frame.render_widget(
+                Paragraph::new(input.value())
+                    .scroll((0, scroll as u16))
+                    .block(input_block),
+                edit_rows[0],
+            );
+            frame.render_widget(Paragraph::new(error), edit_rows[1]);
+        } else {
+            frame.render_widget(Paragraph::new(error), rows[1]);
+        }
+    }
+
+    // Render Objects popup
+    if let Mode::Objects = app.mode {
+        let _span = tracing::info_span!("render_objects_popup").entered();
+        let area = centered_rect(80, 80, frame.area());
+        let block = Block::default()
+            .title(format!(
+                "Objects of {} ({})",
+                app.objects.destination, app.objects.manager_path
+            ))
+            .borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+        let inner_area = block.inner(area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(0)])
+            .split(inner_area);
+
+        let position = match app.objects.at {
+            _ if app.objects.loading => Line::from(Span::styled(
+                "Looking for the object manager",
+                Style::default().add_modifier(Modifier::DIM),
+            )),
+            _ if !app.objects.error.is_empty() => Line::from(Span::styled(
+                app.objects.error.clone(),
+                Style::default().fg(config.color_error_name),
+            )),
+            None => Line::from(Span::styled(
+                "Live",
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(config.color_property_new),
+            )),
+            Some(at) => {
+                let dt: chrono::DateTime<chrono::Local> = at.into();
+                let mut spans = vec![
+                    Span::raw("At "),
+                    Span::styled(
+                        dt.format("%H:%M:%S%.3f").to_string(),
+                        Style::default().fg(config.color_timestamp_normal),
+                    ),
+                ];
+                if let Some(item) = app
+                    .list_state
+                    .selected()
+                    .and_then(|i| filtered_items.get(i))
+                {
+                    spans.push(Span::raw(format!(
+                        " after {} {}@{}",
+                        item.sender_display(),
+                        item.member,
+                        item.path
+                    )));
+                }
+                Line::from(spans)
+            }
+        };
+        frame.render_widget(Paragraph::new(position), rows[0]);
+
+        let mut lines: Vec<Line> = Vec::new();
+        for (path, interfaces) in app.objects.state() {
+            lines.push(Line::from(Span::styled(
+                path,
+                Style::default().fg(config.color_path_normal),
+            )));
+            for (interface, properties) in interfaces {
+                lines.push(Line::from(vec![
+                    Span::raw("  "),
+                    Span::styled(
+                        interface.clone(),
+                        Style::default().fg(config.color_member_normal),
+                    ),
+                ]));
+                for (name, value) in properties {
+                    let text = format_property_value(&interface, &name, &value, config);
+                    lines.push(Line::from(vec![
+                        Span::raw("    "),
+                        Span::styled(name, Style::default().fg(config.color_sender_normal)),
+                        Span::raw(": "),
+                        Span::raw(text),
+                    ]));
+                }
+            }
+        }
+        let max_scroll = (lines.len() as u16).saturating_sub(rows[1].height);
+        app.objects.scroll = app.objects.scroll.min(max_scroll);
+        frame.render_widget(
+            Paragraph::new(lines).scroll((app.objects.scroll, 0)),
+            rows[1],
+        );
+    }
+
+    // Render proxy export popup
+    if let Mode::ProxyExport = app.mode {
+        let _span = tracing::info_span!("render_proxy_export_popup").entered();
+        let area = centered_rect(80, 80, frame.area());
+        let block = Block::default()
+            .title("Export zbus Proxy")
+            .borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+        let inner_area = block.inner(area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(inner_area);
+
+        let input_block = Block::default().title("File").borders(Borders::ALL);
+        let width = input_block.inner(rows[0]).width.max(3);
+        let scroll = app.proxy_export.input.visual_scroll(width as usize);
+        frame.render_widget(
+            Paragraph::new(app.proxy_export.input.value())
+                .scroll((0, scroll as u16))
+                .block(input_block),
+            rows[0],
+        );
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                app.proxy_export.error.clone(),
+                Style::default().fg(config.color_error_name),
+            )),
+            rows[1],
+        );
+
+        let max_scroll =
+            (app.proxy_export.code.lines().count() as u16).saturating_sub(rows[2].height);
+        app.proxy_export.scroll = app.proxy_export.scroll.min(max_scroll);
+        frame.render_widget(
+            Paragraph::new(app.proxy_export.code.clone()).scroll((app.proxy_export.scroll, 0)),
+            rows[2],
+        );
+    }
+
+    // Render fault rules popup
+    if let Mode::Faults = app.mode {
+        let _span = tracing::info_span!("render_faults_popup").entered();
+        let area = centered_rect(80, 60, frame.area());
+        let block = Block::default().title("Fault Rules").borders(Borders::ALL);
+        frame.render_widget(Clear, area);
+        frame.render_widget(&block, area);
+        let inner_area = block.inner(area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(inner_area);
+
+        let input_block = Block::default()
+            .title("New rule, ie: delay 500ms member=GetId | drop | duplicate | error <name>")
+            .borders(Borders::ALL);
+        let width = input_block.inner(rows[0]).width.max(3);
+        let scroll = app.faults.input.visual_scroll(width as usize);
+        frame.render_widget(
+            Paragraph::new(app.faults.input.value())
+                .scroll((0, scroll as u16))
+                .block(input_block),
+            rows[0],
+        );
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                app.faults.error.clone(),
+                Style::default().fg(config.color_error_name),
+            )),
+            rows[1],
+        );
+
+        let rules = app.faults.rules();
+        let list_items: Vec<ListItem> = rules
+            .iter()
+            .map(|rule| {
+                let (state, style) = if rule.enabled {
+                    ("[on]  ", Style::default().fg(config.color_fault))
+                } else {
+                    ("[off] ", Style::default().fg(Color::DarkGray))
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(state, style),
+                    Span::raw(rule.text()),
+                ]))
+            })
+            .collect();
+        let list = List::new(list_items)
+            .block(
+                Block::default()
+                    .title(format!("Rules ({}), first match applies", rules.len()))
+                    .borders(Borders::ALL),
+            )
+            .highlight_symbol("> ")
+            .highlight_style(
+                Style::default()
+                    .bg(config.color_selection_highlight_bg)
+                    .fg(config.color_selection_highlight_fg),
+            );
+        frame.render_stateful_widget(list, rows[2], &mut app.faults.state);
+    }
+
+    // Render launch summary popup
+    if let (Mode::LaunchSummary, Some(launch)) = (&app.mode, &mut app.launch) {
+        let _span = tracing::info_span!("render_launch_summary_popup").entered();
+        let area = centered_rect(80, 60, frame.area());
+        let list_items: Vec<ListItem> = launch
+            .summary
+            .iter()
+            .flatten()
+            .map(|line| {
+                if line.starts_with(' ') {
+                    ListItem::new(line.clone())
+                } else {
+                    ListItem::new(Span::styled(line.clone(), Style::default().bold()))
+                }
+            })
+            .collect();
+        let list = List::new(list_items)
+            .block(
+                Block::default()
+                    .title(format!("Launched: {}", launch.command.join(" ")))
+                    .borders(Borders::ALL),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(config.color_selection_highlight_bg)
+                    .fg(config.color_selection_highlight_fg),
+            );
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut launch.summary_state);
+    }
+
+    // Render command format popup
+    if let Mode::CommandFormat = app.mode {
+        let _span = tracing::info_span!("render_command_format_popup").entered();
+        let area = centered_rect(80, 40, frame.area());
+        let block = Block::default()
+            .title("Copy Call as Command")
+            .borders(Borders::ALL);
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(app.command_choices.len() as u16 + 1),
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        let items: Vec<ListItem> = app
+            .command_choices
+            .iter()
+            .map(|(format, command)| match command {
+                Ok(_) => ListItem::new(format.name()),
+                Err(_) => ListItem::new(Line::from(vec![
+                    Span::raw(format.name()),
+                    Span::raw(" (not possible)").dim(),
+                ])),
+            })
+            .collect();
+        let list = List::new(items).highlight_symbol("> ").highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(config.color_selection_highlight_fg)
+                .bg(config.color_selection_highlight_bg),
+        );
+        frame.render_stateful_widget(list, rows[0], &mut app.command_choice_state);
+
+        // Preview of the selected command
+        let preview = match app
+            .command_choice_state
+            .selected()
+            .and_then(|i| app.command_choices.get(i))
+        {
+            Some((_, Ok(command))) => Line::raw(command.clone()),
+            Some((_, Err(e))) => {
+                Line::styled(e.clone(), Style::default().fg(config.color_error_name))
+            }
+            None => Line::default(),
+        };
+        frame.render_widget(Paragraph::new(preview).wrap(Wrap { trim: false }), rows[1]);
+    }
+
+    // Render message details popup with the body as a tree
+    if app.show_details && app.detail_tab == DetailTab::Body && !app.body_tree.roots.is_empty() {
+        let _span = tracing::info_span!("render_details_tree_popup").entered();
+        let area = centered_rect(80, 80, frame.area());
+        let block = Block::default()
+            .title(details_title(app.detail_tab, ""))
+            .borders(Borders::ALL);
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let header_height = (app.detail_text.lines.len() as u16).min(inner.height / 2);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(header_height), Constraint::Min(0)])
+            .split(inner);
+        frame.render_widget(
+            Paragraph::new(app.detail_text.clone()).wrap(Wrap { trim: false }),
+            rows[0],
+        );
+
+        // The highlight symbol takes two columns
+        let width = rows[1].width.saturating_sub(2) as usize;
+        let items = body_tree_items(&app.body_tree, width, config);
+        let list = List::new(items).highlight_symbol("> ").highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(config.color_selection_highlight_fg)
+                .bg(config.color_selection_highlight_bg),
+        );
+        frame.render_stateful_widget(list, rows[1], &mut app.body_tree.state);
+    } else if app.show_details {
         let _span = tracing::info_span!("render_details_view_popup").entered();
         let area = centered_rect(80, 80, frame.area());
         let popup_inner_height = area.height.saturating_sub(2);
 
-        let num_text_lines = app.detail_text.lines.len() as u16;
+        let text = match app.detail_tab {
+            DetailTab::Body => &app.detail_text,
+            DetailTab::Wire => &app.wire_text,
+        };
+        let num_text_lines = text.lines.len() as u16;
         let max_scroll = num_text_lines.saturating_sub(popup_inner_height);
 
         if let Some(delta) = app.detail_scroll_request.take() {
@@ -588,13 +1648,11 @@ fn render_overlays(
             (false, false) => "",
         };
 
-        let title_spans = Line::from(vec![
-            Span::raw("Message Details "),
-            Span::raw(scroll_indicator),
-        ]);
-        let block = Block::default().title(title_spans).borders(Borders::ALL);
+        let block = Block::default()
+            .title(details_title(app.detail_tab, scroll_indicator))
+            .borders(Borders::ALL);
 
-        let paragraph = Paragraph::new(app.detail_text.clone())
+        let paragraph = Paragraph::new(text.clone())
             .block(block)
             .wrap(Wrap { trim: false })
             .scroll((app.detail_scroll, 0));
@@ -662,12 +1720,113 @@ fn render_status_bar(frame: &mut Frame, app: &mut App, config: &Config, area: Re
             )
             .block(Block::default().borders(Borders::ALL).title("Grouping"))
         }
+        Mode::PendingCalls => {
+            let _span = tracing::info_span!("render_bottom_keybinds_pending_calls").entered();
+            Paragraph::new(app.cached_pending_calls_key_hints.as_ref().unwrap().clone()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Pending Calls"),
+            )
+        }
+        Mode::Errors => {
+            let _span = tracing::info_span!("render_bottom_keybinds_errors").entered();
+            Paragraph::new(app.cached_errors_key_hints.as_ref().unwrap().clone())
+                .block(Block::default().borders(Borders::ALL).title("Errors"))
+        }
+        Mode::Names => {
+            let _span = tracing::info_span!("render_bottom_keybinds_names").entered();
+            let help_text = if !app.status_message.is_empty() {
+                Line::from(app.status_message.as_str().fg(config.color_status_message))
+            } else {
+                app.cached_names_key_hints.as_ref().unwrap().clone()
+            };
+            Paragraph::new(help_text).block(Block::default().borders(Borders::ALL).title("Names"))
+        }
+        Mode::Browser => {
+            let _span = tracing::info_span!("render_bottom_keybinds_browser").entered();
+            let help_text = if !app.status_message.is_empty() {
+                Line::from(app.status_message.as_str().fg(config.color_status_message))
+            } else {
+                app.cached_browser_key_hints.as_ref().unwrap().clone()
+            };
+            Paragraph::new(help_text).block(Block::default().borders(Borders::ALL).title("Browse"))
+        }
+        Mode::Repeater => {
+            let _span = tracing::info_span!("render_bottom_keybinds_repeater").entered();
+            Paragraph::new(app.cached_repeater_key_hints.as_ref().unwrap().clone())
+                .block(Block::default().borders(Borders::ALL).title("Resend"))
+        }
+        Mode::Properties => {
+            let _span = tracing::info_span!("render_bottom_keybinds_properties").entered();
+            let key_hints = if app.properties.editing.is_some() {
+                app.cached_property_edit_key_hints.as_ref().unwrap().clone()
+            } else {
+                app.cached_properties_key_hints.as_ref().unwrap().clone()
+            };
+            Paragraph::new(key_hints)
+                .block(Block::default().borders(Borders::ALL).title("Properties"))
+        }
+        Mode::Objects => {
+            let _span = tracing::info_span!("render_bottom_keybinds_objects").entered();
+            Paragraph::new(app.cached_objects_key_hints.as_ref().unwrap().clone())
+                .block(Block::default().borders(Borders::ALL).title("Objects"))
+        }
+        Mode::ProxyExport => {
+            let _span = tracing::info_span!("render_bottom_keybinds_proxy_export").entered();
+            Paragraph::new(app.cached_proxy_export_key_hints.as_ref().unwrap().clone())
+                .block(Block::default().borders(Borders::ALL).title("Export Proxy"))
+        }
+        Mode::Faults => {
+            let _span = tracing::info_span!("render_bottom_keybinds_faults").entered();
+            Paragraph::new(app.cached_faults_key_hints.as_ref().unwrap().clone())
+                .block(Block::default().borders(Borders::ALL).title("Faults"))
+        }
+        Mode::LaunchSummary => {
+            let _span = tracing::info_span!("render_bottom_keybinds_launch_summary").entered();
+            Paragraph::new(
+                app.cached_launch_summary_key_hints
+                    .as_ref()
+                    .unwrap()
+                    .clone(),
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Launch Summary"),
+            )
+        }
+        Mode::CommandFormat => {
+            let _span = tracing::info_span!("render_bottom_keybinds_command_format").entered();
+            Paragraph::new(
+                app.cached_command_format_key_hints
+                    .as_ref()
+                    .unwrap()
+                    .clone(),
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Copy as Command"),
+            )
+        }
+        Mode::Composer => {
+            let _span = tracing::info_span!("render_bottom_keybinds_composer").entered();
+            Paragraph::new(app.cached_composer_key_hints.as_ref().unwrap().clone())
+                .block(Block::default().borders(Borders::ALL).title("Call Method"))
+        }
     };
     frame.render_widget(help_paragraph, area);
 }
 
 // Helper function to format a `zbus::zvariant::Value` in a YAML-like, readable way.
-pub fn format_value(value: &Value, config: &Config) -> Text<'static> {
+// Top-level fields are labelled with `labels` where given instead of `i_N`, and the symbolic
+// names in `decoded` are appended to fields that fit on a single line.
+pub fn format_value(
+    value: &Value,
+    labels: &[String],
+    decoded: &[Option<String>],
+    config: &Config,
+) -> Text<'static> {
     // Inner recursive function to handle nesting and indentation.
     fn format_recursive(
         value: &Value,
@@ -1073,21 +2232,294 @@ pub fn format_value(value: &Value, config: &Config) -> Text<'static> {
     // Special handling for top-level `Structure` to match desired output format.
     if let Value::Structure(s) = value {
         for (i, field) in s.fields().iter().enumerate() {
-            all_lines.extend(format_recursive(
-                field,
-                0,
-                &format!("i_{}", i + 1),
-                i,
-                config,
-            ));
+            let label = labels
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| format!("i_{}", i + 1));
+            let mut field_lines = format_recursive(field, 0, &label, i, config);
+            append_decoded(&mut field_lines, decoded.get(i), config);
+            all_lines.extend(field_lines);
         }
     } else {
         // Fallback for any non-Structure top-level value.
-        all_lines.extend(format_recursive(value, 0, "value", 0, config));
+        let label = labels.first().map_or("value", String::as_str);
+        let mut value_lines = format_recursive(value, 0, label, 0, config);
+        append_decoded(&mut value_lines, decoded.first(), config);
+        all_lines.extend(value_lines);
     }
     Text::from(all_lines)
 }
 
+/// Title of the details popup with the active tab highlighted.
+fn details_title(tab: DetailTab, scroll_indicator: &str) -> Line<'static> {
+    let tab_span = |name: &'static str, active: bool| {
+        if active {
+            Span::raw(name).bold().reversed()
+        } else {
+            Span::raw(name).dim()
+        }
+    };
+    Line::from(vec![
+        Span::raw("Message Details "),
+        tab_span("Body", tab == DetailTab::Body),
+        Span::raw("|"),
+        tab_span("Wire", tab == DetailTab::Wire),
+        Span::raw(" "),
+        Span::raw(scroll_indicator.to_string()),
+    ])
+}
+
+/// One list item per visible node of the body tree, wrapped to `width`.
+fn body_tree_items(
+    tree: &crate::body_tree::BodyTree,
+    width: usize,
+    config: &Config,
+) -> Vec<ListItem<'static>> {
+    tree.rows()
+        .into_iter()
+        .map(|(depth, node)| {
+            let glyph = match (node.is_container(), node.expanded) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                (false, _) => "  ",
+            };
+            let mut text = format!("{}{}{}", "  ".repeat(depth), glyph, node.label);
+            if let Some(signature) = node.type_text() {
+                text.push_str(&format!(" [{}]", signature));
+            }
+            text.push_str(": ");
+            text.push_str(&node.value_text());
+
+            let style = match node.value {
+                Value::Dict(_) => Style::default().bg(config.color_dict),
+                Value::Structure(_) => Style::default().bg(config.color_struct),
+                _ => Style::default(),
+            };
+
+            // Continuation lines are indented past the glyph of the node
+            let continuation = "  ".repeat(depth + 2);
+            let mut lines: Vec<Line<'static>> = Vec::new();
+            let mut rest = text.as_str();
+            loop {
+                let available = if lines.is_empty() {
+                    width
+                } else {
+                    width.saturating_sub(continuation.len())
+                }
+                .max(1);
+                let split = rest
+                    .char_indices()
+                    .nth(available)
+                    .map_or(rest.len(), |(i, _)| i);
+                let (line, tail) = rest.split_at(split);
+                let line = if lines.is_empty() {
+                    line.to_string()
+                } else {
+                    format!("{}{}", continuation, line)
+                };
+                lines.push(Line::styled(line, style));
+                rest = tail;
+                if rest.is_empty() {
+                    break;
+                }
+            }
+            if let (Some(decoded), Some(last)) = (&node.decoded, lines.last_mut()) {
+                last.spans.push(Span::styled(
+                    format!(" ({})", decoded),
+                    Style::default().fg(config.color_decoded),
+                ));
+            }
+            ListItem::new(Text::from(lines))
+        })
+        .collect()
+}
+
+/// Appends a decoded symbolic name to a value that was formatted on a single line.
+fn append_decoded(lines: &mut [Line<'static>], decoded: Option<&Option<String>>, config: &Config) {
+    if let ([line], Some(Some(name))) = (lines, decoded) {
+        line.spans.push(Span::styled(
+            format!(" ({})", name),
+            Style::default().fg(config.color_decoded),
+        ));
+    }
+}
+
+/// Formats a property value on one line, followed by its symbolic name if a decoder knows it.
+pub fn format_property_value(
+    interface: &str,
+    property: &str,
+    value: &Value,
+    config: &Config,
+) -> String {
+    let text = crate::gvariant::format(value);
+    match config.decoders.decode_property(interface, property, value) {
+        Some(name) => format!("{} ({})", text, name),
+        None => text,
+    }
+}
+
+/// Formats property changes as a diff of old and new values, grouped by interface.
+pub fn format_property_changes(
+    changes: &[crate::bus::PropertyChange],
+    config: &Config,
+) -> Text<'static> {
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut previous_interface: Option<&str> = None;
+    for change in changes {
+        if previous_interface != Some(change.interface.as_str()) {
+            lines.push(Line::from(Span::styled(
+                change.interface.clone(),
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(config.color_member_normal),
+            )));
+            previous_interface = Some(change.interface.as_str());
+        }
+
+        let mut spans = vec![Span::raw(format!("  {}: ", change.name))];
+        let format =
+            |value: &Value| format_property_value(&change.interface, &change.name, value, config);
+        let old = change.old.as_ref().map(format);
+        let new = change.new.as_ref().map(format);
+        match (old, new) {
+            (Some(old), Some(new)) if old == new => {
+                spans.push(Span::raw(new));
+                spans.push(Span::raw(" (unchanged)").dim());
+            }
+            (Some(old), Some(new)) => {
+                spans.push(Span::styled(
+                    old,
+                    Style::default().fg(config.color_property_old),
+                ));
+                spans.push(Span::raw(" → "));
+                spans.push(Span::styled(
+                    new,
+                    Style::default().fg(config.color_property_new),
+                ));
+            }
+            (None, Some(new)) => {
+                spans.push(Span::raw("? → ").dim());
+                spans.push(Span::styled(
+                    new,
+                    Style::default().fg(config.color_property_new),
+                ));
+            }
+            (Some(old), None) => {
+                spans.push(Span::styled(
+                    old,
+                    Style::default().fg(config.color_property_old),
+                ));
+                spans.push(Span::raw(" → invalidated").dim());
+            }
+            (None, None) => spans.push(Span::raw("invalidated").dim()),
+        }
+        lines.push(Line::from(spans));
+    }
+    Text::from(lines)
+}
+
+/// Formats the body of a message for the details view.
+/// Top-level arguments are labelled with `labels` where given.
+pub fn format_message_body(
+    message: &zbus::Message,
+    labels: &[String],
+    config: &Config,
+) -> Text<'static> {
+    let body = message.body();
+    let body_sig = body.signature();
+
+    let header = message.header();
+    let interface = header.interface().map(|i| i.as_str()).unwrap_or_default();
+    let member = header.member().map(|m| m.as_str()).unwrap_or_default();
+    let decode = |args: &[Value]| -> Vec<Option<String>> {
+        args.iter()
+            .enumerate()
+            .map(|(i, arg)| config.decoders.decode_arg(interface, member, i, arg))
+            .collect()
+    };
+
+    if body_sig.to_string().is_empty() {
+        Text::from("[No message body]")
+    } else {
+        match body.deserialize::<Structure>() {
+            Ok(structure) => {
+                let decoded = decode(structure.fields());
+                format_value(&Value::from(structure), labels, &decoded, config)
+            }
+            Err(_) => match body.deserialize::<Value>() {
+                Ok(value) => {
+                    let decoded = decode(std::slice::from_ref(&value));
+                    format_value(&value, labels, &decoded, config)
+                }
+                Err(e) => Text::from(format!(
+                    "Failed to deserialize body.\n\nSignature: {}\nError: {:#?}",
+                    body_sig, e
+                )),
+            },
+        }
+    }
+}
+
+/// Generates the detail text for the reply to a call sent by d-buddy.
+pub fn format_reply(reply: &zbus::Message, config: &Config) -> Text<'static> {
+    let mut lines = format_reply_header(reply, config);
+    lines.extend(format_message_body(reply, &[], config).lines);
+    Text::from(lines)
+}
+
+/// The lines above the body of a reply: who sent it and the error name, if any.
+pub fn format_reply_header(reply: &zbus::Message, config: &Config) -> Vec<Line<'static>> {
+    let header = reply.header();
+    let mut lines: Vec<Line<'static>> = Vec::new();
+
+    let sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+    match header.error_name() {
+        Some(name) => lines.push(Line::from(vec![
+            Span::raw(format!("Error reply from {}: ", sender)),
+            Span::styled(
+                name.to_string(),
+                Style::default().fg(config.color_error_name),
+            ),
+        ])),
+        None => lines.push(Line::from(vec![
+            Span::raw("Reply from "),
+            Span::styled(sender, Style::default().fg(config.color_sender_normal)),
+        ])),
+    }
+    lines.push(Line::from(vec![Span::raw("")])); // Empty line for spacing
+    lines
+}
+
+/// Formats the wire bytes of a message as a hexdump annotated with what each run of bytes encodes.
+pub fn format_wire(message: &zbus::Message, config: &Config) -> Text<'static> {
+    let data: &[u8] = message.data();
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    for segment in crate::wire::annotate(data) {
+        let style = match segment.part {
+            crate::wire::Part::Fixed => Style::default().fg(config.color_member_normal),
+            crate::wire::Part::Field => Style::default().fg(config.color_path_normal),
+            crate::wire::Part::Padding => Style::default().add_modifier(Modifier::DIM),
+            crate::wire::Part::Body => Style::default(),
+            crate::wire::Part::Invalid => Style::default().fg(config.color_error_name),
+        };
+        let bytes = &data[segment.offset..segment.offset + segment.len];
+        for (i, chunk) in bytes.chunks(HEXDUMP_WIDTH).enumerate() {
+            let dump = hexdump(chunk);
+            let (hex, ascii) = dump.split_at(HEXDUMP_WIDTH * 3);
+            let mut spans = vec![
+                Span::raw(format!("{:08x}  ", segment.offset + i * HEXDUMP_WIDTH)),
+                Span::styled(hex.to_string(), style),
+                Span::raw(format!("{:width$}", ascii, width = HEXDUMP_WIDTH + 2)).dim(),
+            ];
+            if i == 0 {
+                spans.push(Span::styled(segment.description.clone(), style));
+            }
+            lines.push(Line::from(spans));
+        }
+    }
+    Text::from(lines)
+}
+
 /// Helper function to create a centered rectangle given a percentage of the available area.
 /// This is typically used for popups or modal dialogs.
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -0,0 +1,387 @@
+/// What a run of bytes in a message belongs to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Part {
+    /// The fixed part of the header: endianness, type, flags, version, lengths and serial.
+    Fixed,
+    /// A header field code, signature or value.
+    Field,
+    /// Alignment padding.
+    Padding,
+    Body,
+    /// Bytes that could not be decoded.
+    Invalid,
+}
+
+/// A run of bytes of a message and what it encodes.
+pub struct Segment {
+    pub offset: usize,
+    pub len: usize,
+    pub part: Part,
+    pub description: String,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+    pos: usize,
+    segments: Vec<Segment>,
+}
+
+impl Reader<'_> {
+    fn push(&mut self, len: usize, part: Part, description: String) {
+        self.segments.push(Segment {
+            offset: self.pos,
+            len,
+            part,
+            description,
+        });
+        self.pos += len;
+    }
+
+    fn pad(&mut self, alignment: usize) {
+        let len = self.pos.next_multiple_of(alignment) - self.pos;
+        if len > 0 {
+            self.push(len, Part::Padding, format!("padding to {}", alignment));
+        }
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn text_at(&self, offset: usize, len: usize) -> Option<String> {
+        let bytes = self.data.get(offset..offset + len)?;
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Reads one header field, `None` if it runs past `end` or has a type no header field uses.
+    fn field(&mut self, end: usize) -> Option<()> {
+        self.pad(8);
+        let code = *self.data.get(self.pos)?;
+        self.push(
+            1,
+            Part::Field,
+            format!("field {} ({})", code, field_name(code)),
+        );
+
+        let signature_len = *self.data.get(self.pos)? as usize;
+        let signature = self.text_at(self.pos + 1, signature_len)?;
+        self.push(
+            signature_len + 2,
+            Part::Field,
+            format!("signature '{}'", signature),
+        );
+
+        match signature.as_str() {
+            "s" | "o" => {
+                self.pad(4);
+                let len = self.u32_at(self.pos)? as usize;
+                let text = self.text_at(self.pos + 4, len)?;
+                self.push(len + 5, Part::Field, format!("{} bytes: \"{}\"", len, text));
+            }
+            "g" => {
+                let len = *self.data.get(self.pos)? as usize;
+                let text = self.text_at(self.pos + 1, len)?;
+                self.push(len + 2, Part::Field, format!("'{}'", text));
+            }
+            "u" => {
+                self.pad(4);
+                let value = self.u32_at(self.pos)?;
+                self.push(4, Part::Field, value.to_string());
+            }
+            _ => return None,
+        }
+        (self.pos <= end).then_some(())
+    }
+}
+
+/// Breaks the wire format of a message down into its header fields, padding and body.
+/// Decoding stops at the first malformed part, and the rest is reported as one invalid run.
+pub fn annotate(data: &[u8]) -> Vec<Segment> {
+    let mut reader = Reader {
+        data,
+        little_endian: data.first() == Some(&b'l'),
+        pos: 0,
+        segments: Vec::new(),
+    };
+    if annotate_header(&mut reader).is_none() {
+        // Drop what was read of the broken part
+        while reader
+            .segments
+            .last()
+            .is_some_and(|s| s.offset + s.len > data.len())
+        {
+            reader.segments.pop();
+        }
+        reader.pos = reader.segments.last().map_or(0, |s| s.offset + s.len);
+    }
+    if reader.pos < data.len() {
+        let len = data.len() - reader.pos;
+        reader.push(len, Part::Invalid, "could not be decoded".to_string());
+    }
+    reader.segments
+}
+
+fn annotate_header(reader: &mut Reader) -> Option<()> {
+    let data = reader.data;
+    if data.len() < 16 {
+        return None;
+    }
+
+    let endianness = match data[0] {
+        b'l' => "little",
+        b'B' => "big",
+        _ => return None,
+    };
+    reader.push(
+        1,
+        Part::Fixed,
+        format!("endianness '{}' ({})", data[0] as char, endianness),
+    );
+    let message_type = match data[1] {
+        1 => "method call",
+        2 => "method return",
+        3 => "error",
+        4 => "signal",
+        _ => "invalid",
+    };
+    reader.push(
+        1,
+        Part::Fixed,
+        format!("type {} ({})", data[1], message_type),
+    );
+    reader.push(
+        1,
+        Part::Fixed,
+        format!("flags {:#04x}{}", data[2], flag_names(data[2])),
+    );
+    reader.push(1, Part::Fixed, format!("protocol version {}", data[3]));
+    let body_len = reader.u32_at(4)? as usize;
+    reader.push(4, Part::Fixed, format!("body length {}", body_len));
+    let serial = reader.u32_at(8)?;
+    reader.push(4, Part::Fixed, format!("serial {}", serial));
+    let fields_len = reader.u32_at(12)? as usize;
+    reader.push(
+        4,
+        Part::Fixed,
+        format!("header fields length {}", fields_len),
+    );
+
+    let fields_end = 16 + fields_len;
+    while reader.pos < fields_end {
+        reader.field(fields_end)?;
+    }
+    if reader.pos > data.len() {
+        return None;
+    }
+
+    reader.pad(8);
+    if reader.pos > data.len() {
+        return None;
+    }
+    let actual = data.len() - reader.pos;
+    let description = if actual == body_len {
+        format!("body start, {} bytes", actual)
+    } else {
+        format!("body start, {} bytes (header says {})", actual, body_len)
+    };
+    if actual > 0 {
+        reader.push(actual, Part::Body, description);
+    }
+    Some(())
+}
+
+fn field_name(code: u8) -> &'static str {
+    match code {
+        1 => "PATH",
+        2 => "INTERFACE",
+        3 => "MEMBER",
+        4 => "ERROR_NAME",
+        5 => "REPLY_SERIAL",
+        6 => "DESTINATION",
+        7 => "SENDER",
+        8 => "SIGNATURE",
+        9 => "UNIX_FDS",
+        _ => "unknown",
+    }
+}
+
+fn flag_names(flags: u8) -> String {
+    let names: Vec<&str> = [
+        (0x1, "NO_REPLY_EXPECTED"),
+        (0x2, "NO_AUTO_START"),
+        (0x4, "ALLOW_INTERACTIVE_AUTHORIZATION"),
+    ]
+    .iter()
+    .filter(|(bit, _)| flags & bit != 0)
+    .map(|(_, name)| *name)
+    .collect();
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", names.join("|"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call() -> zbus::Message {
+        zbus::Message::method_call("/org/example", "Ping")
+            .unwrap()
+            .interface("org.example.Echo")
+            .unwrap()
+            .destination("org.example.Service")
+            .unwrap()
+            .build(&("hello",))
+            .unwrap()
+    }
+
+    fn descriptions(segments: &[Segment]) -> Vec<&str> {
+        segments.iter().map(|s| s.description.as_str()).collect()
+    }
+
+    /// Every byte belongs to exactly one segment, in order.
+    fn assert_contiguous(segments: &[Segment], len: usize) {
+        let mut pos = 0;
+        for segment in segments {
+            assert_eq!(segment.offset, pos, "{}", segment.description);
+            assert!(segment.len > 0, "{}", segment.description);
+            pos += segment.len;
+        }
+        assert_eq!(pos, len);
+    }
+
+    #[test]
+    fn method_call_fields() {
+        let message = call();
+        let data: &[u8] = message.data();
+        let segments = annotate(data);
+        assert_contiguous(&segments, data.len());
+        assert!(segments.iter().all(|s| s.part != Part::Invalid));
+
+        let fixed: Vec<&Segment> = segments.iter().filter(|s| s.part == Part::Fixed).collect();
+        assert_eq!(
+            fixed.iter().map(|s| (s.offset, s.len)).collect::<Vec<_>>(),
+            vec![(0, 1), (1, 1), (2, 1), (3, 1), (4, 4), (8, 4), (12, 4)]
+        );
+        assert_eq!(fixed[0].description, "endianness 'l' (little)");
+        assert_eq!(fixed[1].description, "type 1 (method call)");
+
+        let descriptions = descriptions(&segments);
+        for expected in [
+            "field 1 (PATH)",
+            "signature 'o'",
+            "12 bytes: \"/org/example\"",
+            "field 2 (INTERFACE)",
+            "16 bytes: \"org.example.Echo\"",
+            "field 3 (MEMBER)",
+            "4 bytes: \"Ping\"",
+            "field 6 (DESTINATION)",
+            "19 bytes: \"org.example.Service\"",
+            "field 8 (SIGNATURE)",
+            "signature 'g'",
+            "'s'",
+        ] {
+            assert!(descriptions.contains(&expected), "missing {}", expected);
+        }
+
+        // A string field is its length, the text and a nul
+        let path = segments
+            .iter()
+            .find(|s| s.description == "12 bytes: \"/org/example\"")
+            .unwrap();
+        assert_eq!(path.offset % 4, 0);
+        assert_eq!(path.len, 4 + 12 + 1);
+
+        let body = segments.last().unwrap();
+        assert_eq!(body.part, Part::Body);
+        assert_eq!(body.offset % 8, 0);
+        assert_eq!(body.len, 4 + 5 + 1);
+        assert_eq!(body.description, "body start, 10 bytes");
+    }
+
+    #[test]
+    fn fields_are_padded_to_eight_bytes() {
+        let message = call();
+        let segments = annotate(message.data());
+        for segment in segments
+            .iter()
+            .filter(|s| s.description.starts_with("field "))
+        {
+            assert_eq!(segment.offset % 8, 0, "{}", segment.description);
+        }
+        assert!(segments
+            .iter()
+            .any(|s| s.part == Part::Padding && s.description == "padding to 8"));
+    }
+
+    #[test]
+    fn reply_serial_is_a_number() {
+        let call = call();
+        let reply = zbus::Message::method_return(&call.header())
+            .unwrap()
+            .build(&())
+            .unwrap();
+        let data: &[u8] = reply.data();
+        let segments = annotate(data);
+        assert_contiguous(&segments, data.len());
+        let serial = call.header().primary().serial_num().to_string();
+        let descriptions = descriptions(&segments);
+        assert!(descriptions.contains(&"type 2 (method return)"));
+        assert!(descriptions.contains(&"field 5 (REPLY_SERIAL)"));
+        assert!(descriptions.contains(&"signature 'u'"));
+        assert!(descriptions.contains(&serial.as_str()));
+        // No body, so nothing follows the last field
+        assert!(segments.iter().all(|s| s.part != Part::Body));
+    }
+
+    #[test]
+    fn truncated_message_ends_in_one_invalid_run() {
+        let message = call();
+        let data: &[u8] = message.data();
+        let full = annotate(data);
+        let member = full
+            .iter()
+            .find(|s| s.description == "4 bytes: \"Ping\"")
+            .unwrap();
+        // Cut in the middle of the member name
+        let cut = member.offset + 6;
+        let segments = annotate(&data[..cut]);
+        assert_contiguous(&segments, cut);
+
+        let invalid = segments.last().unwrap();
+        assert_eq!(invalid.part, Part::Invalid);
+        assert_eq!(invalid.description, "could not be decoded");
+        assert!(segments[..segments.len() - 1]
+            .iter()
+            .all(|s| s.part != Part::Invalid));
+        // What was decoded before the cut is kept as it was
+        let kept = &segments[..segments.len() - 1];
+        assert!(kept
+            .iter()
+            .zip(&full)
+            .all(|(a, b)| a.offset == b.offset && a.len == b.len));
+        assert!(descriptions(kept).contains(&"field 3 (MEMBER)"));
+    }
+
+    #[test]
+    fn short_or_unknown_data_is_invalid() {
+        let segments = annotate(&[b'l', 1, 0]);
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].part == Part::Invalid && segments[0].len == 3);
+
+        let mut data = call().data().to_vec();
+        data[0] = b'x';
+        let segments = annotate(&data);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].len, data.len());
+        assert!(annotate(&[]).is_empty());
+    }
+}